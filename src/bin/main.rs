@@ -1,18 +1,28 @@
 use ggez;
 use ggez::conf;
 use ggez::event;
+use ggez::input::gamepad::GamepadId;
 use ggez::{graphics, Context, ContextBuilder, GameResult};
 
 use graphics::Rect;
 use rr8::{
-    ui::{prompt::Prompt, topbar::TopBar, Scale, Ui},
+    cart::CART_EXT,
+    gamepad::PadEvent,
+    render::gpu::{Screen, Textures},
+    replay::Recording,
+    script::ScriptSystem,
+    ui::{
+        code_editor::CodeEditor, map_editor::MapEditor, music_editor::MusicEditor, prompt::Prompt,
+        sfx_editor::SfxEditor, sprite_editor::SpriteEditor, topbar::TopBar, Scale, Ui,
+        SCREEN_COLUMNS, SCREEN_ROWS,
+    },
     Game, GameMode, TILE_SIZE,
 };
 
-const WIN_W: f32 = 20. * TILE_SIZE as f32;
-const WIN_H: f32 = 20. * TILE_SIZE as f32;
+const WIN_W: f32 = SCREEN_COLUMNS as f32 * TILE_SIZE as f32;
+const WIN_H: f32 = SCREEN_ROWS as f32 * TILE_SIZE as f32;
 
-const WIN_SCALE: f32 = Scale::DEFAULT + Scale::DELTA * 2.;
+const WIN_SCALE: f32 = Scale::DEFAULT;
 
 enum MainMode {
     Ready,
@@ -23,7 +33,11 @@ struct MainState {
     game: Game,
     mode: MainMode,
     scale: f32,
-    dt: u32,
+    /// Size of the virtual screen the window was sized for
+    size: (f32, f32),
+    fullscreen: bool,
+    screen: Screen,
+    textures: Textures,
 }
 
 impl MainState {
@@ -33,13 +47,18 @@ impl MainState {
 
         let win = Rect::new(0., 0., WIN_W, WIN_H);
         let ui = Ui::new(ctx, filter_mode, win, scale)?;
-        let game = Game::new(ctx, ui)?;
+        let mut game = Game::new(ui)?;
+        game.load_history(ctx);
+        game.load_keymap(ctx);
 
         let s = MainState {
             game,
             mode,
             scale,
-            dt: 0,
+            size: (win.w, win.h),
+            fullscreen: false,
+            screen: Screen::new(ctx, win)?,
+            textures: Textures::default(),
         };
 
         Ok(s)
@@ -52,44 +71,57 @@ impl MainState {
 
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while ggez::timer::check_update_time(ctx, 60) {
-            self.dt += 1;
-        }
-
-        Ok(())
+        self.game.update(ctx)
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, (0, 0, 0).into());
-
         let scale = self.game.ui.get_scale();
-        if self.scale != scale {
+        let win = self.game.ui.get_win();
+        if self.scale != scale || self.size != (win.w, win.h) {
             self.scale = scale;
-            let (w, h) = (WIN_W * scale, WIN_H * scale);
-            graphics::set_mode(
-                ctx,
-                conf::WindowMode::default()
-                    .dimensions(w, h)
-                    .min_dimensions(w, h)
-                    .max_dimensions(w, h)
-                    .resizable(true),
-            )?;
+            self.size = (win.w, win.h);
+            let (w, h) = (win.w * scale, win.h * scale);
+            graphics::set_mode(ctx, window_mode(win.w, win.h).dimensions(w, h))?;
             graphics::set_screen_coordinates(ctx, Rect::new(0., 0., w, h))?;
             println!("Update Scale to {}", scale);
         }
 
-        // UI has its own delta time for animations and stuff
-        self.game.ui.dt = self.dt;
-
-        self.game.ui.draw_all(ctx, &self.game)?;
+        self.screen.draw(ctx, &mut self.textures, &self.game)?;
 
         graphics::present(ctx)?;
 
         Ok(())
     }
 
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let window = Rect::new(0., 0., width, height);
+        let result = graphics::set_screen_coordinates(ctx, window).map(|()| String::new());
+        self.game.report(result);
+    }
+
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
-        self.game.ui.set_mouse_coords((x, y));
+        let (x, y) = self.screen.letterbox(ctx).to_screen(x, y);
+        self.game.mouse_motion(x, y);
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.game.mouse_button(button, true);
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.game.mouse_button(button, false);
     }
 
     fn key_down_event(
@@ -108,6 +140,21 @@ impl event::EventHandler for MainState {
         match keycode {
             event::KeyCode::F1 => self.switch_mode(GameMode::Normal),
             event::KeyCode::F2 => self.switch_mode(GameMode::Prompt),
+            event::KeyCode::F3 => self.switch_mode(GameMode::Map),
+            event::KeyCode::F4 => self.switch_mode(GameMode::Sprite),
+            event::KeyCode::F5 => self.switch_mode(GameMode::Code),
+            event::KeyCode::F6 => self.switch_mode(GameMode::Sfx),
+            event::KeyCode::F7 => self.switch_mode(GameMode::Music),
+            event::KeyCode::F8 => self.game.run_command(ctx, "savestate"),
+            event::KeyCode::F9 => self.game.run_command(ctx, "loadstate"),
+            event::KeyCode::F10 => {
+                self.fullscreen = !self.fullscreen;
+                let result = graphics::set_fullscreen(ctx, fullscreen_type(self.fullscreen))
+                    .map(|()| String::new());
+                self.game.report(result);
+            }
+            event::KeyCode::F11 => self.game.run_command(ctx, "shot"),
+            event::KeyCode::F12 => self.game.run_command(ctx, "gif"),
             //event::KeyCode::Escape => ggez::event::quit(ctx),
             _ => {
                 is_done = false;
@@ -137,7 +184,23 @@ impl event::EventHandler for MainState {
             }
             GameMode::Prompt => {
                 match keycode {
-                    event::KeyCode::Return => self.game.run_prompt(),
+                    event::KeyCode::Return => self.game.run_prompt(ctx),
+                    event::KeyCode::Escape => self.game.mode = GameMode::Normal,
+                    _ => self.game.key_down(ctx, keycode, keymods),
+                };
+            }
+            GameMode::Map | GameMode::Sprite | GameMode::Sfx | GameMode::Music => {
+                match keycode {
+                    event::KeyCode::Escape => self.game.mode = GameMode::Normal,
+                    _ => self.game.key_down(ctx, keycode, keymods),
+                };
+            }
+            GameMode::Code => {
+                let searching = self.game.get_code_edit().get_search().is_some();
+                match keycode {
+                    event::KeyCode::Escape if searching => {
+                        self.game.get_code_edit_mut().end_search()
+                    }
                     event::KeyCode::Escape => self.game.mode = GameMode::Normal,
                     _ => self.game.key_down(ctx, keycode, keymods),
                 };
@@ -145,13 +208,64 @@ impl event::EventHandler for MainState {
         }
     }
 
+    fn key_up_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: event::KeyCode,
+        _keymods: event::KeyMods,
+    ) {
+        self.game.key_up(ctx, keycode);
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: event::Button,
+        id: GamepadId,
+    ) {
+        self.game
+            .gamepad_event(ctx, id, PadEvent::ButtonDown(button));
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, button: event::Button, id: GamepadId) {
+        self.game.gamepad_event(ctx, id, PadEvent::ButtonUp(button));
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        axis: event::Axis,
+        value: f32,
+        id: GamepadId,
+    ) {
+        self.game
+            .gamepad_event(ctx, id, PadEvent::Axis(axis, value));
+    }
+
     fn text_input_event(&mut self, _ctx: &mut Context, c: char) {
-        if let GameMode::Prompt = self.game.mode {
-            self.game.update_prompt(c);
+        match self.game.mode {
+            GameMode::Prompt => self.game.update_prompt(c),
+            GameMode::Code => self.game.update_code(c),
+            _ => {}
         }
     }
 }
 
+/// Resizable down to one virtual screen, the game is letterboxed in it
+fn window_mode(w: f32, h: f32) -> conf::WindowMode {
+    conf::WindowMode::default()
+        .min_dimensions(w, h)
+        .resizable(true)
+}
+
+fn fullscreen_type(fullscreen: bool) -> conf::FullscreenType {
+    if fullscreen {
+        conf::FullscreenType::Desktop
+    } else {
+        conf::FullscreenType::Windowed
+    }
+}
+
 pub fn main() -> GameResult {
     let scale = WIN_SCALE;
     let mut cb = ContextBuilder::new("rr8", "rr8")
@@ -160,7 +274,7 @@ pub fn main() -> GameResult {
                 .title("Retro Rust 8-bit IDE")
                 .vsync(true),
         )
-        .window_mode(conf::WindowMode::default().dimensions(WIN_W * scale, WIN_H * scale));
+        .window_mode(window_mode(WIN_W, WIN_H).dimensions(WIN_W * scale, WIN_H * scale));
 
     if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
         let mut path = std::path::PathBuf::from(manifest_dir);
@@ -176,6 +290,24 @@ pub fn main() -> GameResult {
     let topbar = TopBar::default();
     state.game.ui.add_system(prompt);
     state.game.ui.add_system(topbar);
+    state.game.ui.add_system(MapEditor);
+    state.game.ui.add_system(SpriteEditor::default());
+    state.game.ui.add_system(CodeEditor);
+    state.game.ui.add_system(SfxEditor);
+    state.game.ui.add_system(MusicEditor);
+
+    if let Some(path) = std::env::args().nth(1) {
+        if path.ends_with(&format!(".{}", CART_EXT)) {
+            state.game.load_cart(ctx, &path)?;
+            // a recording passed after the cart is replayed against it
+            match std::env::args().nth(2) {
+                Some(recording) => state.game.start_replay(Recording::load(recording)?)?,
+                None => state.game.run_cart()?,
+            }
+        } else {
+            state.game.set_script(ScriptSystem::load(path)?);
+        }
+    }
 
     event::run(ctx, event_loop, state)
 }
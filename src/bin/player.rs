@@ -0,0 +1,190 @@
+use ggez::conf;
+use ggez::event;
+use ggez::input::gamepad::GamepadId;
+use ggez::{graphics, Context, ContextBuilder, GameError, GameResult};
+
+use graphics::Rect;
+use rr8::{
+    bundle,
+    cart::Cart,
+    gamepad::PadEvent,
+    render::gpu::{Screen, Textures},
+    ui::{Scale, Ui, SCREEN_COLUMNS, SCREEN_ROWS},
+    Game, TILE_SIZE,
+};
+
+const WIN_SCALE: f32 = Scale::DEFAULT;
+
+/// Runs a single cart, without the prompt or the editors.
+struct PlayerState {
+    game: Game,
+    fullscreen: bool,
+    screen: Screen,
+    textures: Textures,
+}
+
+impl PlayerState {
+    fn new(ctx: &mut Context, cart: Cart, win: Rect, scale: f32) -> GameResult<PlayerState> {
+        let filter_mode = graphics::FilterMode::Nearest;
+
+        let mut ui = Ui::from_cart(&cart, filter_mode, win, scale)?;
+        ui.set_chrome(false);
+
+        let mut game = Game::new(ui)?;
+        game.load_keymap(ctx);
+        game.set_cart(ctx, cart)?;
+        game.run_cart()?;
+
+        Ok(PlayerState {
+            game,
+            fullscreen: false,
+            screen: Screen::new(ctx, win)?,
+            textures: Textures::default(),
+        })
+    }
+}
+
+impl event::EventHandler for PlayerState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        self.game.update(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.screen.draw(ctx, &mut self.textures, &self.game)?;
+
+        graphics::present(ctx)?;
+
+        Ok(())
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let window = Rect::new(0., 0., width, height);
+        if let Err(err) = graphics::set_screen_coordinates(ctx, window) {
+            println!("Could not resize: {}", err);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        let (x, y) = self.screen.letterbox(ctx).to_screen(x, y);
+        self.game.mouse_motion(x, y);
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.game.mouse_button(button, true);
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.game.mouse_button(button, false);
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: event::KeyCode,
+        keymods: event::KeyMods,
+        _repeat: bool,
+    ) {
+        match keycode {
+            event::KeyCode::Escape => event::quit(ctx),
+            event::KeyCode::F10 => {
+                self.fullscreen = !self.fullscreen;
+                let fullscreen = if self.fullscreen {
+                    conf::FullscreenType::Desktop
+                } else {
+                    conf::FullscreenType::Windowed
+                };
+                if let Err(err) = graphics::set_fullscreen(ctx, fullscreen) {
+                    println!("Could not switch fullscreen: {}", err);
+                }
+            }
+            _ => self.game.key_down(ctx, keycode, keymods),
+        }
+    }
+
+    fn key_up_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: event::KeyCode,
+        _keymods: event::KeyMods,
+    ) {
+        self.game.key_up(ctx, keycode);
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: event::Button,
+        id: GamepadId,
+    ) {
+        self.game
+            .gamepad_event(ctx, id, PadEvent::ButtonDown(button));
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, button: event::Button, id: GamepadId) {
+        self.game.gamepad_event(ctx, id, PadEvent::ButtonUp(button));
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        axis: event::Axis,
+        value: f32,
+        id: GamepadId,
+    ) {
+        self.game
+            .gamepad_event(ctx, id, PadEvent::Axis(axis, value));
+    }
+}
+
+pub fn main() -> GameResult {
+    // an exported game carries its cart, a bare player takes one as argument
+    let cart = match bundle::embedded_cart()? {
+        Some(cart) => cart,
+        None => match std::env::args().nth(1) {
+            Some(path) => Cart::load(path)?,
+            None => {
+                return Err(GameError::ResourceLoadError(format!(
+                    "usage: {} <cart>",
+                    bundle::PLAYER_NAME
+                )))
+            }
+        },
+    };
+
+    // the window is sized for the screen of the cart from the start
+    let (columns, rows) = cart.screen.unwrap_or((SCREEN_COLUMNS, SCREEN_ROWS));
+    let unit = TILE_SIZE as f32;
+    let win = Rect::new(0., 0., columns as f32 * unit, rows as f32 * unit);
+    Ui::validate_rect(win)?;
+
+    let scale = WIN_SCALE;
+    let (ctx, event_loop) = &mut ContextBuilder::new(bundle::PLAYER_NAME, "rr8")
+        .window_setup(
+            conf::WindowSetup::default()
+                .title("Rust Retro 8-bit")
+                .vsync(true),
+        )
+        .window_mode(
+            conf::WindowMode::default()
+                .dimensions(win.w * scale, win.h * scale)
+                .min_dimensions(win.w, win.h)
+                .resizable(true),
+        )
+        .build()?;
+
+    let state = &mut PlayerState::new(ctx, cart, win, scale)?;
+
+    event::run(ctx, event_loop, state)
+}
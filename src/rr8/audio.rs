@@ -0,0 +1,109 @@
+use std::fmt;
+
+use ggez::audio::{SoundData, SoundSource, Source};
+
+use crate::music::Track;
+use crate::synth::{to_wav, SAMPLE_RATE};
+use crate::*;
+
+enum MusicCmd {
+    Play(Track),
+    Stop,
+}
+
+/// The song playing: the intro first, then the loop once the intro is done
+struct Music {
+    intro: Option<Source>,
+    looped: Vec<f32>,
+    source: Option<Source>,
+}
+
+/// Sounds waiting to be played: systems don't get a `Context`, so what
+/// they play is queued and handed to ggez once per frame.
+#[derive(Default)]
+pub struct Audio {
+    queue: Vec<Vec<f32>>,
+    music_cmd: Option<MusicCmd>,
+    music: Option<Music>,
+    music_playing: bool,
+}
+
+impl fmt::Debug for Audio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Audio")
+            .field("queue", &self.queue.len())
+            .field("music_playing", &self.music_playing)
+            .finish()
+    }
+}
+
+impl Audio {
+    /// Queues samples rendered at `synth::SAMPLE_RATE`.
+    pub fn play(&mut self, samples: Vec<f32>) {
+        self.queue.push(samples);
+    }
+
+    /// Sounds queued since the last flush
+    pub fn queued(&self) -> &[Vec<f32>] {
+        &self.queue
+    }
+
+    /// Replaces the music playing.
+    pub fn play_music(&mut self, track: Track) {
+        self.music_cmd = Some(MusicCmd::Play(track));
+        self.music_playing = true;
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music_cmd = Some(MusicCmd::Stop);
+        self.music_playing = false;
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.music_playing
+    }
+
+    pub fn flush(&mut self, ctx: &mut Context) -> GameResult {
+        for samples in self.queue.drain(..) {
+            source(ctx, &samples)?.play_detached()?;
+        }
+
+        match self.music_cmd.take() {
+            // dropping the sources stops them
+            Some(MusicCmd::Stop) => self.music = None,
+            Some(MusicCmd::Play(track)) => {
+                let mut intro = source(ctx, &track.intro)?;
+                intro.play()?;
+                self.music = Some(Music {
+                    intro: Some(intro),
+                    looped: track.looped,
+                    source: None,
+                });
+            }
+            None => {}
+        }
+
+        if let Some(music) = self.music.as_mut() {
+            let intro_done = matches!(&music.intro, Some(intro) if !intro.playing());
+            if intro_done {
+                music.intro = None;
+                if music.looped.is_empty() {
+                    self.music = None;
+                    self.music_playing = false;
+                } else {
+                    let mut looped = source(ctx, &music.looped)?;
+                    looped.set_repeat(true);
+                    looped.play()?;
+                    music.source = Some(looped);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn source(ctx: &mut Context, samples: &[f32]) -> GameResult<Source> {
+    let data = SoundData::from_bytes(&to_wav(samples, SAMPLE_RATE));
+    Source::from_data(ctx, data)
+}
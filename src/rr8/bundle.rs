@@ -0,0 +1,86 @@
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+use ggez::GameError;
+
+use crate::*;
+
+/// Name of the player binary, built next to the IDE one
+pub const PLAYER_NAME: &str = "rr8-player";
+/// Ends a player with a cart appended
+const BUNDLE_MAGIC: &[u8; 8] = b"rr8cart\0";
+/// Cart length and magic
+const TRAILER_LEN: usize = 8 + BUNDLE_MAGIC.len();
+
+/// Appends a cart to a player executable.
+///
+/// The cart file follows the executable, then its length as a little
+/// endian u64 and `BUNDLE_MAGIC`, so the player finds it by reading the
+/// end of its own file.
+pub fn embed(player: &[u8], cart: &Cart) -> Vec<u8> {
+    let cart = cart.serialize().into_bytes();
+    let mut data = Vec::with_capacity(player.len() + cart.len() + TRAILER_LEN);
+
+    data.extend_from_slice(player);
+    data.extend_from_slice(&cart);
+    data.extend_from_slice(&(cart.len() as u64).to_le_bytes());
+    data.extend_from_slice(BUNDLE_MAGIC);
+
+    data
+}
+
+/// The cart appended by `embed`, `None` for plain executables.
+pub fn extract(data: &[u8]) -> GameResult<Option<Cart>> {
+    if data.len() < TRAILER_LEN || !data.ends_with(BUNDLE_MAGIC) {
+        return Ok(None);
+    }
+
+    let len_at = data.len() - TRAILER_LEN;
+    let len = u64::from_le_bytes(data[len_at..len_at + 8].try_into().unwrap()) as usize;
+    if len > len_at {
+        return Err(bundle_error("truncated cart"));
+    }
+
+    let text = std::str::from_utf8(&data[len_at - len..len_at])
+        .map_err(|_| bundle_error("cart is not text"))?;
+    Cart::parse(text).map(Some)
+}
+
+/// The cart bundled with the running executable, if any.
+pub fn embedded_cart() -> GameResult<Option<Cart>> {
+    extract(&std::fs::read(std::env::current_exe()?)?)
+}
+
+/// Where the player binary is expected: next to the running executable.
+pub fn player_path() -> GameResult<PathBuf> {
+    let name = format!("{}{}", PLAYER_NAME, std::env::consts::EXE_SUFFIX);
+    let path = std::env::current_exe()?.with_file_name(name);
+
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(bundle_error(&format!(
+            "no player at {}, build it with cargo build --bin {}",
+            path.display(),
+            PLAYER_NAME
+        )))
+    }
+}
+
+/// Writes a copy of the player running `cart` as soon as it starts.
+pub fn export(player: &Path, cart: &Cart, path: &Path) -> GameResult {
+    let player = std::fs::read(player)?;
+    std::fs::write(path, embed(&player, cart))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
+
+fn bundle_error(msg: &str) -> GameError {
+    GameError::ResourceLoadError(format!("bundle: {}", msg))
+}
@@ -0,0 +1,111 @@
+use crate::keymap::PLAYERS;
+use crate::*;
+
+/// Ticks a button is held before `btnp` repeats
+pub const REPEAT_DELAY: u32 = 15;
+/// Ticks between repeats of `btnp`
+pub const REPEAT_INTERVAL: u32 = 4;
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Button {
+    /// Held right now, as told by the key and controller events
+    down: bool,
+    /// Pressed since the last tick, so quick taps aren't lost
+    tapped: bool,
+    /// Ticks held so far, 0 when not held
+    ticks: u32,
+    released: bool,
+}
+
+/// The buttons of every player, as seen by the game on each tick.
+///
+/// Events press and release buttons whenever they come, `tick()` then
+/// samples them once per update so `btn`, `btnp` and `released` don't
+/// change during a tick. A button pressed and released between two ticks
+/// is held for one tick.
+#[derive(Clone, Debug)]
+pub struct ButtonState {
+    players: [[Button; Btn::ALL.len()]; PLAYERS],
+}
+
+impl Default for ButtonState {
+    fn default() -> Self {
+        Self {
+            players: [[Button::default(); Btn::ALL.len()]; PLAYERS],
+        }
+    }
+}
+
+impl ButtonState {
+    fn get(&self, player: usize, btn: Btn) -> Option<&Button> {
+        self.players
+            .get(player)
+            .map(|buttons| &buttons[btn as usize])
+    }
+
+    fn get_mut(&mut self, player: usize, btn: Btn) -> Option<&mut Button> {
+        self.players
+            .get_mut(player)
+            .map(|buttons| &mut buttons[btn as usize])
+    }
+
+    pub fn press(&mut self, player: usize, btn: Btn) {
+        if let Some(button) = self.get_mut(player, btn) {
+            button.down = true;
+            button.tapped = true;
+        }
+    }
+
+    pub fn release(&mut self, player: usize, btn: Btn) {
+        if let Some(button) = self.get_mut(player, btn) {
+            button.down = false;
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for button in self.players.iter_mut().flat_map(|b| b.iter_mut()) {
+            button.down = false;
+        }
+    }
+
+    /// Samples the events received since the last tick.
+    pub fn tick(&mut self) {
+        for button in self.players.iter_mut().flat_map(|b| b.iter_mut()) {
+            let held = button.down || button.tapped;
+            button.released = button.ticks > 0 && !held;
+            button.ticks = if held { button.ticks + 1 } else { 0 };
+            button.tapped = false;
+        }
+    }
+
+    /// Held during this tick
+    pub fn btn(&self, player: usize, btn: Btn) -> bool {
+        matches!(self.get(player, btn), Some(b) if b.ticks > 0)
+    }
+
+    /// Pressed on this tick, or held long enough to repeat
+    pub fn btnp(&self, player: usize, btn: Btn) -> bool {
+        match self.get(player, btn).map_or(0, |b| b.ticks) {
+            0 => false,
+            1 => true,
+            ticks if ticks > REPEAT_DELAY => {
+                matches!((ticks - 1 - REPEAT_DELAY) % REPEAT_INTERVAL, 0)
+            }
+            _ => false,
+        }
+    }
+
+    /// Let go on this tick
+    pub fn released(&self, player: usize, btn: Btn) -> bool {
+        matches!(self.get(player, btn), Some(b) if b.released)
+    }
+
+    /// Buttons held by a player during this tick
+    pub fn held(&self, player: usize) -> Vec<Btn> {
+        Btn::ALL
+            .iter()
+            .copied()
+            .filter(|btn| self.btn(player, *btn))
+            .collect()
+    }
+}
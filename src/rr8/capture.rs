@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::GameError;
+use gif::SetParameter;
+
+use crate::render::soft::Framebuffer;
+use crate::*;
+
+/// Ticks between two frames of a GIF
+pub const GIF_FRAME_TICKS: u32 = 3;
+/// GIF delays are in 1/100th of a second
+const GIF_DELAY: u16 = (100 * GIF_FRAME_TICKS / tick::TICK_RATE) as u16;
+/// Length of the GIFs started without one
+pub const GIF_SECONDS: f32 = 5.;
+/// Frames are kept in memory until saved, a minute is about 70MB
+pub const MAX_GIF_SECONDS: f32 = 60.;
+
+/// The colors of a palette as the RGB triplets of a GIF palette
+pub fn gif_palette(palette: &Palette) -> Vec<u8> {
+    Pal::ALL
+        .iter()
+        .flat_map(|pal| {
+            let (r, g, b) = palette.rgb(*pal);
+            vec![r, g, b]
+        })
+        .collect()
+}
+
+/// Index in `Pal::ALL` of the color of a palette closest to a pixel.
+pub fn pal_index(palette: &Palette, pixel: [u8; 4]) -> u8 {
+    palette.nearest(pixel) as u8
+}
+
+/// Frames of the game captured once every `GIF_FRAME_TICKS` ticks, for a
+/// number of seconds of game time.
+#[derive(Debug)]
+pub struct GifRecorder {
+    ticks: u32,
+    length: u32,
+    width: u16,
+    height: u16,
+    frames: Vec<Vec<u8>>,
+    palette: Palette,
+}
+
+impl GifRecorder {
+    pub fn new(seconds: f32) -> Self {
+        Self::with_palette(seconds, Palette::default())
+    }
+
+    /// Records frames drawn with a palette other than the default one.
+    pub fn with_palette(seconds: f32, palette: Palette) -> Self {
+        Self {
+            ticks: 0,
+            length: (seconds.clamp(0., MAX_GIF_SECONDS) * tick::TICK_RATE as f32).round() as u32,
+            width: 0,
+            height: 0,
+            frames: Vec::new(),
+            palette,
+        }
+    }
+
+    /// Whether the frame of the current tick goes in the GIF
+    pub fn wants_frame(&self) -> bool {
+        !self.is_done() && matches!(self.ticks % GIF_FRAME_TICKS, 0)
+    }
+
+    pub fn push(&mut self, fb: &Framebuffer) {
+        self.width = fb.width();
+        self.height = fb.height();
+        self.frames.push(
+            fb.pixels()
+                .chunks(4)
+                .map(|p| pal_index(&self.palette, [p[0], p[1], p[2], p[3]]))
+                .collect(),
+        );
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.ticks >= self.length
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// An animated GIF of the frames, looping forever.
+    pub fn encode(&self) -> GameResult<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(
+                &mut data,
+                self.width,
+                self.height,
+                &gif_palette(&self.palette),
+            )?;
+            encoder.set(gif::Repeat::Infinite)?;
+
+            for pixels in self.frames.iter() {
+                let frame = gif::Frame {
+                    width: self.width,
+                    height: self.height,
+                    delay: GIF_DELAY,
+                    buffer: pixels.as_slice().into(),
+                    ..gif::Frame::default()
+                };
+                encoder.write_frame(&frame)?;
+            }
+        }
+
+        Ok(data)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> GameResult {
+        if self.frames.is_empty() {
+            return Err(GameError::RenderError("no frames captured".to_string()));
+        }
+        std::fs::write(path, self.encode()?)?;
+
+        Ok(())
+    }
+}
+
+/// File name for captures saved without one, like `rr8-1600000000.png`
+pub fn default_name(ext: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    PathBuf::from(format!("rr8-{}.{}", secs, ext))
+}
@@ -0,0 +1,320 @@
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::Path;
+
+use ggez::filesystem;
+use ggez::GameError;
+
+use crate::*;
+
+pub const CART_EXT: &str = "rr8";
+pub const CART_VERSION: u32 = 1;
+const CART_HEADER: &str = "rr8 cartridge";
+
+/// Bytes per line in hex encoded sections
+const HEX_LINE: usize = 64;
+
+/// A palette color replaced by a cart, with its RGB value
+pub type PalColor = (Pal, (u8, u8, u8));
+
+/// A game bundled in a single text file.
+///
+/// The file starts with a header and a version line, followed by sections
+/// introduced by a `__name__` line. Code is stored verbatim after a line
+/// giving its length in bytes, so it can hold anything, binary data (PNG
+/// images, map and audio) is hex encoded:
+///
+/// ```text
+/// rr8 cartridge
+/// version 1
+/// __code__ 35
+/// fn draw() { text("hi", 1, 1, 9); }
+/// __tiles__
+/// 89504e470d0a1a0a...
+/// ```
+///
+/// Missing sections fall back to the default resources. A `__screen__`
+/// section holds the size of the virtual screen in tiles, like `24 20`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cart {
+    pub code: String,
+    pub tiles: Option<Vec<u8>>,
+    pub tiles_alt: Option<Vec<u8>>,
+    pub font: Option<Vec<u8>>,
+    pub map: Vec<u8>,
+    pub palette: Vec<PalColor>,
+    /// Columns and rows of tiles of the screen, the default size if `None`
+    pub screen: Option<(u16, u16)>,
+    pub sfx: Vec<u8>,
+    pub music: Vec<u8>,
+}
+
+impl Cart {
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> GameResult {
+        std::fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    /// Fills the missing images with the ones shipped in `resources/` so
+    /// the saved file doesn't depend on them.
+    pub fn fill_from_resources(&mut self, ctx: &mut Context) -> GameResult {
+        if self.tiles.is_none() {
+            self.tiles = Some(read_resource(ctx, TILESET_PATH)?);
+        }
+        if self.tiles_alt.is_none() {
+            self.tiles_alt = Some(read_resource(ctx, TILESET_ALT_PATH)?);
+        }
+        if self.font.is_none() {
+            self.font = Some(read_resource(ctx, FONT_PATH)?);
+        }
+
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> GameResult<Self> {
+        let (header, rest) = next_line(text);
+        if header.trim() != CART_HEADER {
+            return Err(cart_error("missing header"));
+        }
+
+        let (version, mut rest) = next_line(rest);
+        let version = version
+            .trim()
+            .strip_prefix("version ")
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| cart_error("missing version"))?;
+        if version > CART_VERSION {
+            return Err(cart_error(&format!(
+                "version {} is newer than {}",
+                version, CART_VERSION
+            )));
+        }
+
+        let mut cart = Self::default();
+        let mut section: Option<&str> = None;
+        let mut body: Vec<&str> = Vec::new();
+
+        while !rest.is_empty() {
+            let (line, next) = next_line(rest);
+            rest = next;
+
+            if let Some((name, length)) = section_header(line) {
+                if let Some(current) = section {
+                    cart.read_section(current, &body)?;
+                }
+                body.clear();
+                section = match (name, length) {
+                    ("code", Some(length)) => {
+                        let (code, next) = raw_section(rest, length).ok_or_else(|| {
+                            cart_error(&format!("code shorter than {} bytes", length))
+                        })?;
+                        cart.code = code.to_string();
+                        rest = next;
+                        None
+                    }
+                    ("code", None) => return Err(cart_error("missing code length")),
+                    (_, Some(_)) => {
+                        return Err(cart_error(&format!("unexpected length for {:?}", name)))
+                    }
+                    (_, None) => Some(name),
+                };
+            } else if section.is_some() {
+                body.push(line);
+            } else if !line.trim().is_empty() {
+                return Err(cart_error(&format!("unexpected line {:?}", line)));
+            }
+        }
+        if let Some(current) = section {
+            cart.read_section(current, &body)?;
+        }
+
+        Ok(cart)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "{}", CART_HEADER).unwrap();
+        writeln!(out, "version {}", CART_VERSION).unwrap();
+
+        writeln!(out, "__code__ {}", self.code.len()).unwrap();
+        writeln!(out, "{}", self.code).unwrap();
+
+        let images = [
+            ("tiles", &self.tiles),
+            ("tiles_alt", &self.tiles_alt),
+            ("font", &self.font),
+        ];
+        for (name, data) in images.iter() {
+            if let Some(data) = data {
+                write_hex_section(&mut out, name, data);
+            }
+        }
+
+        if !self.map.is_empty() {
+            write_hex_section(&mut out, "map", &self.map);
+        }
+
+        if !self.palette.is_empty() {
+            writeln!(out, "__pal__").unwrap();
+            for (pal, (r, g, b)) in self.palette.iter() {
+                writeln!(out, "{} {:02x}{:02x}{:02x}", *pal as usize, r, g, b).unwrap();
+            }
+        }
+
+        if let Some((columns, rows)) = self.screen {
+            writeln!(out, "__screen__").unwrap();
+            writeln!(out, "{} {}", columns, rows).unwrap();
+        }
+
+        if !self.sfx.is_empty() {
+            write_hex_section(&mut out, "sfx", &self.sfx);
+        }
+
+        if !self.music.is_empty() {
+            write_hex_section(&mut out, "music", &self.music);
+        }
+
+        out
+    }
+
+    fn read_section(&mut self, name: &str, body: &[&str]) -> GameResult {
+        match name {
+            "tiles" => self.tiles = Some(decode_hex(body)?),
+            "tiles_alt" => self.tiles_alt = Some(decode_hex(body)?),
+            "font" => self.font = Some(decode_hex(body)?),
+            "map" => self.map = decode_hex(body)?,
+            "pal" => self.palette = decode_palette(body)?,
+            "screen" => self.screen = Some(decode_screen(body)?),
+            "sfx" => self.sfx = decode_hex(body)?,
+            "music" => self.music = decode_hex(body)?,
+            _ => return Err(cart_error(&format!("unknown section {:?}", name))),
+        }
+
+        Ok(())
+    }
+}
+
+fn section_name(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    if line.len() > 4 && line.starts_with("__") && line.ends_with("__") {
+        Some(&line[2..line.len() - 2])
+    } else {
+        None
+    }
+}
+
+/// Name of the section a `__name__` or `__name__ <length>` line starts
+pub(crate) fn section_header(line: &str) -> Option<(&str, Option<usize>)> {
+    let line = line.trim_end();
+    match line.find(' ') {
+        Some(i) => {
+            let length = line[i + 1..].parse::<usize>().ok()?;
+            Some((section_name(&line[..i])?, Some(length)))
+        }
+        None => Some((section_name(line)?, None)),
+    }
+}
+
+/// Splits `length` bytes of raw text from the rest, dropping the newline
+/// that puts the next section on its own line.
+pub(crate) fn raw_section(text: &str, length: usize) -> Option<(&str, &str)> {
+    let raw = text.get(..length)?;
+    let rest = &text[length..];
+
+    Some((raw, rest.strip_prefix('\n').unwrap_or(rest)))
+}
+
+/// The first line of a text without its line ending, and the text after it
+pub(crate) fn next_line(text: &str) -> (&str, &str) {
+    let (line, rest) = match text.find('\n') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => (text, ""),
+    };
+
+    (line.strip_suffix('\r').unwrap_or(line), rest)
+}
+
+fn write_hex_section(out: &mut String, name: &str, data: &[u8]) {
+    writeln!(out, "__{}__", name).unwrap();
+    for chunk in data.chunks(HEX_LINE) {
+        for byte in chunk {
+            write!(out, "{:02x}", byte).unwrap();
+        }
+        out.push('\n');
+    }
+}
+
+fn decode_hex(body: &[&str]) -> GameResult<Vec<u8>> {
+    let mut data = Vec::new();
+
+    for line in body {
+        let line = line.trim();
+        if line.len() % 2 != 0 {
+            return Err(cart_error("odd number of hex digits"));
+        }
+        // by bytes, a character that isn't hex may be wider than one
+        for pair in line.as_bytes().chunks(2) {
+            let byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| {
+                    cart_error(&format!("bad hex {:?}", String::from_utf8_lossy(pair)))
+                })?;
+            data.push(byte);
+        }
+    }
+
+    Ok(data)
+}
+
+fn decode_palette(body: &[&str]) -> GameResult<Vec<PalColor>> {
+    let mut palette = Vec::new();
+
+    for line in body.iter().filter(|l| !l.trim().is_empty()) {
+        let mut parts = line.split_whitespace();
+        let pal = parts
+            .next()
+            .and_then(|i| i.parse::<usize>().ok())
+            .and_then(Pal::from_index)
+            .ok_or_else(|| cart_error(&format!("bad palette entry {:?}", line)))?;
+        let rgb = parts
+            .next()
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| cart_error(&format!("bad palette color {:?}", line)))?;
+
+        palette.push((pal, ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)));
+    }
+
+    Ok(palette)
+}
+
+fn decode_screen(body: &[&str]) -> GameResult<(u16, u16)> {
+    let line = body.iter().find(|l| !l.trim().is_empty()).unwrap_or(&"");
+    let size = line
+        .split_whitespace()
+        .map(|n| n.parse::<u16>().ok())
+        .collect::<Option<Vec<_>>>();
+
+    match size.as_deref() {
+        Some(&[columns, rows]) => Ok((columns, rows)),
+        _ => Err(cart_error(&format!("bad screen size {:?}", line))),
+    }
+}
+
+fn read_resource(ctx: &mut Context, path: &str) -> GameResult<Vec<u8>> {
+    let mut data = Vec::new();
+    filesystem::open(ctx, path)?.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+fn cart_error(msg: &str) -> GameError {
+    GameError::ResourceLoadError(format!("cart: {}", msg))
+}
@@ -0,0 +1,453 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::script::{CALLBACKS, FUNCTIONS};
+use crate::*;
+
+/// Size of the code editor text box in characters on the smallest screen,
+/// the game sizes it to the screen
+pub const CODE_ROWS: usize = 14;
+pub const CODE_COLUMNS: usize = 36;
+/// Characters taken by line numbers on the left of the text
+pub const GUTTER: usize = 4;
+
+const TAB: &str = "    ";
+
+const KEYWORDS: &[&str] = &[
+    "let", "const", "fn", "if", "else", "while", "loop", "for", "in", "do", "until", "return",
+    "break", "continue", "throw", "switch", "import", "export", "as", "private", "true", "false",
+    "this",
+];
+
+/// A position in the text, in characters
+#[derive(Copy, Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Pos {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// State of the code editor: the lines of the script, cursor, selection
+/// and search.
+///
+/// The selection goes from `anchor` to the cursor, the clipboard is kept
+/// here since it's only shared with the editor itself.
+#[derive(Debug)]
+pub struct CodeEdit {
+    lines: Vec<String>,
+    cursor: Pos,
+    anchor: Option<Pos>,
+    /// First line and column shown in the text box
+    scroll: Pos,
+    /// Size of the text box in characters, line numbers included
+    rows: usize,
+    columns: usize,
+    clipboard: String,
+    search: Option<String>,
+}
+
+impl Default for CodeEdit {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl CodeEdit {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text
+                .split('\n')
+                .map(|l| l.trim_end_matches('\r').to_string())
+                .collect(),
+            cursor: Pos::default(),
+            anchor: None,
+            scroll: Pos::default(),
+            rows: CODE_ROWS,
+            columns: CODE_COLUMNS,
+            clipboard: String::new(),
+            search: None,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Resizes the text box, scrolling so the cursor stays visible.
+    pub fn set_size(&mut self, rows: usize, columns: usize) {
+        self.rows = rows.max(1);
+        self.columns = columns.max(GUTTER + 1);
+        self.scroll_to_cursor();
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn cursor(&self) -> Pos {
+        self.cursor
+    }
+
+    pub fn scroll(&self) -> Pos {
+        self.scroll
+    }
+
+    /// Start and end of the selection, in order
+    pub fn selection(&self) -> Option<(Pos, Pos)> {
+        match self.anchor {
+            Some(anchor) if anchor < self.cursor => Some((anchor, self.cursor)),
+            Some(anchor) if anchor > self.cursor => Some((self.cursor, anchor)),
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        if start.line == end.line {
+            return Some(slice(&self.lines[start.line], start.column, end.column).to_string());
+        }
+
+        let mut text = slice(&self.lines[start.line], start.column, usize::MAX).to_string();
+        for line in &self.lines[start.line + 1..end.line] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text.push('\n');
+        text.push_str(slice(&self.lines[end.line], 0, end.column));
+
+        Some(text)
+    }
+
+    fn line_len(&self, line: usize) -> usize {
+        self.lines[line].chars().count()
+    }
+
+    /// Moves the cursor, extending the selection from where it was when
+    /// `select` is set.
+    pub fn move_to(&mut self, pos: Pos, select: bool) {
+        if !select {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+
+        let line = pos.line.min(self.lines.len() - 1);
+        self.cursor = Pos::new(line, pos.column.min(self.line_len(line)));
+    }
+
+    /// Moves by characters, left and right wrap around line ends.
+    pub fn move_by(&mut self, dx: isize, dy: isize, select: bool) {
+        let Pos { line, column } = self.cursor;
+        let pos = if dx < 0 && column == 0 && line > 0 {
+            Pos::new(line - 1, self.line_len(line - 1))
+        } else if dx > 0 && column == self.line_len(line) && line + 1 < self.lines.len() {
+            Pos::new(line + 1, 0)
+        } else {
+            let line = (line as isize + dy).max(0) as usize;
+            Pos::new(line, (column as isize + dx).max(0) as usize)
+        };
+
+        self.move_to(pos, select);
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.move_to(Pos::new(self.cursor.line, 0), select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        self.move_to(Pos::new(self.cursor.line, usize::MAX), select);
+    }
+
+    pub fn page(&mut self, pages: isize, select: bool) {
+        self.move_by(0, pages * self.rows as isize, select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.move_to(Pos::default(), false);
+        self.move_to(Pos::new(usize::MAX, usize::MAX), true);
+    }
+
+    /// Removes the selected text, returns whether there was any.
+    fn delete_selection(&mut self) -> bool {
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                self.anchor = None;
+                return false;
+            }
+        };
+
+        let tail = slice(&self.lines[end.line], end.column, usize::MAX).to_string();
+        let head = slice(&self.lines[start.line], 0, start.column).to_string();
+        self.lines.drain(start.line + 1..=end.line);
+        self.lines[start.line] = head + tail.as_str();
+        self.anchor = None;
+        self.cursor = start;
+
+        true
+    }
+
+    /// Inserts text at the cursor, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+
+        let Pos { line, column } = self.cursor;
+        let i = byte_index(&self.lines[line], column);
+        let tail = self.lines[line].split_off(i);
+
+        let mut parts = text.split('\n');
+        self.lines[line].push_str(parts.next().unwrap_or(""));
+        let mut last = line;
+        for part in parts {
+            last += 1;
+            self.lines.insert(last, part.to_string());
+        }
+
+        let column = self.line_len(last);
+        self.lines[last].push_str(&tail);
+        self.cursor = Pos::new(last, column);
+    }
+
+    /// Breaks the line, keeping the indentation of the current one.
+    pub fn newline(&mut self) {
+        let indent: String = self.lines[self.cursor.line]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.insert(&format!("\n{}", indent));
+    }
+
+    pub fn tab(&mut self) {
+        self.insert(TAB);
+    }
+
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor != Pos::default() {
+            self.move_by(-1, 0, true);
+            self.delete_selection();
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if !self.delete_selection() {
+            self.move_by(1, 0, true);
+            self.delete_selection();
+        }
+    }
+
+    pub fn copy(&mut self) {
+        if let Some(text) = self.selected_text() {
+            self.clipboard = text;
+        }
+    }
+
+    pub fn cut(&mut self) {
+        self.copy();
+        self.delete_selection();
+    }
+
+    pub fn paste(&mut self) {
+        let text = self.clipboard.clone();
+        self.insert(&text);
+    }
+
+    /// Text typed in the editor, in the search field while searching.
+    pub fn input(&mut self, c: char) {
+        if let Some(query) = self.search.as_mut() {
+            if c == 0x08 as char {
+                query.pop();
+            } else if !c.is_ascii_control() {
+                query.push(c);
+            }
+            return;
+        }
+
+        if !c.is_ascii_control() {
+            self.insert(&c.to_string());
+        } else if c == 0x7F as char {
+            self.delete();
+        } else if c == 0x08 as char {
+            self.backspace();
+        }
+    }
+
+    pub fn get_search(&self) -> Option<&str> {
+        self.search.as_deref()
+    }
+
+    /// Opens the search field with the selected text, if any.
+    pub fn start_search(&mut self) {
+        let query = self
+            .selected_text()
+            .filter(|text| !text.contains('\n'))
+            .unwrap_or_default();
+        self.search = Some(query);
+    }
+
+    pub fn end_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Selects the next match of the search after the cursor, wrapping
+    /// around the end of the text. Returns whether there was one.
+    pub fn find_next(&mut self) -> bool {
+        let query = match self.search.as_deref() {
+            Some(query) if !query.is_empty() => query.to_string(),
+            _ => return false,
+        };
+
+        let Pos { line, column } = self.cursor;
+        let count = self.lines.len();
+        // the cursor line is searched again last, for matches before it
+        for i in 0..=count {
+            let l = (line + i) % count;
+            let from = if i == 0 { column } else { 0 };
+            let text = &self.lines[l];
+            let rest = &text[byte_index(text, from)..];
+            let found = rest.find(&query).map(|b| from + rest[..b].chars().count());
+
+            if let Some(start) = found {
+                self.move_to(Pos::new(l, start), false);
+                self.move_to(Pos::new(l, start + query.chars().count()), true);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Scrolls the text box so the cursor is visible.
+    pub fn scroll_to_cursor(&mut self) {
+        let (rows, columns) = (self.rows, self.columns - GUTTER);
+        let Pos { line, column } = self.cursor;
+
+        if line < self.scroll.line {
+            self.scroll.line = line;
+        } else if line >= self.scroll.line + rows {
+            self.scroll.line = line + 1 - rows;
+        }
+        if column < self.scroll.column {
+            self.scroll.column = column;
+        } else if column >= self.scroll.column + columns {
+            self.scroll.column = column + 1 - columns;
+        }
+    }
+}
+
+/// Kinds of tokens of the scripting language, for coloring
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Token {
+    Text,
+    Keyword,
+    Function,
+    Number,
+    String,
+    Comment,
+    Symbol,
+}
+
+impl Token {
+    pub fn color(self) -> Pal {
+        match self {
+            Token::Text => Pal::White,
+            Token::Keyword => Pal::Pink,
+            Token::Function => Pal::Blue,
+            Token::Number => Pal::Orange,
+            Token::String => Pal::Green,
+            Token::Comment => Pal::Gray,
+            Token::Symbol => Pal::LightGray,
+        }
+    }
+}
+
+/// Splits a line of script into tokens. Lines are highlighted on their
+/// own, so block comments and strings spanning lines aren't recognized.
+pub fn highlight(line: &str) -> Vec<(Token, &str)> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let (token, end) = if line[start..].starts_with("//") {
+            (Token::Comment, line.len())
+        } else if c == '"' || c == '\'' || c == '`' {
+            let mut escaped = false;
+            let mut end = line.len();
+            for (i, next) in &mut chars {
+                if next == c && !escaped {
+                    end = i + next.len_utf8();
+                    break;
+                }
+                escaped = next == '\\' && !escaped;
+            }
+            (Token::String, end)
+        } else if c.is_ascii_digit() {
+            let end = skip_while(&mut chars, line, |c| {
+                c.is_alphanumeric() || c == '.' || c == '_'
+            });
+            (Token::Number, end)
+        } else if c.is_alphabetic() || c == '_' {
+            let end = skip_while(&mut chars, line, |c| c.is_alphanumeric() || c == '_');
+            let word = &line[start..end];
+            let token = if KEYWORDS.contains(&word) {
+                Token::Keyword
+            } else if FUNCTIONS.contains(&word) || CALLBACKS.contains(&word) {
+                Token::Function
+            } else {
+                Token::Text
+            };
+            (token, end)
+        } else if c.is_whitespace() {
+            (
+                Token::Text,
+                skip_while(&mut chars, line, char::is_whitespace),
+            )
+        } else {
+            (Token::Symbol, start + c.len_utf8())
+        };
+
+        tokens.push((token, &line[start..end]));
+        if token == Token::Comment {
+            break;
+        }
+    }
+
+    tokens
+}
+
+/// Consumes the characters matching `f`, returns the offset after them.
+fn skip_while(chars: &mut Peekable<CharIndices>, line: &str, f: impl Fn(char) -> bool) -> usize {
+    while let Some(&(i, c)) = chars.peek() {
+        if !f(c) {
+            return i;
+        }
+        chars.next();
+    }
+
+    line.len()
+}
+
+/// Byte offset of a character column, the end of the line past it
+fn byte_index(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+/// Characters from `start` to `end` of a line
+fn slice(line: &str, start: usize, end: usize) -> &str {
+    &line[byte_index(line, start)..byte_index(line, end)]
+}
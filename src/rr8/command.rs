@@ -0,0 +1,638 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use ggez::GameError;
+
+use crate::bundle;
+use crate::capture;
+use crate::complete::Completion;
+use crate::ui::{palette, Scale};
+use crate::*;
+
+pub type CommandFn = dyn Fn(&mut Context, &mut Game, &[String]) -> GameResult<String>;
+
+/// A named command runnable from the prompt, the returned string is shown
+/// as its output.
+#[derive(Clone)]
+pub struct Command {
+    pub usage: String,
+    pub help: String,
+    pub completion: Completion,
+    run: Rc<CommandFn>,
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("usage", &self.usage)
+            .field("help", &self.help)
+            .field("completion", &self.completion)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Commands {
+    commands: BTreeMap<String, Command>,
+}
+
+impl Commands {
+    pub fn with_builtins() -> Self {
+        let mut commands = Self::default();
+
+        commands.register("help", "help [command]", "list commands", help);
+        commands.register("ls", "ls [path]", "list files", ls);
+        commands.register("cd", "cd [path]", "change directory", cd);
+        commands.register("clear", "clear", "clear the console", clear);
+        commands.register("load", "load <cart>", "load a cart", load);
+        commands.register("save", "save [cart]", "save the cart", save);
+        commands.register("run", "run", "run the cart", run);
+        commands.register("wav", "wav <file>", "export the song as wav", wav);
+        commands.register(
+            "scale",
+            "scale [up|down|min|max|default]",
+            "change window scale",
+            scale,
+        );
+        commands.register(
+            "screen",
+            "screen [columns rows]",
+            "show or set the screen size",
+            screen,
+        );
+        commands.register("pal", "pal [color] [rrggbb]", "show or set colors", pal);
+        commands.register(
+            "palette",
+            "palette <file.gpl|file.hex|reset>",
+            "import the cart palette",
+            palette,
+        );
+        commands.register("speed", "speed [factor]", "slow down or speed up", speed);
+        commands.register(
+            "frameskip",
+            "frameskip [steps]",
+            "max updates per frame",
+            frameskip,
+        );
+        commands.register("pause", "pause", "pause or resume updates", pause);
+        commands.register("step", "step [steps]", "update while paused", step);
+        commands.register(
+            "key",
+            "key [key|reset] [button|none] [player]",
+            "show or bind keys",
+            key,
+        );
+        commands.register(
+            "record",
+            "record [file]",
+            "record inputs, again to stop",
+            record,
+        );
+        commands.register("replay", "replay [file]", "replay recorded inputs", replay);
+        commands.register(
+            "savestate",
+            "savestate [file]",
+            "snapshot the running cart",
+            savestate,
+        );
+        commands.register(
+            "loadstate",
+            "loadstate [file]",
+            "resume a snapshot",
+            loadstate,
+        );
+        commands.register("shot", "shot [file]", "save a screenshot", shot);
+        commands.register(
+            "export",
+            "export <file>",
+            "export a standalone game",
+            export,
+        );
+        commands.register(
+            "gif",
+            "gif [seconds] [file]",
+            "capture a gif, again to stop",
+            gif,
+        );
+
+        commands.set_completion("help", Completion::Commands);
+        let paths = [
+            "ls",
+            "cd",
+            "load",
+            "save",
+            "wav",
+            "record",
+            "replay",
+            "savestate",
+            "loadstate",
+            "shot",
+            "export",
+            "palette",
+        ];
+        for name in paths.iter() {
+            commands.set_completion(name, Completion::Paths);
+        }
+        commands.set_completion(
+            "scale",
+            Completion::Words(
+                ["up", "down", "min", "max", "default"]
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect(),
+            ),
+        );
+
+        commands
+    }
+
+    /// Adds a command, replacing any other command with the same name.
+    pub fn register<F>(&mut self, name: &str, usage: &str, help: &str, f: F)
+    where
+        F: Fn(&mut Context, &mut Game, &[String]) -> GameResult<String> + 'static,
+    {
+        self.commands.insert(
+            name.to_string(),
+            Command {
+                usage: usage.to_string(),
+                help: help.to_string(),
+                completion: Completion::None,
+                run: Rc::new(f),
+            },
+        );
+    }
+
+    /// Sets what the arguments of a registered command complete to.
+    pub fn set_completion(&mut self, name: &str, completion: Completion) {
+        if let Some(cmd) = self.commands.get_mut(name) {
+            cmd.completion = completion;
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    /// Parses and runs a prompt line, returning the command output.
+    pub fn run_line(ctx: &mut Context, game: &mut Game, line: &str) -> GameResult<String> {
+        let args = parse_args(line)?;
+        let (name, args) = match args.split_first() {
+            Some(split) => split,
+            None => return Ok(String::new()),
+        };
+
+        // the command is cloned out of the registry so it can borrow the game
+        let run = match game.commands.get(name) {
+            Some(cmd) => cmd.run.clone(),
+            None => return Err(error(&format!("unknown command {}", name))),
+        };
+
+        run(ctx, game, args)
+    }
+}
+
+/// Splits a line in words, single and double quotes group words together
+/// and a backslash escapes the next char.
+pub fn parse_args(line: &str) -> GameResult<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => match chars.next() {
+                Some(c) => arg.get_or_insert_with(String::new).push(c),
+                None => return Err(error("trailing \\")),
+            },
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => arg.get_or_insert_with(String::new).push(c),
+            ('"', None) | ('\'', None) => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (c, None) if c.is_whitespace() => {
+                if let Some(arg) = arg.take() {
+                    args.push(arg);
+                }
+            }
+            (c, None) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(error(&format!("unclosed {}", q)));
+    }
+    if let Some(arg) = arg {
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+pub fn error(msg: &str) -> GameError {
+    GameError::ConfigError(msg.to_string())
+}
+
+/// Message for errors shown in the prompt, without the variant noise.
+pub fn error_message(err: &GameError) -> String {
+    match err {
+        GameError::ConfigError(s)
+        | GameError::FilesystemError(s)
+        | GameError::ResourceLoadError(s)
+        | GameError::RenderError(s)
+        | GameError::AudioError(s)
+        | GameError::WindowError(s) => s.clone(),
+        GameError::IOError(e) => e.to_string(),
+        err => p(err),
+    }
+}
+
+fn usage(game: &Game, name: &str) -> GameError {
+    match game.commands.get(name) {
+        Some(cmd) => error(&format!("usage: {}", cmd.usage)),
+        None => error(name),
+    }
+}
+
+fn help(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    match args.first() {
+        Some(name) => match game.commands.get(name) {
+            Some(cmd) => Ok(format!("{}\n{}", cmd.usage, cmd.help)),
+            None => Err(error(&format!("unknown command {}", name))),
+        },
+        None => Ok(game
+            .commands
+            .commands
+            .iter()
+            .map(|(name, cmd)| format!("{:<6} {}", name, cmd.help))
+            .collect::<Vec<_>>()
+            .join("\n")),
+    }
+}
+
+fn ls(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let dir = game.resolve_path(args.first().map(String::as_str).unwrap_or("."));
+
+    let mut names = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            name
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+
+    Ok(names.join("\n"))
+}
+
+fn cd(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if let Some(path) = args.first() {
+        let dir = game.resolve_path(path).canonicalize()?;
+        if !dir.is_dir() {
+            return Err(error(&format!("not a directory {}", path)));
+        }
+        game.cwd = dir;
+    }
+
+    Ok(game.cwd.display().to_string())
+}
+
+fn clear(_ctx: &mut Context, game: &mut Game, _args: &[String]) -> GameResult<String> {
+    game.console.clear();
+
+    Ok(String::new())
+}
+
+fn load(ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = args.first().ok_or_else(|| usage(game, "load"))?;
+    let path = game.resolve_path(path);
+    game.load_cart(ctx, &path)?;
+
+    Ok(format!("loaded {}", path.display()))
+}
+
+fn save(ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = match args.first() {
+        Some(path) => game.resolve_path(path),
+        None => game.cart_path.clone().ok_or_else(|| usage(game, "save"))?,
+    };
+    game.save_cart(ctx, &path)?;
+
+    Ok(format!("saved {}", path.display()))
+}
+
+fn run(_ctx: &mut Context, game: &mut Game, _args: &[String]) -> GameResult<String> {
+    game.run_cart()?;
+    game.mode = GameMode::Normal;
+
+    Ok(String::new())
+}
+
+fn wav(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = match args.first() {
+        Some(path) => game.resolve_path(path),
+        None => return Err(usage(game, "wav")),
+    };
+    std::fs::write(&path, game.song.to_wav(&game.sfx, synth::SAMPLE_RATE))?;
+
+    Ok(format!("wrote {}", path.display()))
+}
+
+fn scale(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if let Some(arg) = args.first() {
+        let scale = match arg.as_str() {
+            "up" => Scale::Up,
+            "down" => Scale::Down,
+            "min" => Scale::Min,
+            "max" => Scale::Max,
+            "default" => Scale::Default,
+            _ => return Err(usage(game, "scale")),
+        };
+        game.ui.set_scale(scale);
+    }
+
+    Ok(format!("x{}", game.ui.get_scale()))
+}
+
+fn screen(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    match args {
+        [] => {}
+        [columns, rows] => {
+            let columns = columns.parse().map_err(|_| usage(game, "screen"))?;
+            let rows = rows.parse().map_err(|_| usage(game, "screen"))?;
+            game.set_screen(columns, rows)?;
+            game.cart.screen = Some((columns, rows));
+        }
+        _ => return Err(usage(game, "screen")),
+    }
+
+    let (columns, rows) = game.ui.screen_size();
+    Ok(format!("{}x{} tiles", columns, rows))
+}
+
+fn pal(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    match args {
+        [] => Ok(Pal::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, pal)| {
+                let (r, g, b) = game.ui.get_palette().rgb(*pal);
+                format!("{:>2} {:?} {:02x}{:02x}{:02x}", i, pal, r, g, b)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        [color, rgb] => {
+            let pal = color
+                .parse::<usize>()
+                .ok()
+                .and_then(Pal::from_index)
+                .ok_or_else(|| error(&format!("no color {}", color)))?;
+            let rgb =
+                palette::parse_rgb(rgb).ok_or_else(|| error(&format!("bad color {}", rgb)))?;
+
+            let mut palette = *game.ui.get_palette();
+            palette.set(pal, rgb);
+            game.cart.palette = palette.overrides();
+            game.ui.set_palette(palette);
+
+            Ok(format!(
+                "{:?} = {:02x}{:02x}{:02x}",
+                pal, rgb.0, rgb.1, rgb.2
+            ))
+        }
+        _ => Err(usage(game, "pal")),
+    }
+}
+
+fn palette(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let palette = match args.first().map(String::as_str) {
+        Some("reset") => Palette::default(),
+        Some(path) => Palette::load(game.resolve_path(path))?,
+        None => return Err(usage(game, "palette")),
+    };
+    game.cart.palette = palette.overrides();
+    game.ui.set_palette(palette);
+
+    Ok(format!("{} colors changed", game.cart.palette.len()))
+}
+
+fn speed(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if let Some(arg) = args.first() {
+        let speed = arg.parse::<f32>().map_err(|_| usage(game, "speed"))?;
+        game.ticker.set_speed(speed);
+    }
+
+    Ok(format!("speed x{}", game.ticker.get_speed()))
+}
+
+fn frameskip(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if let Some(arg) = args.first() {
+        let steps = arg.parse::<u32>().map_err(|_| usage(game, "frameskip"))?;
+        game.ticker.set_max_steps(steps);
+    }
+
+    Ok(format!("{} updates per frame", game.ticker.get_max_steps()))
+}
+
+fn pause(_ctx: &mut Context, game: &mut Game, _args: &[String]) -> GameResult<String> {
+    let paused = !game.ticker.is_paused();
+    game.ticker.set_paused(paused);
+
+    Ok(if paused { "paused" } else { "resumed" }.to_string())
+}
+
+fn step(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if !game.ticker.is_paused() {
+        return Err(error("not paused"));
+    }
+
+    let steps = match args.first() {
+        Some(arg) => arg.parse::<u32>().map_err(|_| usage(game, "step"))?,
+        None => 1,
+    }
+    .min(Ticker::MAX_QUEUED);
+    for _ in 0..steps {
+        game.ticker.step();
+    }
+
+    Ok(format!("step {}", game.get_dt().saturating_add(steps)))
+}
+
+fn key(ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let list = |keymap: &Keymap, key: Option<event::KeyCode>| {
+        keymap
+            .bindings()
+            .iter()
+            .filter(|b| key.is_none() || key == Some(b.key))
+            .filter_map(|b| {
+                let name = keymap::key_name(b.key)?;
+                Some(format!("{} {:<6} {}", b.player + 1, b.btn.name(), name))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut keymap = game.get_keymap().clone();
+    let key = match args {
+        [] => return Ok(list(&keymap, None)),
+        [arg] if arg == "reset" => {
+            keymap = Keymap::default();
+            None
+        }
+        [name, rest @ ..] => {
+            let key = keymap::key_from_name(name)
+                .ok_or_else(|| error(&format!("unknown key {}", name)))?;
+            match rest {
+                [] => return Ok(list(&keymap, Some(key))),
+                [btn] if btn == "none" => {
+                    keymap.unbind(key);
+                }
+                [btn] | [btn, _] => {
+                    let btn =
+                        Btn::from_name(btn).ok_or_else(|| error(&format!("no button {}", btn)))?;
+                    let player = match rest.get(1) {
+                        Some(player) => keymap::parse_player(player)
+                            .ok_or_else(|| error(&format!("no player {}", player)))?,
+                        None => 0,
+                    };
+                    keymap.bind(key, player, btn);
+                }
+                _ => return Err(usage(game, "key")),
+            }
+            Some(key)
+        }
+    };
+
+    keymap.save(ctx)?;
+    let shown = list(&keymap, key);
+    game.set_keymap(keymap);
+
+    Ok(shown)
+}
+
+fn record(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if game.is_recording() {
+        let path = game.record_path.clone();
+        let recording = game.stop_recording();
+        return match (path, recording) {
+            (Some(path), Some(recording)) => {
+                recording.save(&path)?;
+                Ok(format!(
+                    "recorded {} ticks to {}",
+                    recording.ticks,
+                    path.display()
+                ))
+            }
+            _ => Ok("stopped recording".to_string()),
+        };
+    }
+
+    let path = args.first().ok_or_else(|| usage(game, "record"))?;
+    let path = game.resolve_path(path);
+    game.start_recording()?;
+    game.record_path = Some(path.clone());
+    game.mode = GameMode::Normal;
+
+    Ok(format!("recording to {}", path.display()))
+}
+
+fn replay(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = match args.first() {
+        Some(path) => game.resolve_path(path),
+        None if game.get_replay().is_some() => {
+            game.stop_replay();
+            return Ok("stopped replay".to_string());
+        }
+        None => return Err(usage(game, "replay")),
+    };
+    let recording = Recording::load(&path)?;
+    let ticks = recording.ticks;
+    game.start_replay(recording)?;
+    game.mode = GameMode::Normal;
+
+    Ok(format!("replaying {} ticks of {}", ticks, path.display()))
+}
+
+fn savestate(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    match args.first() {
+        Some(path) => {
+            let path = game.resolve_path(path);
+            game.save_state()?.save(&path)?;
+            Ok(format!("saved state {}", path.display()))
+        }
+        None => {
+            game.quick_save()?;
+            Ok(format!("saved state at {}", game.get_dt()))
+        }
+    }
+}
+
+fn loadstate(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    match args.first() {
+        Some(path) => game.restore_state(SaveState::load(game.resolve_path(path))?)?,
+        None => game.quick_load()?,
+    }
+    game.mode = GameMode::Normal;
+
+    Ok(format!("resumed at {}", game.get_dt()))
+}
+
+fn shot(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = match args.first() {
+        Some(path) => game.resolve_path(path),
+        None => game.resolve_path(capture::default_name("png")),
+    };
+    // the prompt isn't part of the picture
+    if let GameMode::Prompt = game.mode {
+        game.mode = GameMode::Normal;
+    }
+    game.screenshot(&path)?;
+
+    Ok(format!("saved {}", path.display()))
+}
+
+fn gif(_ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    if game.is_capturing_gif() {
+        return game.stop_gif();
+    }
+
+    let (seconds, path) = match args {
+        [] => (capture::GIF_SECONDS, None),
+        [seconds] | [seconds, _] => {
+            let seconds = match seconds.parse::<f32>() {
+                Ok(seconds) if seconds > 0. && seconds <= capture::MAX_GIF_SECONDS => seconds,
+                _ => return Err(usage(game, "gif")),
+            };
+            (seconds, args.get(1))
+        }
+        _ => return Err(usage(game, "gif")),
+    };
+    let path = match path {
+        Some(path) => game.resolve_path(path),
+        None => game.resolve_path(capture::default_name("gif")),
+    };
+    game.start_gif(seconds, &path);
+    if let GameMode::Prompt = game.mode {
+        game.mode = GameMode::Normal;
+    }
+
+    Ok(format!("capturing {}s to {}", seconds, path.display()))
+}
+
+fn export(ctx: &mut Context, game: &mut Game, args: &[String]) -> GameResult<String> {
+    let path = args.first().ok_or_else(|| usage(game, "export"))?;
+    let path = game.resolve_path(path);
+    let cart = game.bundle_cart(ctx)?;
+    bundle::export(&bundle::player_path()?, &cart, &path)?;
+
+    Ok(format!("exported {}", path.display()))
+}
@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use crate::*;
+
+/// What the arguments of a command complete to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Completion {
+    None,
+    Commands,
+    Paths,
+    Words(Vec<String>),
+}
+
+/// Result of completing the word under the cursor.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Completed {
+    /// Byte range of the line to replace
+    pub start: usize,
+    pub end: usize,
+    pub replacement: Option<String>,
+    /// Shown when the completion is ambiguous
+    pub candidates: Vec<String>,
+}
+
+/// Completes the word ending at byte `end` of `line` with the commands of
+/// `commands`, paths are relative to `cwd`.
+pub fn complete(commands: &Commands, cwd: &Path, line: &str, end: usize) -> Completed {
+    let head = &line[..end];
+    let (start, quote) = word_start(head);
+    let word = unescape(&head[start..]);
+
+    let is_first = head[..start].trim().is_empty();
+    let completion = if is_first {
+        Completion::Commands
+    } else {
+        let name = head.split_whitespace().next().unwrap_or("");
+        commands
+            .get(name)
+            .map(|cmd| cmd.completion.clone())
+            .unwrap_or(Completion::None)
+    };
+
+    // (text to insert, is final) for each candidate
+    let candidates: Vec<(String, bool)> = match completion {
+        Completion::None => Vec::new(),
+        Completion::Commands => commands
+            .names()
+            .filter(|name| name.starts_with(&word))
+            .map(|name| (name.to_string(), true))
+            .collect(),
+        Completion::Words(words) => words
+            .into_iter()
+            .filter(|w| w.starts_with(&word))
+            .map(|w| (w, true))
+            .collect(),
+        Completion::Paths => paths(cwd, &word),
+    };
+
+    let mut completed = Completed {
+        start,
+        end,
+        ..Completed::default()
+    };
+
+    match candidates.as_slice() {
+        [] => {}
+        [(candidate, is_final)] => {
+            completed.replacement = Some(escape(candidate, quote, *is_final));
+        }
+        _ => {
+            let prefix = common_prefix(candidates.iter().map(|(c, _)| c.as_str()));
+            if prefix.chars().count() > word.chars().count() {
+                completed.replacement = Some(escape(&prefix, quote, false));
+            } else {
+                completed.candidates = candidates
+                    .iter()
+                    .map(|(c, _)| match c.rfind('/') {
+                        // only show the file name for paths
+                        Some(i) if i + 1 < c.len() => c[i + 1..].to_string(),
+                        Some(i) => c[..i].rsplit('/').next().unwrap_or("").to_string() + "/",
+                        None => c.clone(),
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    completed
+}
+
+/// Lays out candidates in columns fitting `width` characters.
+pub fn columns(candidates: &[String], width: usize) -> String {
+    let line = width.max(1);
+    let width = candidates
+        .iter()
+        .map(|c| c.chars().count() + 1)
+        .max()
+        .unwrap_or(1)
+        .min(line);
+    let per_line = (line / width).max(1);
+
+    candidates
+        .chunks(per_line)
+        .map(|row| {
+            row.iter()
+                .map(|c| format!("{:<width$}", c, width = width))
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn paths(cwd: &Path, word: &str) -> Vec<(String, bool)> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..i + 1], &word[i + 1..]),
+        None => ("", word),
+    };
+
+    let entries = match std::fs::read_dir(cwd.join(if dir.is_empty() { "." } else { dir })) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            let path = format!("{}{}{}", dir, name, if is_dir { "/" } else { "" });
+            Some((path, !is_dir))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+}
+
+/// Start of the last word and the quote still open at the end, split like
+/// `command::parse_args`: whitespace escaped with a backslash or inside
+/// quotes doesn't split words.
+fn word_start(head: &str) -> (usize, Option<char>) {
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quote = None;
+
+    for (i, c) in head.char_indices() {
+        match (c, quote) {
+            _ if escaped => escaped = false,
+            ('\\', _) => escaped = true,
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, None) if c.is_whitespace() => start = i + c.len_utf8(),
+            _ => {}
+        }
+    }
+
+    (start, quote)
+}
+
+/// Writes a word back so it parses as typed: inside the quote it was
+/// started with, or with backslashes. A final word is closed with a space.
+fn escape(word: &str, quote: Option<char>, is_final: bool) -> String {
+    let mut out = String::new();
+    out.extend(quote);
+
+    for c in word.chars() {
+        let special = match quote {
+            Some(q) => c == q || c == '\\',
+            None => c.is_whitespace() || c == '\\' || c == '"' || c == '\'',
+        };
+        if special {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    if is_final {
+        out.extend(quote);
+        out.push(' ');
+    }
+
+    out
+}
+
+/// A word as `command::parse_args` reads it, without quotes and escapes
+fn unescape(word: &str) -> String {
+    let mut out = String::new();
+    let mut quote = None;
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => out.extend(chars.next()),
+            (c, Some(q)) if c == q => quote = None,
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, _) => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn common_prefix<'a>(mut words: impl Iterator<Item = &'a str>) -> String {
+    let first = match words.next() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+
+    let mut len = first.len();
+    for word in words {
+        len = first
+            .char_indices()
+            .zip(word.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+
+    first[..len].to_string()
+}
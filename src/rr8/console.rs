@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use ggez::filesystem;
+
+use crate::ui::text;
+use crate::*;
+
+/// Rows and columns of the console text box on the smallest screen, the
+/// game sizes it to the screen
+pub const CONSOLE_ROWS: usize = 14;
+pub const CONSOLE_COLUMNS: usize = 36;
+
+const MAX_LINES: usize = 1000;
+const MAX_HISTORY: usize = 200;
+const HISTORY_FILE: &str = "history";
+
+/// Scrollback of the prompt: previous commands with their output, and the
+/// history of the lines typed so far.
+#[derive(Debug)]
+pub struct Console {
+    lines: Vec<(String, Pal)>,
+    rows: usize,
+    columns: usize,
+    scroll: usize,
+    history: Vec<String>,
+    history_pos: Option<usize>,
+    draft: String,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            rows: CONSOLE_ROWS,
+            columns: CONSOLE_COLUMNS,
+            scroll: 0,
+            history: Vec::new(),
+            history_pos: None,
+            draft: String::new(),
+        }
+    }
+}
+
+impl Console {
+    /// Appends text to the log, wrapping lines longer than the box. Lines
+    /// already in it keep their wrapping when the box is resized.
+    pub fn push(&mut self, text: &str, color: Pal) {
+        for line in text.lines() {
+            for wrapped in text::wrap(line, self.columns) {
+                self.lines.push((wrapped, color));
+            }
+        }
+
+        if self.lines.len() > MAX_LINES {
+            let extra = self.lines.len() - MAX_LINES;
+            self.lines.drain(..extra);
+        }
+        self.scroll = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll = 0;
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Resizes the box, keeping the scroll within the lines.
+    pub fn set_size(&mut self, rows: usize, columns: usize) {
+        self.rows = rows.max(1);
+        self.columns = columns.max(1);
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(self.rows));
+    }
+
+    /// Lines currently visible in the box, oldest first.
+    pub fn visible(&self) -> &[(String, Pal)] {
+        let end = self.lines.len() - self.scroll;
+        let start = end.saturating_sub(self.rows);
+
+        &self.lines[start..end]
+    }
+
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        let max = self.lines.len().saturating_sub(self.rows);
+        self.scroll = (self.scroll + rows).min(max);
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_sub(rows);
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        self.history_pos = None;
+
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Steps back in history, `current` is kept to be restored when coming
+    /// back to the bottom.
+    pub fn history_prev(&mut self, current: &str) -> Option<&str> {
+        let pos = match self.history_pos {
+            Some(0) => 0,
+            Some(pos) => pos - 1,
+            None if self.history.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+
+        self.history.get(pos).map(String::as_str)
+    }
+
+    pub fn history_next(&mut self) -> Option<&str> {
+        match self.history_pos {
+            Some(pos) if pos + 1 < self.history.len() => {
+                self.history_pos = Some(pos + 1);
+                self.history.get(pos + 1).map(String::as_str)
+            }
+            Some(_) => {
+                self.history_pos = None;
+                Some(&self.draft)
+            }
+            None => None,
+        }
+    }
+
+    /// Reads the history saved at `path`, a missing file is an empty one.
+    pub fn load_history(&mut self, path: &Path) -> GameResult {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                self.history = text.lines().map(str::to_string).collect();
+                Ok(())
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save_history(&self, path: &Path) -> GameResult {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut text = self.history.join("\n");
+        text.push('\n');
+        std::fs::write(path, text)?;
+
+        Ok(())
+    }
+}
+
+/// Where the history is kept between runs, in the user config directory
+pub fn history_path(ctx: &mut Context) -> PathBuf {
+    filesystem::user_config_dir(ctx).join(HISTORY_FILE)
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use ggez::event::{Axis, Button};
+
+use crate::keymap::PLAYERS;
+use crate::*;
+
+/// Stick values closer to the center than this are ignored
+pub const DEADZONE: f32 = 0.35;
+
+/// What a controller reports, as ggez hands it to the event handler
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PadEvent {
+    ButtonDown(Button),
+    ButtonUp(Button),
+    Axis(Axis, f32),
+}
+
+/// A button of a player pressed or released
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PadChange {
+    pub player: usize,
+    pub btn: Btn,
+    pub pressed: bool,
+}
+
+pub fn btn_from_button(button: Button) -> Option<Btn> {
+    match button {
+        Button::South => Some(Btn::A),
+        Button::East => Some(Btn::B),
+        Button::West => Some(Btn::X),
+        Button::North => Some(Btn::Y),
+        Button::LeftTrigger => Some(Btn::L1),
+        Button::RightTrigger => Some(Btn::R1),
+        Button::Start => Some(Btn::Start),
+        Button::Select => Some(Btn::Select),
+        Button::DPadUp => Some(Btn::Up),
+        Button::DPadDown => Some(Btn::Down),
+        Button::DPadLeft => Some(Btn::Left),
+        Button::DPadRight => Some(Btn::Right),
+        _ => None,
+    }
+}
+
+/// Buttons of the negative and positive sides of an axis, up is positive
+fn axis_buttons(axis: Axis) -> Option<(Btn, Btn)> {
+    match axis {
+        Axis::LeftStickX | Axis::DPadX => Some((Btn::Left, Btn::Right)),
+        Axis::LeftStickY | Axis::DPadY => Some((Btn::Down, Btn::Up)),
+        _ => None,
+    }
+}
+
+/// Turns controller events into button changes.
+///
+/// Every controller plays as the player of its own: the first one seen is
+/// player 1, the next one player 2 and so on. Sticks press a direction
+/// once they leave the deadzone and release it when they come back.
+///
+/// Controllers are told apart by `Id`, ggez's `GamepadId` in the game and
+/// anything comparable in tests.
+#[derive(Debug)]
+pub struct Gamepads<Id> {
+    pub deadzone: f32,
+    pads: Vec<Id>,
+    /// Side of the center each axis was last seen on: -1, 0 or 1
+    axes: HashMap<(Id, Axis), i8>,
+}
+
+impl<Id> Default for Gamepads<Id> {
+    fn default() -> Self {
+        Self {
+            deadzone: DEADZONE,
+            pads: Vec::new(),
+            axes: HashMap::new(),
+        }
+    }
+}
+
+impl<Id: Copy + Eq + Hash> Gamepads<Id> {
+    /// Player of a controller, giving it the next one if it's new. There
+    /// are only `keymap::PLAYERS` players, other controllers are ignored.
+    pub fn player(&mut self, id: Id) -> Option<usize> {
+        if let Some(player) = self.pads.iter().position(|pad| *pad == id) {
+            return Some(player);
+        }
+        if self.pads.len() >= PLAYERS {
+            return None;
+        }
+        self.pads.push(id);
+
+        Some(self.pads.len() - 1)
+    }
+
+    pub fn handle(&mut self, id: Id, event: PadEvent) -> Vec<PadChange> {
+        let player = match self.player(id) {
+            Some(player) => player,
+            None => return Vec::new(),
+        };
+        let change = |btn, pressed| PadChange {
+            player,
+            btn,
+            pressed,
+        };
+
+        match event {
+            PadEvent::ButtonDown(button) => btn_from_button(button)
+                .map(|btn| change(btn, true))
+                .into_iter()
+                .collect(),
+            PadEvent::ButtonUp(button) => btn_from_button(button)
+                .map(|btn| change(btn, false))
+                .into_iter()
+                .collect(),
+            PadEvent::Axis(axis, value) => {
+                let (negative, positive) = match axis_buttons(axis) {
+                    Some(buttons) => buttons,
+                    None => return Vec::new(),
+                };
+                let side = if value <= -self.deadzone {
+                    -1
+                } else if value >= self.deadzone {
+                    1
+                } else {
+                    0
+                };
+                let last = self.axes.insert((id, axis), side).unwrap_or(0);
+                if side == last {
+                    return Vec::new();
+                }
+
+                let mut changes = Vec::new();
+                match last {
+                    -1 => changes.push(change(negative, false)),
+                    1 => changes.push(change(positive, false)),
+                    _ => {}
+                }
+                match side {
+                    -1 => changes.push(change(negative, true)),
+                    1 => changes.push(change(positive, true)),
+                    _ => {}
+                }
+                changes
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use ggez::event::KeyCode;
+use ggez::{filesystem, GameError};
+
+use crate::*;
+
+/// Players sharing the keyboard
+pub const PLAYERS: usize = 4;
+
+const KEYMAP_FILE: &str = "keymap";
+
+/// Keys that can be bound, by the name used in the keymap file
+const KEYS: &[(&str, KeyCode)] = &[
+    ("a", KeyCode::A),
+    ("b", KeyCode::B),
+    ("c", KeyCode::C),
+    ("d", KeyCode::D),
+    ("e", KeyCode::E),
+    ("f", KeyCode::F),
+    ("g", KeyCode::G),
+    ("h", KeyCode::H),
+    ("i", KeyCode::I),
+    ("j", KeyCode::J),
+    ("k", KeyCode::K),
+    ("l", KeyCode::L),
+    ("m", KeyCode::M),
+    ("n", KeyCode::N),
+    ("o", KeyCode::O),
+    ("p", KeyCode::P),
+    ("q", KeyCode::Q),
+    ("r", KeyCode::R),
+    ("s", KeyCode::S),
+    ("t", KeyCode::T),
+    ("u", KeyCode::U),
+    ("v", KeyCode::V),
+    ("w", KeyCode::W),
+    ("x", KeyCode::X),
+    ("y", KeyCode::Y),
+    ("z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("space", KeyCode::Space),
+    ("return", KeyCode::Return),
+    ("tab", KeyCode::Tab),
+    ("back", KeyCode::Back),
+    ("lshift", KeyCode::LShift),
+    ("rshift", KeyCode::RShift),
+    ("lctrl", KeyCode::LControl),
+    ("rctrl", KeyCode::RControl),
+    ("lalt", KeyCode::LAlt),
+    ("ralt", KeyCode::RAlt),
+    ("comma", KeyCode::Comma),
+    ("period", KeyCode::Period),
+    ("slash", KeyCode::Slash),
+    ("semicolon", KeyCode::Semicolon),
+    ("apostrophe", KeyCode::Apostrophe),
+    ("lbracket", KeyCode::LBracket),
+    ("rbracket", KeyCode::RBracket),
+    ("minus", KeyCode::Minus),
+    ("equals", KeyCode::Equals),
+    ("num0", KeyCode::Numpad0),
+    ("num1", KeyCode::Numpad1),
+    ("num2", KeyCode::Numpad2),
+    ("num3", KeyCode::Numpad3),
+    ("num4", KeyCode::Numpad4),
+    ("num5", KeyCode::Numpad5),
+    ("num6", KeyCode::Numpad6),
+    ("num7", KeyCode::Numpad7),
+    ("num8", KeyCode::Numpad8),
+    ("num9", KeyCode::Numpad9),
+];
+
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEYS.iter().find(|(_, k)| *k == key).map(|(name, _)| *name)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    let name = name.to_lowercase();
+    KEYS.iter().find(|(n, _)| *n == name).map(|(_, key)| *key)
+}
+
+/// A key pressing a button of a player, players start at 0
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Binding {
+    pub key: KeyCode,
+    pub player: usize,
+    pub btn: Btn,
+}
+
+/// Maps keys to the buttons of each player. A button can have any number
+/// of keys and a key can press several buttons.
+///
+/// It's saved in the user config dir as a text file with a binding per
+/// line, players are counted from 1 there:
+///
+/// ```text
+/// # player button key
+/// 1 a z
+/// 1 up up
+/// 2 up i
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+
+        let players = [
+            [
+                (Btn::Up, KeyCode::Up),
+                (Btn::Down, KeyCode::Down),
+                (Btn::Left, KeyCode::Left),
+                (Btn::Right, KeyCode::Right),
+                (Btn::A, KeyCode::Z),
+                (Btn::B, KeyCode::X),
+                (Btn::X, KeyCode::Q),
+                (Btn::Y, KeyCode::W),
+                (Btn::L1, KeyCode::A),
+                (Btn::R1, KeyCode::S),
+                (Btn::Start, KeyCode::C),
+                (Btn::Select, KeyCode::V),
+            ],
+            [
+                (Btn::Up, KeyCode::I),
+                (Btn::Down, KeyCode::K),
+                (Btn::Left, KeyCode::J),
+                (Btn::Right, KeyCode::L),
+                (Btn::A, KeyCode::N),
+                (Btn::B, KeyCode::M),
+                (Btn::X, KeyCode::T),
+                (Btn::Y, KeyCode::Y),
+                (Btn::L1, KeyCode::U),
+                (Btn::R1, KeyCode::O),
+                (Btn::Start, KeyCode::B),
+                (Btn::Select, KeyCode::G),
+            ],
+        ];
+        for (player, bindings) in players.iter().enumerate() {
+            for (btn, key) in bindings.iter() {
+                keymap.bind(*key, player, *btn);
+            }
+        }
+
+        keymap
+    }
+}
+
+impl Keymap {
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// Buttons pressed by a key
+    pub fn buttons(&self, key: KeyCode) -> impl Iterator<Item = (usize, Btn)> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |b| b.key == key)
+            .map(|b| (b.player, b.btn))
+    }
+
+    pub fn bind(&mut self, key: KeyCode, player: usize, btn: Btn) {
+        let binding = Binding { key, player, btn };
+        if !self.bindings.contains(&binding) {
+            self.bindings.push(binding);
+        }
+    }
+
+    /// Removes every binding of a key, returns how many there were.
+    pub fn unbind(&mut self, key: KeyCode) -> usize {
+        let count = self.bindings.len();
+        self.bindings.retain(|b| b.key != key);
+
+        count - self.bindings.len()
+    }
+
+    pub fn parse(text: &str) -> GameResult<Self> {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error =
+                |what: &str| GameError::ConfigError(format!("keymap line {}: {}", i + 1, what));
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (player, btn, key) = match words[..] {
+                [player, btn, key] => (player, btn, key),
+                _ => return Err(error("expected player, button and key")),
+            };
+
+            let player = parse_player(player).ok_or_else(|| error("bad player"))?;
+            let btn = Btn::from_name(btn).ok_or_else(|| error("unknown button"))?;
+            let key = key_from_name(key).ok_or_else(|| error("unknown key"))?;
+            keymap.bind(key, player, btn);
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::from("# player button key\n");
+        for binding in self.bindings.iter() {
+            if let Some(key) = key_name(binding.key) {
+                let btn = binding.btn.name();
+                writeln!(out, "{} {} {}", binding.player + 1, btn, key).unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// Loads the keymap of the user, the default one if there's none.
+    pub fn load(ctx: &mut Context) -> GameResult<Self> {
+        match std::fs::read_to_string(keymap_path(ctx)) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, ctx: &mut Context) -> GameResult {
+        let path = keymap_path(ctx);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.serialize())?;
+
+        Ok(())
+    }
+}
+
+/// Players are counted from 1 when typed
+pub fn parse_player(text: &str) -> Option<usize> {
+    match text.parse::<usize>() {
+        Ok(player) if (1..=PLAYERS).contains(&player) => Some(player - 1),
+        _ => None,
+    }
+}
+
+fn keymap_path(ctx: &mut Context) -> PathBuf {
+    filesystem::user_config_dir(ctx).join(KEYMAP_FILE)
+}
@@ -0,0 +1,276 @@
+use ggez::GameError;
+
+use crate::render::Renderer;
+use crate::*;
+
+pub const MAP_LAYERS: usize = 3;
+
+const MAP_FORMAT: u8 = 1;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tileset {
+    Main,
+    Alt,
+}
+
+/// A reference to a tile of one of the tilesets
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Cell {
+    pub tileset: Tileset,
+    pub row: u16,
+    pub column: u16,
+    pub color: Pal,
+    pub flip: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            tileset: Tileset::Alt,
+            row: 0,
+            column: 0,
+            color: Pal::White,
+            flip: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub visible: bool,
+    cells: Vec<Option<Cell>>,
+}
+
+/// A level made of layers of cells, drawn bottom to top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    width: u16,
+    height: u16,
+    layers: Vec<Layer>,
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::for_screen((ui::SCREEN_COLUMNS, ui::SCREEN_ROWS))
+    }
+}
+
+impl Map {
+    pub fn new(width: u16, height: u16, layers: usize) -> Self {
+        let layer = Layer {
+            visible: true,
+            cells: vec![None; width as usize * height as usize],
+        };
+
+        Self {
+            width,
+            height,
+            layers: vec![layer; layers.max(1)],
+        }
+    }
+
+    /// An empty map the size of the viewport of a screen of `columns` and
+    /// `rows` tiles, below the top bar and above the status row.
+    pub fn for_screen((columns, rows): (u16, u16)) -> Self {
+        Self::new(columns, rows.saturating_sub(2), MAP_LAYERS)
+    }
+
+    /// Grows the map to at least `width`x`height` cells keeping the ones it
+    /// has, it never shrinks so a smaller screen doesn't lose any.
+    pub fn grow(&mut self, width: u16, height: u16) {
+        let (w, h) = (self.width.max(width), self.height.max(height));
+        if (w, h) == (self.width, self.height) {
+            return;
+        }
+
+        for layer in self.layers.iter_mut() {
+            let mut cells = vec![None; w as usize * h as usize];
+            for (y, row) in layer.cells.chunks(self.width.max(1) as usize).enumerate() {
+                let start = y * w as usize;
+                cells[start..start + row.len()].copy_from_slice(row);
+            }
+            layer.cells = cells;
+        }
+        self.width = w;
+        self.height = h;
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, layer: usize) -> Option<&mut Layer> {
+        self.layers.get_mut(layer)
+    }
+
+    pub fn get(&self, layer: usize, x: u16, y: u16) -> Option<Cell> {
+        let i = self.index(x, y)?;
+        self.layers.get(layer).and_then(|l| l.cells[i])
+    }
+
+    pub fn set(&mut self, layer: usize, x: u16, y: u16, cell: Option<Cell>) {
+        if let Some(i) = self.index(x, y) {
+            if let Some(layer) = self.layers.get_mut(layer) {
+                layer.cells[i] = cell;
+            }
+        }
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Draws the visible layers with the top left corner at (x, y) in tiles.
+    ///
+    /// Cells of a layer never overlap, so each layer draws all the cells of
+    /// one tileset before the other: the renderer batches consecutive
+    /// sprites of the same sheet and every layer costs at most two batches.
+    pub fn draw(&self, r: &mut dyn Renderer, ui: &Ui, x: f32, y: f32) -> GameResult {
+        for layer in self.layers.iter().filter(|l| l.visible) {
+            for tileset in [Tileset::Main, Tileset::Alt].iter() {
+                for (i, cell) in layer.cells.iter().enumerate() {
+                    let cell = match cell {
+                        Some(cell) if cell.tileset == *tileset => cell,
+                        _ => continue,
+                    };
+                    let cx = x + (i % self.width as usize) as f32;
+                    let cy = y + (i / self.width as usize) as f32;
+                    ui.draw_cell(r, cell, cx, cy)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Binary form stored in carts: a header with the format, size and
+    /// number of layers, then every layer as a visibility byte followed by
+    /// its cells. A cell is a flags byte (0 for empty cells), then row and
+    /// column as little endian u16 and color.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![MAP_FORMAT];
+        data.extend_from_slice(&self.width.to_le_bytes());
+        data.extend_from_slice(&self.height.to_le_bytes());
+        data.push(self.layers.len() as u8);
+
+        for layer in self.layers.iter() {
+            data.push(layer.visible as u8);
+            for cell in layer.cells.iter() {
+                match cell {
+                    None => data.push(0),
+                    Some(cell) => {
+                        let mut flags = 0b001;
+                        if cell.tileset == Tileset::Alt {
+                            flags |= 0b010;
+                        }
+                        if cell.flip {
+                            flags |= 0b100;
+                        }
+                        data.push(flags);
+                        data.extend_from_slice(&cell.row.to_le_bytes());
+                        data.extend_from_slice(&cell.column.to_le_bytes());
+                        data.push(cell.color as u8);
+                    }
+                }
+            }
+        }
+
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> GameResult<Self> {
+        let error = || GameError::ResourceLoadError("map: truncated data".to_string());
+        let mut bytes = data.iter().copied();
+        let mut next = || bytes.next().ok_or_else(error);
+
+        let format = next()?;
+        if format != MAP_FORMAT {
+            return Err(GameError::ResourceLoadError(format!(
+                "map: unknown format {}",
+                format
+            )));
+        }
+        let width = u16::from_le_bytes([next()?, next()?]);
+        let height = u16::from_le_bytes([next()?, next()?]);
+        let layers = next()? as usize;
+
+        // every layer takes at least its visibility byte and a byte per cell,
+        // checked before a bogus size gets allocated
+        let size = width as usize * height as usize;
+        if data.len() < 6 + layers * (1 + size) {
+            return Err(error());
+        }
+
+        let mut map = Self::new(width, height, layers);
+        for layer in map.layers.iter_mut() {
+            layer.visible = next()? != 0;
+            for cell in layer.cells.iter_mut() {
+                let flags = next()?;
+                if flags & 0b001 == 0 {
+                    continue;
+                }
+                let row = u16::from_le_bytes([next()?, next()?]);
+                let column = u16::from_le_bytes([next()?, next()?]);
+                *cell = Some(Cell {
+                    tileset: if flags & 0b010 > 0 {
+                        Tileset::Alt
+                    } else {
+                        Tileset::Main
+                    },
+                    flip: flags & 0b100 > 0,
+                    row,
+                    column,
+                    color: Pal::from_index(next()? as usize).unwrap_or(Pal::White),
+                });
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+/// State of the map editor: the cell painted and the layer painted on.
+#[derive(Debug, Default)]
+pub struct MapEdit {
+    pub brush: Cell,
+    pub layer: usize,
+}
+
+impl MapEdit {
+    pub fn next_color(&mut self, step: isize) {
+        let count = Pal::ALL.len() as isize;
+        let i = (self.brush.color as isize + step).rem_euclid(count);
+        self.brush.color = Pal::ALL[i as usize];
+    }
+
+    pub fn move_brush(&mut self, dx: i32, dy: i32, ui: &Ui) {
+        let (rows, columns) = ui.tileset_size(self.brush.tileset);
+        // a tileset smaller than a tile has nothing to pick
+        if rows == 0 || columns == 0 {
+            return;
+        }
+        self.brush.column = (self.brush.column as i32 + dx).rem_euclid(columns as i32) as u16;
+        self.brush.row = (self.brush.row as i32 + dy).rem_euclid(rows as i32) as u16;
+    }
+
+    pub fn switch_tileset(&mut self, ui: &Ui) {
+        self.brush.tileset = match self.brush.tileset {
+            Tileset::Main => Tileset::Alt,
+            Tileset::Alt => Tileset::Main,
+        };
+        // keep the brush inside the new tileset
+        self.move_brush(0, 0, ui);
+    }
+}
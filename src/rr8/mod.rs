@@ -1,10 +1,57 @@
+pub mod audio;
+pub mod bundle;
+pub mod button;
+pub mod capture;
+pub mod cart;
+pub mod code;
+pub mod command;
+pub mod complete;
+pub mod console;
+pub mod gamepad;
+pub mod keymap;
+pub mod map;
+pub mod music;
+pub mod render;
+pub mod replay;
+pub mod rng;
+pub mod script;
+pub mod sfx;
+pub mod snapshot;
+pub mod sprite;
+pub mod state;
+pub mod synth;
+pub mod tick;
 pub mod ui;
 
+use std::path::{Path, PathBuf};
+
 use ggez;
 use ggez::error::GameResult;
-use ggez::{event, Context};
+use ggez::event::MouseButton;
+use ggez::input::gamepad::GamepadId;
+use ggez::{event, timer, Context, GameError};
 
-pub(crate) use ui::{palette::Pal, Ui};
+pub(crate) use audio::Audio;
+pub(crate) use button::ButtonState;
+pub(crate) use capture::GifRecorder;
+pub(crate) use cart::Cart;
+pub(crate) use code::CodeEdit;
+pub(crate) use command::Commands;
+pub(crate) use console::Console;
+pub(crate) use gamepad::{Gamepads, PadEvent};
+pub(crate) use keymap::Keymap;
+pub(crate) use map::{Map, MapEdit};
+pub(crate) use music::{MusicEdit, Song, TrackCache};
+pub(crate) use replay::{Input, Recording, Replay};
+pub(crate) use script::ScriptSystem;
+pub(crate) use sfx::{Sfx, SfxEdit};
+pub(crate) use sprite::SpriteEdit;
+pub(crate) use state::SaveState;
+pub(crate) use tick::Ticker;
+pub(crate) use ui::{
+    palette::{Pal, Palette},
+    Ui,
+};
 
 const FONT_PATH: &'static str = "/roguelike-font-16.png";
 const FONT_MAP: &'static str = "\
@@ -54,17 +101,71 @@ pub enum TileId {
     Ico = 60,
 }
 
+/// Screen, map and sound of a cart, decoded before the game switches to it
+#[derive(Debug)]
+struct CartData {
+    win: ggez::graphics::Rect,
+    map: Map,
+    sfx: Vec<Sfx>,
+    song: Song,
+}
+
+impl CartData {
+    fn decode(cart: &Cart) -> GameResult<Self> {
+        let (columns, rows) = cart.screen.unwrap_or((ui::SCREEN_COLUMNS, ui::SCREEN_ROWS));
+
+        Ok(Self {
+            win: Ui::screen_rect(columns, rows)?,
+            map: if cart.map.is_empty() {
+                Map::for_screen((columns, rows))
+            } else {
+                Map::from_bytes(&cart.map)?
+            },
+            sfx: if cart.sfx.is_empty() {
+                vec![Sfx::default(); sfx::SFX_COUNT]
+            } else {
+                sfx::from_bytes(&cart.sfx)?
+            },
+            song: if cart.music.is_empty() {
+                Song::default()
+            } else {
+                Song::from_bytes(&cart.music)?
+            },
+        })
+    }
+}
+
 /// Small alias for formatting Debug types
 fn p(t: impl std::fmt::Debug) -> String {
     format!("{:?}", t)
 }
 
+fn save_gif(gif: &GifRecorder, path: &Path) -> GameResult<String> {
+    gif.save(path)?;
+
+    Ok(format!(
+        "saved {} frames to {}",
+        gif.frame_count(),
+        path.display()
+    ))
+}
+
+/// Rows and characters per row of the big text box of the console and the
+/// code editor: the body minus the title box and the margins, two
+/// characters per tile.
+fn text_box_size(ui: &Ui) -> (usize, usize) {
+    let (columns, rows) = ui.screen_size();
+
+    (rows as usize - 6, (columns as usize - 2) * 2)
+}
+
 pub trait System: std::fmt::Debug {
-    fn update(&mut self, ctx: &mut Context, game: &mut Game) -> GameResult;
-    fn draw(&self, ctx: &mut Context, game: &Game) -> GameResult;
+    /// Called `tick::TICK_RATE` times per second of game time.
+    fn update(&mut self, game: &mut Game) -> GameResult;
+    fn draw(&self, r: &mut dyn render::Renderer, game: &Game) -> GameResult;
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Btn {
     Up,
     Down,
@@ -80,32 +181,269 @@ pub enum Btn {
     Y,
 }
 
+impl Btn {
+    pub const ALL: [Btn; 12] = [
+        Btn::Up,
+        Btn::Down,
+        Btn::Left,
+        Btn::Right,
+        Btn::A,
+        Btn::B,
+        Btn::Start,
+        Btn::Select,
+        Btn::L1,
+        Btn::R1,
+        Btn::X,
+        Btn::Y,
+    ];
+
+    /// Name used in the keymap and at the prompt
+    pub fn name(&self) -> &'static str {
+        match self {
+            Btn::Up => "up",
+            Btn::Down => "down",
+            Btn::Left => "left",
+            Btn::Right => "right",
+            Btn::A => "a",
+            Btn::B => "b",
+            Btn::Start => "start",
+            Btn::Select => "select",
+            Btn::L1 => "l1",
+            Btn::R1 => "r1",
+            Btn::X => "x",
+            Btn::Y => "y",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Btn> {
+        let name = name.to_lowercase();
+        Btn::ALL.iter().copied().find(|btn| btn.name() == name)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum GameMode {
     Normal,
     Prompt,
+    Map,
+    Sprite,
+    Code,
+    Sfx,
+    Music,
+}
+
+impl GameMode {
+    /// Editors take the whole screen below the top bar
+    pub fn is_editor(&self) -> bool {
+        match self {
+            GameMode::Normal | GameMode::Prompt => false,
+            GameMode::Map | GameMode::Sprite | GameMode::Code | GameMode::Sfx | GameMode::Music => {
+                true
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Game {
     pub mode: GameMode,
     pub ui: Ui,
+    pub ticker: Ticker,
+    pub map: Map,
+    pub sfx: Vec<Sfx>,
+    pub song: Song,
+    map_edit: MapEdit,
+    sprite_edit: SpriteEdit,
+    code_edit: CodeEdit,
+    sfx_edit: SfxEdit,
+    music_edit: MusicEdit,
+    audio: Audio,
+    tracks: TrackCache,
+    dt: u32,
     cursor: usize,
     status: String,
+    keymap: Keymap,
+    gamepads: Gamepads<GamepadId>,
+    buttons: ButtonState,
+    message: Option<(String, bool)>,
+    console: Console,
+    commands: Commands,
+    cwd: PathBuf,
+    cart: Cart,
+    cart_path: Option<PathBuf>,
+    script: Option<ScriptSystem>,
+    seed: u64,
+    recording: Option<Recording>,
+    record_path: Option<PathBuf>,
+    replay: Option<Replay>,
+    /// Position the song was started from and when
+    music_start: Option<(usize, u32)>,
+    quick_state: Option<SaveState>,
+    gif: Option<(GifRecorder, PathBuf)>,
 }
 
 impl Game {
-    pub fn new(_ctx: &mut Context, ui: Ui) -> GameResult<Self> {
+    pub fn new(ui: Ui) -> GameResult<Self> {
         let mode = GameMode::Normal;
         let cursor = 0;
         let status = String::new();
+        let buttons = ButtonState::default();
+        let console = Console::default();
+        let commands = Commands::with_builtins();
+        let cwd = std::env::current_dir()?;
+        let cart = Cart::default();
 
-        Ok(Self {
+        let mut game = Self {
             mode,
             ui,
+            ticker: Ticker::default(),
+            map: Map::default(),
+            sfx: vec![Sfx::default(); sfx::SFX_COUNT],
+            song: Song::default(),
+            map_edit: MapEdit::default(),
+            sprite_edit: SpriteEdit::default(),
+            code_edit: CodeEdit::default(),
+            sfx_edit: SfxEdit::default(),
+            music_edit: MusicEdit::default(),
+            audio: Audio::default(),
+            tracks: TrackCache::default(),
+            dt: 0,
             cursor,
             status,
-        })
+            keymap: Keymap::default(),
+            gamepads: Gamepads::default(),
+            buttons,
+            message: None,
+            console,
+            commands,
+            cwd,
+            cart,
+            cart_path: None,
+            script: None,
+            seed: rng::time_seed(),
+            recording: None,
+            record_path: None,
+            replay: None,
+            music_start: None,
+            quick_state: None,
+            gif: None,
+        };
+        game.fit_screen();
+
+        Ok(game)
+    }
+
+    /// Runs as many fixed steps as the time passed since the last frame
+    /// asks for.
+    pub fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let steps = self.ticker.advance(timer::delta(ctx));
+        for _ in 0..steps {
+            self.tick()?;
+        }
+
+        // no sound is no reason to stop the game
+        if let Err(err) = self.audio.flush(ctx) {
+            let message = format!("could not play sound: {}", command::error_message(&err));
+            self.console.push(&message, Pal::Red);
+        }
+
+        Ok(())
+    }
+
+    /// Advances the game by one step, updating every system.
+    pub fn tick(&mut self) -> GameResult {
+        self.dt = self.dt.wrapping_add(1);
+        // UI has its own delta time for animations and stuff
+        self.ui.dt = self.dt;
+
+        let dt = self.dt;
+        let replayed = self.replay.as_mut().map(|replay| replay.inputs(dt));
+        for input in replayed.unwrap_or_default() {
+            self.apply_input(input);
+        }
+        self.buttons.tick();
+
+        // systems are moved out of the game while they borrow it mutably,
+        // the ones added in the meantime are kept after them
+        let mut systems = self.ui.take_systems();
+        let result = systems
+            .iter_mut()
+            .try_for_each(|system| system.update(self));
+        self.ui.restore_systems(systems);
+        result?;
+
+        if let Some(mut script) = self.script.take() {
+            let result = script.update(self);
+            // keep the script a command may have started meanwhile
+            if self.script.is_none() {
+                self.script = Some(script);
+            }
+            result?;
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.ticks = self.dt;
+        }
+        if matches!(&self.replay, Some(r) if r.is_done(self.dt)) {
+            self.stop_replay();
+            self.console.push("replay done", Pal::Gray);
+        }
+
+        if let Some((mut gif, path)) = self.gif.take() {
+            if gif.wants_frame() {
+                gif.push(&self.capture()?);
+            }
+            gif.tick();
+            if gif.is_done() {
+                match save_gif(&gif, &path) {
+                    Ok(output) => self.console.push(&output, Pal::Gray),
+                    Err(err) => self.console.push(&command::error_message(&err), Pal::Red),
+                }
+            } else {
+                self.gif = Some((gif, path));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders the virtual screen with the software renderer, at its own
+    /// size whatever the window scale.
+    pub fn capture(&self) -> GameResult<render::soft::Framebuffer> {
+        let win = self.ui.get_win();
+        let mut fb = render::soft::Framebuffer::new(win.w as u16, win.h as u16);
+        self.ui.draw_all(&mut fb, self)?;
+
+        Ok(fb)
+    }
+
+    /// Saves the virtual screen as a PNG.
+    pub fn screenshot(&self, path: impl AsRef<Path>) -> GameResult {
+        snapshot::save_png(&self.capture()?, path.as_ref())
+    }
+
+    /// Captures the next seconds of game time as an animated GIF, saved
+    /// once done.
+    pub fn start_gif(&mut self, seconds: f32, path: impl AsRef<Path>) {
+        let gif = GifRecorder::with_palette(seconds, *self.ui.get_palette());
+        self.gif = Some((gif, path.as_ref().to_path_buf()));
+    }
+
+    /// Saves the GIF being captured before its time is up.
+    pub fn stop_gif(&mut self) -> GameResult<String> {
+        match self.gif.take() {
+            Some((gif, path)) => save_gif(&gif, &path),
+            None => Err(GameError::RenderError("no gif capture".to_string())),
+        }
+    }
+
+    pub fn is_capturing_gif(&self) -> bool {
+        self.gif.is_some()
+    }
+
+    /// Number of steps run so far
+    pub fn get_dt(&self) -> u32 {
+        self.dt
     }
 
     pub fn set_status(&mut self, text: String) {
@@ -120,19 +458,13 @@ impl Game {
     ) {
         match self.mode {
             GameMode::Normal => {
-                match keycode {
-                    event::KeyCode::Up => self._key_down(ctx, Btn::Up),
-                    event::KeyCode::Down => self._key_down(ctx, Btn::Down),
-                    event::KeyCode::Left => self._key_down(ctx, Btn::Left),
-                    event::KeyCode::Right => self._key_down(ctx, Btn::Right),
-                    event::KeyCode::Z => self._key_down(ctx, Btn::A),
-                    event::KeyCode::X => self._key_down(ctx, Btn::B),
-                    event::KeyCode::A => self._key_down(ctx, Btn::L1),
-                    event::KeyCode::S => self._key_down(ctx, Btn::R1),
-                    event::KeyCode::C => self._key_down(ctx, Btn::Start),
-                    event::KeyCode::V => self._key_down(ctx, Btn::Select),
-                    _ => return,
-                };
+                let pressed: Vec<_> = self.keymap.buttons(keycode).collect();
+                if pressed.is_empty() {
+                    return;
+                }
+                for (player, btn) in pressed {
+                    self._key_down(ctx, player, btn);
+                }
             }
             GameMode::Prompt => match keycode {
                 event::KeyCode::Home => {
@@ -151,14 +483,322 @@ impl Game {
                         self.cursor = self.cursor + 1;
                     }
                 }
+                event::KeyCode::Up => {
+                    if let Some(line) = self.console.history_prev(&self.status) {
+                        self.status = line.to_string();
+                        self.cursor = self.status.chars().count();
+                    }
+                }
+                event::KeyCode::Down => {
+                    if let Some(line) = self.console.history_next() {
+                        self.status = line.to_string();
+                        self.cursor = self.status.chars().count();
+                    }
+                }
+                event::KeyCode::Tab => self.complete_prompt(),
+                event::KeyCode::PageUp => self.console.scroll_up(self.console.rows() - 1),
+                event::KeyCode::PageDown => self.console.scroll_down(self.console.rows() - 1),
                 _ => return,
             },
+            GameMode::Map => {
+                let shift = keymods.contains(event::KeyMods::SHIFT);
+                let edit = &mut self.map_edit;
+                match keycode {
+                    event::KeyCode::Left => edit.move_brush(-1, 0, &self.ui),
+                    event::KeyCode::Right => edit.move_brush(1, 0, &self.ui),
+                    event::KeyCode::Up => edit.move_brush(0, -1, &self.ui),
+                    event::KeyCode::Down => edit.move_brush(0, 1, &self.ui),
+                    event::KeyCode::Tab => edit.switch_tileset(&self.ui),
+                    event::KeyCode::C => edit.next_color(if shift { -1 } else { 1 }),
+                    event::KeyCode::F => edit.brush.flip = !edit.brush.flip,
+                    event::KeyCode::PageUp => {
+                        edit.layer = (edit.layer + 1).min(self.map.layers().len() - 1)
+                    }
+                    event::KeyCode::PageDown => edit.layer = edit.layer.saturating_sub(1),
+                    event::KeyCode::H => {
+                        if let Some(layer) = self.map.layer_mut(edit.layer) {
+                            layer.visible = !layer.visible;
+                        }
+                    }
+                    _ => return,
+                }
+            }
+            GameMode::Sprite => {
+                let shift = keymods.contains(event::KeyMods::SHIFT);
+                let ctrl = keymods.contains(event::KeyMods::CTRL);
+                let edit = &mut self.sprite_edit;
+                match keycode {
+                    event::KeyCode::Left => edit.move_tile(-1, 0, &self.ui),
+                    event::KeyCode::Right => edit.move_tile(1, 0, &self.ui),
+                    event::KeyCode::Up => edit.move_tile(0, -1, &self.ui),
+                    event::KeyCode::Down => edit.move_tile(0, 1, &self.ui),
+                    event::KeyCode::Tab => edit.switch_tileset(&self.ui),
+                    event::KeyCode::W => edit.toggle_narrow(&self.ui),
+                    event::KeyCode::C => edit.next_color(if shift { -1 } else { 1 }),
+                    event::KeyCode::Key1 => edit.tool = sprite::Tool::Pencil,
+                    event::KeyCode::Key2 => edit.tool = sprite::Tool::Fill,
+                    event::KeyCode::Key3 => edit.tool = sprite::Tool::Line,
+                    event::KeyCode::Key4 => edit.tool = sprite::Tool::Rect,
+                    event::KeyCode::Z if ctrl => {
+                        edit.pop_undo(&mut self.ui);
+                    }
+                    _ => return,
+                }
+            }
+            GameMode::Code => {
+                let shift = keymods.contains(event::KeyMods::SHIFT);
+                let ctrl = keymods.contains(event::KeyMods::CTRL);
+                let edit = &mut self.code_edit;
+                match keycode {
+                    event::KeyCode::Left => edit.move_by(-1, 0, shift),
+                    event::KeyCode::Right => edit.move_by(1, 0, shift),
+                    event::KeyCode::Up => edit.move_by(0, -1, shift),
+                    event::KeyCode::Down => edit.move_by(0, 1, shift),
+                    event::KeyCode::Home => edit.home(shift),
+                    event::KeyCode::End => edit.end(shift),
+                    event::KeyCode::PageUp => edit.page(-1, shift),
+                    event::KeyCode::PageDown => edit.page(1, shift),
+                    event::KeyCode::Return if edit.get_search().is_some() => {
+                        edit.find_next();
+                    }
+                    event::KeyCode::Return => edit.newline(),
+                    event::KeyCode::Tab => edit.tab(),
+                    event::KeyCode::A if ctrl => edit.select_all(),
+                    event::KeyCode::C if ctrl => edit.copy(),
+                    event::KeyCode::X if ctrl => edit.cut(),
+                    event::KeyCode::V if ctrl => edit.paste(),
+                    event::KeyCode::F if ctrl => edit.start_search(),
+                    event::KeyCode::G if ctrl => {
+                        edit.find_next();
+                    }
+                    _ => return,
+                }
+                edit.scroll_to_cursor();
+            }
+            GameMode::Sfx => {
+                let shift = keymods.contains(event::KeyMods::SHIFT);
+                let edit = &mut self.sfx_edit;
+                let sfx = &mut self.sfx[edit.index];
+                let step = &mut sfx.steps[edit.step];
+                match keycode {
+                    event::KeyCode::PageUp => edit.next_sfx(-1),
+                    event::KeyCode::PageDown => edit.next_sfx(1),
+                    event::KeyCode::Left => edit.move_step(-1),
+                    event::KeyCode::Right => edit.move_step(1),
+                    event::KeyCode::Up | event::KeyCode::Down => {
+                        let octave = if shift { 12 } else { 1 };
+                        step.pitch = if keycode == event::KeyCode::Up {
+                            (step.pitch + octave).min(sfx::MAX_PITCH)
+                        } else {
+                            step.pitch.saturating_sub(octave)
+                        };
+                    }
+                    event::KeyCode::W => {
+                        step.wave =
+                            sfx::cycle(&sfx::Wave::ALL, step.wave, if shift { -1 } else { 1 });
+                        edit.wave = step.wave;
+                    }
+                    event::KeyCode::E => {
+                        step.effect =
+                            sfx::cycle(&sfx::Effect::ALL, step.effect, if shift { -1 } else { 1 });
+                    }
+                    event::KeyCode::V => {
+                        step.volume = if shift {
+                            step.volume.saturating_sub(1)
+                        } else {
+                            (step.volume + 1).min(sfx::MAX_VOLUME)
+                        };
+                    }
+                    event::KeyCode::LBracket => sfx.speed = sfx.speed.saturating_sub(1).max(1),
+                    event::KeyCode::RBracket => sfx.speed = sfx.speed.saturating_add(1),
+                    event::KeyCode::Delete | event::KeyCode::Back => *step = sfx::Step::default(),
+                    event::KeyCode::Space => {
+                        let index = edit.index;
+                        self.play_sfx(index);
+                    }
+                    _ => return,
+                }
+            }
+            GameMode::Music => {
+                let shift = keymods.contains(event::KeyMods::SHIFT);
+                let edit = &mut self.music_edit;
+                let song = &mut self.song;
+                let step = if shift { 8 } else { 1 };
+                match keycode {
+                    event::KeyCode::Up => edit.move_position(-1, song),
+                    event::KeyCode::Down => edit.move_position(1, song),
+                    event::KeyCode::Left => edit.move_channel(-1),
+                    event::KeyCode::Right => edit.move_channel(1),
+                    event::KeyCode::PageUp => edit.next_sfx(step, song),
+                    event::KeyCode::PageDown => edit.next_sfx(-step, song),
+                    event::KeyCode::Period => edit.next_pattern(1, song),
+                    event::KeyCode::Comma => edit.next_pattern(-1, song),
+                    event::KeyCode::Return => edit.insert(song),
+                    event::KeyCode::Delete | event::KeyCode::Back => {
+                        edit.remove(song);
+                        song.loop_start = song.loop_start.min(song.order.len() - 1);
+                        song.loop_end = song.loop_end.min(song.order.len() - 1);
+                    }
+                    event::KeyCode::L if shift => {
+                        song.loop_end = edit.position;
+                        song.loop_start = song.loop_start.min(edit.position);
+                    }
+                    event::KeyCode::L => {
+                        song.loop_start = edit.position;
+                        song.loop_end = song.loop_end.max(edit.position);
+                    }
+                    event::KeyCode::LBracket => song.tempo = song.tempo.saturating_sub(1).max(1),
+                    event::KeyCode::RBracket => song.tempo = song.tempo.saturating_add(1),
+                    event::KeyCode::Space if self.audio.is_music_playing() => self.play_music(None),
+                    event::KeyCode::Space => {
+                        let position = edit.position;
+                        self.play_music(Some(position));
+                    }
+                    _ => return,
+                }
+            }
         }
         println!("cursor {:?}", self.cursor);
     }
 
-    pub fn _key_down(&mut self, _ctx: &mut Context, btn: Btn) {
-        println!("{:?}", btn);
+    pub fn _key_down(&mut self, _ctx: &mut Context, player: usize, btn: Btn) {
+        self.input(Input::Press(player, btn));
+    }
+
+    pub fn key_up(&mut self, _ctx: &mut Context, keycode: event::KeyCode) {
+        // released buttons are tracked in every mode so none get stuck when
+        // switching to the prompt while holding them
+        let released: Vec<_> = self.keymap.buttons(keycode).collect();
+        for (player, btn) in released {
+            self._key_up(player, btn);
+        }
+    }
+
+    fn _key_up(&mut self, player: usize, btn: Btn) {
+        self.input(Input::Release(player, btn));
+    }
+
+    /// Moves the mouse to pixels of the virtual screen, see `Letterbox`
+    /// for converting window coordinates.
+    pub fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.input(Input::MouseMove(x, y));
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.input(Input::MouseButton(button, pressed));
+    }
+
+    /// Feeds an input to the game, it's recorded for the tick that will
+    /// sample it. Live input is ignored while a replay runs.
+    pub fn input(&mut self, input: Input) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(self.dt.wrapping_add(1), input);
+        }
+        self.apply_input(input);
+    }
+
+    fn apply_input(&mut self, input: Input) {
+        match input {
+            Input::Press(player, btn) => self.buttons.press(player, btn),
+            Input::Release(player, btn) => self.buttons.release(player, btn),
+            Input::MouseMove(x, y) => self.ui.set_mouse_coords((x, y)),
+            Input::MouseButton(button, pressed) => self.ui.set_mouse_button(button, pressed),
+        }
+    }
+
+    /// Restarts the cart and records the inputs from its first tick.
+    pub fn start_recording(&mut self) -> GameResult {
+        self.stop_replay();
+        self.restart_cart(rng::time_seed())?;
+        self.recording = Some(Recording::new(self.seed));
+
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.record_path = None;
+        self.recording.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Restarts the cart with the seed of a recording and plays its inputs
+    /// back on the same ticks.
+    pub fn start_replay(&mut self, recording: Recording) -> GameResult {
+        self.stop_recording();
+        self.restart_cart(recording.seed)?;
+        self.replay = Some(Replay::new(recording));
+
+        Ok(())
+    }
+
+    /// Hands the buttons back to the player.
+    pub fn stop_replay(&mut self) {
+        if self.replay.take().is_some() {
+            self.buttons.release_all();
+        }
+    }
+
+    pub fn get_replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    /// Runs the cart from a clean state: the first tick is 1 again and no
+    /// button is held.
+    fn restart_cart(&mut self, seed: u64) -> GameResult {
+        self.dt = 0;
+        self.ui.dt = 0;
+        self.buttons = ButtonState::default();
+        self.run_cart_with_seed(seed)
+    }
+
+    /// Presses and releases the buttons of a controller event. Like keys,
+    /// controllers only press buttons in `GameMode::Normal`.
+    pub fn gamepad_event(&mut self, ctx: &mut Context, id: GamepadId, event: PadEvent) {
+        for change in self.gamepads.handle(id, event) {
+            if !change.pressed {
+                self._key_up(change.player, change.btn);
+            } else if self.mode == GameMode::Normal {
+                self._key_down(ctx, change.player, change.btn);
+            }
+        }
+    }
+
+    /// Buttons of every player as of the current tick, players start at 0
+    pub fn get_buttons(&self) -> &ButtonState {
+        &self.buttons
+    }
+
+    pub fn get_buttons_mut(&mut self) -> &mut ButtonState {
+        &mut self.buttons
+    }
+
+    pub fn btn(&self, player: usize, btn: Btn) -> bool {
+        self.buttons.btn(player, btn)
+    }
+
+    pub fn btnp(&self, player: usize, btn: Btn) -> bool {
+        self.buttons.btnp(player, btn)
+    }
+
+    pub fn released(&self, player: usize, btn: Btn) -> bool {
+        self.buttons.released(player, btn)
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Replaces the keymap, releasing every button since the keys holding
+    /// them may not be bound anymore.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.buttons.release_all();
     }
 
     pub fn get_prompt(&self) -> (usize, &str) {
@@ -166,6 +806,8 @@ impl Game {
     }
 
     pub fn update_prompt(&mut self, c: char) {
+        self.message = None;
+
         let byte_cursor = self
             .status
             .chars()
@@ -197,15 +839,409 @@ impl Game {
         }
     }
 
-    pub fn run_prompt(&mut self) {
-        p(&self.status);
-        self.status.clear();
+    /// Text typed in the code editor
+    pub fn update_code(&mut self, c: char) {
+        self.code_edit.input(c);
+        self.code_edit.scroll_to_cursor();
+    }
+
+    pub fn run_prompt(&mut self, ctx: &mut Context) {
+        let line = std::mem::take(&mut self.status);
         self.cursor = 0;
+
+        self.console.push(&format!("# {}", line), Pal::Gray);
+        self.console.add_history(&line);
+        if let Err(err) = self.console.save_history(&console::history_path(ctx)) {
+            let message = format!("could not save history: {}", command::error_message(&err));
+            self.console.push(&message, Pal::Red);
+        }
+
+        self.message = match Commands::run_line(ctx, self, &line) {
+            Ok(output) => {
+                self.console.push(&output, Pal::White);
+                None
+            }
+            Err(err) => {
+                let message = command::error_message(&err);
+                self.console.push(&message, Pal::Red);
+                Some((message, true))
+            }
+        };
+    }
+
+    /// Runs a command bound to a key, reporting its output in the console
+    /// and the message line.
+    pub fn run_command(&mut self, ctx: &mut Context, line: &str) {
+        let result = Commands::run_line(ctx, self, line);
+        self.report(result);
+    }
+
+    /// Shows the output of something done from a key in the console and
+    /// the message line, errors in red. Empty outputs show nothing.
+    pub fn report(&mut self, result: GameResult<String>) {
+        self.message = match result {
+            Ok(output) if output.is_empty() => return,
+            Ok(output) => {
+                self.console.push(&output, Pal::White);
+                Some((output, false))
+            }
+            Err(err) => {
+                let message = command::error_message(&err);
+                self.console.push(&message, Pal::Red);
+                Some((message, true))
+            }
+        };
+    }
+
+    /// Completes the word under the cursor, listing the candidates in the
+    /// console when there's more than one.
+    pub fn complete_prompt(&mut self) {
+        let end = self.byte_cursor();
+        let completed = complete::complete(&self.commands, &self.cwd, &self.status, end);
+
+        if let Some(replacement) = completed.replacement {
+            self.status
+                .replace_range(completed.start..completed.end, &replacement);
+            self.cursor = self.status[..completed.start + replacement.len()]
+                .chars()
+                .count();
+        } else if !completed.candidates.is_empty() {
+            self.console.push(&format!("# {}", self.status), Pal::Gray);
+            self.console.push(
+                &complete::columns(&completed.candidates, self.console.columns()),
+                Pal::LightGray,
+            );
+        }
+    }
+
+    fn byte_cursor(&self) -> usize {
+        self.status
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.status.len())
+    }
+
+    /// Restores the prompt history of previous sessions.
+    pub fn load_history(&mut self, ctx: &mut Context) {
+        if let Err(err) = self.console.load_history(&console::history_path(ctx)) {
+            let message = format!("could not load history: {}", command::error_message(&err));
+            self.console.push(&message, Pal::Red);
+        }
+    }
+
+    /// Restores the keymap of the user, keeping the default one on error.
+    pub fn load_keymap(&mut self, ctx: &mut Context) {
+        match Keymap::load(ctx) {
+            Ok(keymap) => self.set_keymap(keymap),
+            Err(err) => {
+                let message = format!("could not load keymap: {}", command::error_message(&err));
+                self.console.push(&message, Pal::Red);
+            }
+        }
+    }
+
+    pub fn get_map_edit(&self) -> &MapEdit {
+        &self.map_edit
+    }
+
+    pub fn get_sprite_edit(&self) -> &SpriteEdit {
+        &self.sprite_edit
+    }
+
+    pub fn get_sprite_edit_mut(&mut self) -> &mut SpriteEdit {
+        &mut self.sprite_edit
+    }
+
+    /// Saves the edited tile so the next stroke can be undone.
+    pub fn push_sprite_undo(&mut self) {
+        let sheet = self.ui.sheet(self.sprite_edit.tileset);
+        self.sprite_edit.push_undo(sheet);
+    }
+
+    /// Applies the sprite editor tool to a pixel of the edited tile.
+    pub fn paint_sprite(&mut self, x: u16, y: u16, erase: bool) {
+        let sheet = self.ui.sheet_mut(self.sprite_edit.tileset);
+        self.sprite_edit.apply(sheet, x, y, erase);
+    }
+
+    pub fn get_code_edit(&self) -> &CodeEdit {
+        &self.code_edit
+    }
+
+    pub fn get_code_edit_mut(&mut self) -> &mut CodeEdit {
+        &mut self.code_edit
+    }
+
+    pub fn get_sfx_edit(&self) -> &SfxEdit {
+        &self.sfx_edit
+    }
+
+    pub fn get_sfx_edit_mut(&mut self) -> &mut SfxEdit {
+        &mut self.sfx_edit
+    }
+
+    /// Plays one of the cart sound effects, unknown ones are ignored.
+    pub fn play_sfx(&mut self, index: usize) {
+        if let Some(sfx) = self.sfx.get(index) {
+            self.audio.play(synth::render(sfx, synth::SAMPLE_RATE));
+        }
+    }
+
+    pub fn get_music_edit(&self) -> &MusicEdit {
+        &self.music_edit
+    }
+
+    /// Plays the song from a position of its order, `None` stops it.
+    pub fn play_music(&mut self, position: Option<usize>) {
+        match position {
+            Some(position) if position < self.song.order.len() => {
+                let track = self
+                    .tracks
+                    .render(&self.song, &self.sfx, position, synth::SAMPLE_RATE);
+                self.audio.play_music(track.clone());
+                self.music_start = Some((position, self.dt));
+            }
+            _ => {
+                self.audio.stop_music();
+                self.music_start = None;
+            }
+        }
+    }
+
+    pub fn get_audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    pub fn get_console(&self) -> &Console {
+        &self.console
+    }
+
+    /// Output of the last command and whether it failed.
+    pub fn get_message(&self) -> Option<(&str, bool)> {
+        self.message
+            .as_ref()
+            .map(|(text, is_error)| (text.as_str(), *is_error))
+    }
+
+    pub fn register_command<F>(&mut self, name: &str, usage: &str, help: &str, f: F)
+    where
+        F: Fn(&mut Context, &mut Game, &[String]) -> GameResult<String> + 'static,
+    {
+        self.commands.register(name, usage, help, f);
+    }
+
+    pub fn get_commands(&self) -> &Commands {
+        &self.commands
+    }
+
+    /// Resolves a path typed in the prompt against the current directory.
+    pub fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.cwd.join(path)
+    }
+
+    pub fn get_cart(&self) -> &Cart {
+        &self.cart
+    }
+
+    /// Resizes the virtual screen, in tiles, the text boxes and the map
+    /// follow it.
+    pub fn set_screen(&mut self, columns: u16, rows: u16) -> GameResult {
+        self.ui.set_screen(columns, rows)?;
+        self.fit_screen();
+
+        Ok(())
+    }
+
+    /// Sizes the console and code editor boxes to the screen, and grows the
+    /// map to cover the viewport.
+    fn fit_screen(&mut self) {
+        let (rows, columns) = text_box_size(&self.ui);
+        self.console.set_size(rows, columns);
+        self.code_edit.set_size(rows, columns);
+
+        let (width, height) = self.ui.get_mouse().get_viewport();
+        self.map.grow(width, height);
+    }
+
+    pub fn load_cart(&mut self, ctx: &mut Context, path: impl AsRef<Path>) -> GameResult {
+        let cart = Cart::load(&path)?;
+        self.set_cart(ctx, cart)?;
+        self.cart_path = Some(path.as_ref().to_path_buf());
+
+        Ok(())
+    }
+
+    /// Replaces the cart being edited, the player uses it for the cart
+    /// bundled with it.
+    pub fn set_cart(&mut self, ctx: &mut Context, cart: Cart) -> GameResult {
+        let data = CartData::decode(&cart)?;
+        self.ui.load_cart(ctx, &cart)?;
+        self.read_cart(cart, data);
+
+        Ok(())
+    }
+
+    /// Replaces the map, sound and code with the ones of a cart.
+    fn read_cart(&mut self, cart: Cart, data: CartData) {
+        self.ui.set_win(data.win);
+        self.map = data.map;
+        self.sfx = data.sfx;
+        self.song = data.song;
+        self.music_edit = MusicEdit::default();
+        self.code_edit = CodeEdit::new(&cart.code);
+        self.fit_screen();
+        self.ui.set_palette(Palette::from_overrides(&cart.palette));
+        self.cart = cart;
+    }
+
+    pub fn save_cart(&mut self, ctx: &mut Context, path: impl AsRef<Path>) -> GameResult {
+        let cart = self.bundle_cart(ctx)?;
+        cart.save(&path)?;
+        self.cart = cart;
+        self.cart_path = Some(path.as_ref().to_path_buf());
+
+        Ok(())
+    }
+
+    /// The cart as edited, with every resource it uses so it can run on its
+    /// own.
+    pub fn bundle_cart(&self, ctx: &mut Context) -> GameResult<Cart> {
+        let mut cart = self.write_cart()?;
+        cart.fill_from_resources(ctx)?;
+
+        Ok(cart)
+    }
+
+    /// The cart with what was edited since it was loaded.
+    fn write_cart(&self) -> GameResult<Cart> {
+        let mut cart = self.cart.clone();
+        cart.code = self.code_edit.text();
+        cart.map = self.map.to_bytes();
+        cart.sfx = if self.sfx.iter().all(Sfx::is_empty) {
+            Vec::new()
+        } else {
+            sfx::to_bytes(&self.sfx)
+        };
+        cart.music = if self.song == Song::default() {
+            Vec::new()
+        } else {
+            self.song.to_bytes()
+        };
+        self.ui.store_cart(&mut cart)?;
+
+        Ok(cart)
+    }
+
+    /// Snapshots the running game: the cart as edited, its script
+    /// variables, the song position, the prompt and `dt`.
+    pub fn save_state(&self) -> GameResult<SaveState> {
+        let mut cart = self.write_cart()?;
+        self.ui.store_sheets(&mut cart)?;
+
+        let music = match self.music_start {
+            Some((start, dt)) if self.audio.is_music_playing() => {
+                self.song.position_after(start, self.dt.wrapping_sub(dt))
+            }
+            _ => None,
+        };
+        let script = self
+            .script
+            .as_ref()
+            .map(ScriptSystem::save_state)
+            .transpose()?;
+
+        Ok(SaveState {
+            dt: self.dt,
+            cursor: self.cursor,
+            status: self.status.clone(),
+            music,
+            script,
+            cart,
+        })
+    }
+
+    /// Resumes a snapshot taken by `save_state`. The song starts again
+    /// from the beginning of the position it was playing.
+    pub fn restore_state(&mut self, state: SaveState) -> GameResult {
+        // everything that can fail comes first, a bad state changes nothing
+        let script = state
+            .script
+            .as_ref()
+            .map(ScriptSystem::restore)
+            .transpose()?;
+        let sheets = ui::CartSheets::decode(&state.cart)?;
+        let data = CartData::decode(&state.cart)?;
+
+        self.ui.set_sheets(sheets);
+        self.read_cart(state.cart, data);
+
+        self.stop_replay();
+        self.dt = state.dt;
+        self.ui.dt = state.dt;
+        self.cursor = state.cursor.min(state.status.chars().count());
+        self.status = state.status;
+        self.buttons = ButtonState::default();
+        self.script = script;
+        self.play_music(state.music);
+
+        Ok(())
+    }
+
+    /// Keeps a snapshot in memory for `quick_load`.
+    pub fn quick_save(&mut self) -> GameResult {
+        self.quick_state = Some(self.save_state()?);
+
+        Ok(())
+    }
+
+    pub fn quick_load(&mut self) -> GameResult {
+        match self.quick_state.clone() {
+            Some(state) => self.restore_state(state),
+            None => Err(command::error("no state saved")),
+        }
+    }
+
+    /// Starts the cart code, replacing the script already running.
+    pub fn run_cart(&mut self) -> GameResult {
+        self.run_cart_with_seed(rng::time_seed())
+    }
+
+    /// Starts the cart code with its random generator seeded.
+    pub fn run_cart_with_seed(&mut self, seed: u64) -> GameResult {
+        self.cart.code = self.code_edit.text();
+        let script = ScriptSystem::with_seed(&self.cart.code, seed)?;
+        self.seed = seed;
+        self.set_script(script);
+
+        Ok(())
+    }
+
+    /// Seed of the random generator of the running cart
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_script(&mut self, script: ScriptSystem) {
+        // init() runs as the script loads
+        for error in script.take_errors() {
+            self.console.push(&error, Pal::Red);
+        }
+        self.script = Some(script);
+    }
+
+    pub fn get_script(&self) -> Option<&ScriptSystem> {
+        self.script.as_ref()
     }
 
     pub fn get_status(&self) -> &str {
         match self.mode {
-            GameMode::Normal => "",
+            GameMode::Normal
+            | GameMode::Map
+            | GameMode::Sprite
+            | GameMode::Code
+            | GameMode::Sfx
+            | GameMode::Music => "",
             GameMode::Prompt => &self.status,
         }
     }
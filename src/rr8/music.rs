@@ -0,0 +1,303 @@
+use ggez::GameError;
+
+use crate::sfx::{Step, SFX_STEPS};
+use crate::synth::{tick_samples, to_wav, Voice};
+use crate::*;
+
+pub const CHANNELS: usize = 4;
+pub const PATTERN_COUNT: usize = 64;
+/// Positions in the pattern order
+pub const MAX_ORDER: usize = 255;
+
+const MUSIC_FORMAT: u8 = 1;
+/// Stored for channels that don't play anything
+const NO_SFX: u8 = 0xFF;
+
+/// One sound effect per channel, played together
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pattern {
+    pub channels: [Option<u8>; CHANNELS],
+}
+
+/// The patterns to play one after the other, every step of every channel
+/// lasts `tempo` ticks of 1/120th of a second.
+///
+/// When the position `loop_end` is done playing the song goes back to
+/// `loop_start`, starting after `loop_end` plays the rest of the song
+/// once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Song {
+    pub tempo: u8,
+    pub patterns: Vec<Pattern>,
+    /// Pattern played at each position
+    pub order: Vec<u8>,
+    pub loop_start: usize,
+    pub loop_end: usize,
+}
+
+impl Default for Song {
+    fn default() -> Self {
+        Self {
+            tempo: 16,
+            patterns: vec![Pattern::default(); PATTERN_COUNT],
+            order: vec![0],
+            loop_start: 0,
+            loop_end: 0,
+        }
+    }
+}
+
+/// A song rendered to samples: `intro` plays once, then `looped` repeats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Track {
+    pub intro: Vec<f32>,
+    pub looped: Vec<f32>,
+}
+
+impl Song {
+    pub fn pattern_at(&self, position: usize) -> Option<&Pattern> {
+        let index = *self.order.get(position)?;
+        self.patterns.get(index as usize)
+    }
+
+    /// Positions played once and positions looped when starting at `start`
+    pub fn positions(&self, start: usize) -> (Vec<usize>, Vec<usize>) {
+        let end = self.loop_end.min(self.order.len().saturating_sub(1));
+        if start > end || self.loop_start > end {
+            ((start..self.order.len()).collect(), Vec::new())
+        } else if start < self.loop_start {
+            (
+                (start..self.loop_start).collect(),
+                (self.loop_start..=end).collect(),
+            )
+        } else {
+            ((start..=end).collect(), (self.loop_start..=end).collect())
+        }
+    }
+
+    /// Position playing `ticks` game ticks after starting at `start`, `None`
+    /// once a song without loop is over.
+    pub fn position_after(&self, start: usize, ticks: u32) -> Option<usize> {
+        let (intro, looped) = self.positions(start);
+        let per_position =
+            SFX_STEPS as u32 * self.tempo as u32 * tick::TICK_RATE / synth::TICK_RATE;
+        let played = (ticks / per_position.max(1)) as usize;
+
+        if played < intro.len() {
+            Some(intro[played])
+        } else if looped.is_empty() {
+            None
+        } else {
+            Some(looped[(played - intro.len()) % looped.len()])
+        }
+    }
+
+    /// Renders the song from a position, mixing the channels.
+    pub fn render(&self, sfx: &[Sfx], start: usize, rate: u32) -> Track {
+        let (intro, looped) = self.positions(start);
+        let mut voices = vec![Voice::new(rate); CHANNELS];
+
+        Track {
+            intro: self.render_positions(sfx, &intro, &mut voices, rate),
+            looped: self.render_positions(sfx, &looped, &mut voices, rate),
+        }
+    }
+
+    fn render_positions(
+        &self,
+        sfx: &[Sfx],
+        positions: &[usize],
+        voices: &mut [Voice],
+        rate: u32,
+    ) -> Vec<f32> {
+        let len = tick_samples(self.tempo, rate);
+        let mut mix = vec![0.; positions.len() * SFX_STEPS * len];
+        let rest = vec![Step::default(); SFX_STEPS];
+
+        for (i, position) in positions.iter().enumerate() {
+            let pattern = self.pattern_at(*position).copied().unwrap_or_default();
+
+            for (channel, voice) in pattern.channels.iter().zip(voices.iter_mut()) {
+                let steps = channel
+                    .and_then(|n| sfx.get(n as usize))
+                    .map(|s| &s.steps)
+                    .unwrap_or(&rest);
+
+                // silent channels still play rests, so notes fade out
+                let mut samples = Vec::with_capacity(SFX_STEPS * len);
+                let mut previous = None;
+                for step in steps.iter() {
+                    voice.play_step(step, previous, len, &mut samples);
+                    previous = Some(step);
+                }
+
+                let offset = i * SFX_STEPS * len;
+                for (out, sample) in mix[offset..].iter_mut().zip(samples) {
+                    *out += sample;
+                }
+            }
+        }
+
+        mix
+    }
+
+    /// A WAV file of the song playing its loop once.
+    pub fn to_wav(&self, sfx: &[Sfx], rate: u32) -> Vec<u8> {
+        let track = self.render(sfx, 0, rate);
+        let mut samples = track.intro;
+        samples.extend(track.looped);
+
+        to_wav(&samples, rate)
+    }
+
+    /// Binary form stored in carts: a header with the format, tempo and
+    /// loop points, the order and then every pattern as one sfx per
+    /// channel, 0xff for none.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![
+            MUSIC_FORMAT,
+            self.tempo,
+            self.loop_start as u8,
+            self.loop_end as u8,
+            self.order.len() as u8,
+        ];
+        data.extend_from_slice(&self.order);
+
+        data.push(self.patterns.len() as u8);
+        for pattern in self.patterns.iter() {
+            for channel in pattern.channels.iter() {
+                data.push(channel.unwrap_or(NO_SFX));
+            }
+        }
+
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> GameResult<Self> {
+        let error = || GameError::ResourceLoadError("music: truncated data".to_string());
+        let mut bytes = data.iter().copied();
+        let mut next = || bytes.next().ok_or_else(error);
+
+        let format = next()?;
+        if format != MUSIC_FORMAT {
+            return Err(GameError::ResourceLoadError(format!(
+                "music: unknown format {}",
+                format
+            )));
+        }
+        let tempo = next()?.max(1);
+        let loop_start = next()? as usize;
+        let loop_end = next()? as usize;
+
+        let mut order = Vec::new();
+        for _ in 0..next()? {
+            order.push(next()?);
+        }
+        if order.is_empty() {
+            order.push(0);
+        }
+
+        let mut patterns = Vec::new();
+        for _ in 0..next()? {
+            let mut pattern = Pattern::default();
+            for channel in pattern.channels.iter_mut() {
+                let n = next()?;
+                *channel = if n == NO_SFX { None } else { Some(n) };
+            }
+            patterns.push(pattern);
+        }
+        patterns.resize(PATTERN_COUNT.max(patterns.len()), Pattern::default());
+        if let Some(n) = order.iter().find(|&&n| n as usize >= patterns.len()) {
+            return Err(GameError::ResourceLoadError(format!(
+                "music: no pattern {} in the order",
+                n
+            )));
+        }
+
+        Ok(Self {
+            tempo,
+            patterns,
+            order,
+            loop_start,
+            loop_end,
+        })
+    }
+}
+
+/// The last song rendered, so playing it again doesn't render it again.
+#[derive(Debug, Default)]
+pub struct TrackCache {
+    key: Option<(Song, Vec<Sfx>, usize, u32)>,
+    track: Track,
+}
+
+impl TrackCache {
+    /// Same as `Song::render`, reusing the last track when nothing changed.
+    pub fn render(&mut self, song: &Song, sfx: &[Sfx], start: usize, rate: u32) -> &Track {
+        let fresh = match &self.key {
+            Some((s, x, p, r)) => s == song && x.as_slice() == sfx && *p == start && *r == rate,
+            None => false,
+        };
+        if !fresh {
+            self.track = song.render(sfx, start, rate);
+            self.key = Some((song.clone(), sfx.to_vec(), start, rate));
+        }
+
+        &self.track
+    }
+}
+
+/// State of the music editor: the position in the order and the channel
+/// being edited.
+#[derive(Debug, Default)]
+pub struct MusicEdit {
+    pub position: usize,
+    pub channel: usize,
+}
+
+impl MusicEdit {
+    pub fn move_position(&mut self, step: isize, song: &Song) {
+        let count = song.order.len() as isize;
+        self.position = (self.position as isize + step).rem_euclid(count) as usize;
+    }
+
+    pub fn move_channel(&mut self, step: isize) {
+        let count = CHANNELS as isize;
+        self.channel = (self.channel as isize + step).rem_euclid(count) as usize;
+    }
+
+    /// Adds a position after the current one playing the next pattern.
+    pub fn insert(&mut self, song: &mut Song) {
+        if song.order.len() >= MAX_ORDER {
+            return;
+        }
+        let next = song.order[self.position].saturating_add(1) % PATTERN_COUNT as u8;
+        self.position += 1;
+        song.order.insert(self.position, next);
+    }
+
+    pub fn remove(&mut self, song: &mut Song) {
+        if song.order.len() > 1 {
+            song.order.remove(self.position);
+            self.position = self.position.min(song.order.len() - 1);
+        }
+    }
+
+    /// Changes the pattern played at the current position.
+    pub fn next_pattern(&mut self, step: isize, song: &mut Song) {
+        let count = song.patterns.len() as isize;
+        let index = &mut song.order[self.position];
+        *index = (*index as isize + step).rem_euclid(count) as u8;
+    }
+
+    /// Changes the sfx of the current channel, going through no sfx.
+    pub fn next_sfx(&mut self, step: isize, song: &mut Song) {
+        let index = song.order[self.position] as usize;
+        let channel = &mut song.patterns[index].channels[self.channel];
+        // -1 stands for no sfx
+        let count = sfx::SFX_COUNT as isize + 1;
+        let current = channel.map(|n| n as isize).unwrap_or(-1);
+        let next = (current + 1 + step).rem_euclid(count) - 1;
+        *channel = if next < 0 { None } else { Some(next as u8) };
+    }
+}
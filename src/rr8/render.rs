@@ -0,0 +1,248 @@
+pub mod gpu;
+pub mod soft;
+
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ggez::filesystem;
+use ggez::graphics::{Color, Rect};
+use ggez::nalgebra::Point2;
+
+use crate::*;
+
+static NEXT_SHEET_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Index of the transparent pixels of a `Sheet`
+pub const TRANSPARENT: u8 = 0xff;
+
+/// Pixels of a tileset or font as indices into `Pal::ALL`, kept on the CPU
+/// so every renderer can draw from them. `SheetColors` tells renderers what
+/// each index looks like.
+#[derive(Clone, Debug)]
+pub struct Sheet {
+    id: usize,
+    version: u32,
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+}
+
+impl Sheet {
+    pub fn from_indices(width: u16, height: u16, pixels: Vec<u8>) -> GameResult<Self> {
+        if pixels.len() != width as usize * height as usize {
+            return Err(ggez::GameError::ResourceLoadError(format!(
+                "{} pixels is not a {}x{} image",
+                pixels.len(),
+                width,
+                height
+            )));
+        }
+        if let Some(i) = pixels
+            .iter()
+            .find(|i| **i != TRANSPARENT && Pal::from_index(**i as usize).is_none())
+        {
+            return Err(ggez::GameError::ResourceLoadError(format!(
+                "{} is not a palette index",
+                i
+            )));
+        }
+
+        Ok(Self {
+            id: NEXT_SHEET_ID.fetch_add(1, Ordering::Relaxed),
+            version: 0,
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Converts an RGBA image to the nearest colors of the default palette,
+    /// pixels less than half opaque are transparent.
+    pub fn from_rgba(width: u16, height: u16, pixels: Vec<u8>) -> GameResult<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(ggez::GameError::ResourceLoadError(format!(
+                "{} bytes is not a {}x{} RGBA image",
+                pixels.len(),
+                width,
+                height
+            )));
+        }
+
+        let palette = Palette::default();
+        let indices = pixels
+            .chunks(4)
+            .map(|p| {
+                if p[3] < 0x80 {
+                    TRANSPARENT
+                } else {
+                    palette.nearest([p[0], p[1], p[2], p[3]]) as u8
+                }
+            })
+            .collect();
+
+        Self::from_indices(width, height, indices)
+    }
+
+    pub fn from_png(data: &[u8]) -> GameResult<Self> {
+        let image = image::load_from_memory(data)
+            .map_err(|e| ggez::GameError::ResourceLoadError(e.to_string()))?
+            .to_rgba();
+        let (w, h) = image.dimensions();
+
+        Self::from_rgba(w as u16, h as u16, image.into_raw())
+    }
+
+    /// Loads a PNG from the ggez resource paths.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Self> {
+        let mut data = Vec::new();
+        filesystem::open(ctx, path)?.read_to_end(&mut data)?;
+
+        Self::from_png(&data)
+    }
+
+    /// Loads a PNG from disk, for when there's no `Context` around.
+    pub fn load_file(path: impl AsRef<Path>) -> GameResult<Self> {
+        Self::from_png(&std::fs::read(path)?)
+    }
+
+    /// Saves the sheet in the default palette, so loading it back gives the
+    /// same indices whatever palette the cart uses.
+    pub fn to_png(&self) -> GameResult<Vec<u8>> {
+        let colors = SheetColors::from_palette(&Palette::default());
+        let mut data = Vec::new();
+        image::png::PNGEncoder::new(&mut data).encode(
+            &self.to_rgba(&colors),
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::RGBA(8),
+        )?;
+
+        Ok(data)
+    }
+
+    /// RGBA bytes of the sheet drawn with some colors, to upload textures.
+    pub fn to_rgba(&self, colors: &SheetColors) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|i| colors.get(*i).unwrap_or([0; 4]).to_vec())
+            .collect()
+    }
+
+    /// Identifies the sheet in texture caches.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Bumped on every change, so caches know when to upload it again.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Palette indices, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Pixels outside the sheet are transparent.
+    pub fn pixel(&self, x: u16, y: u16) -> u8 {
+        if x >= self.width || y >= self.height {
+            return TRANSPARENT;
+        }
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Pixels outside the sheet are ignored.
+    pub fn set_pixel(&mut self, x: u16, y: u16, index: u8) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.pixels[y as usize * self.width as usize + x as usize] = index;
+        self.version = self.version.wrapping_add(1);
+    }
+}
+
+/// The RGBA color each palette index of a sheet is drawn with, `None` for
+/// the ones that aren't drawn. They only depend on the palette and remap,
+/// so textures are cached per sheet and colors while the color a tile is
+/// drawn with tints it at draw time.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SheetColors([Option<[u8; 4]>; 17]);
+
+impl SheetColors {
+    pub fn new(colors: [Option<[u8; 4]>; 17]) -> Self {
+        Self(colors)
+    }
+
+    /// Every index in its color of a palette
+    pub fn from_palette(palette: &Palette) -> Self {
+        let mut colors = [None; 17];
+        for (color, pal) in colors.iter_mut().zip(Pal::ALL.iter()) {
+            let (r, g, b) = palette.rgb(*pal);
+            *color = Some([r, g, b, 0xff]);
+        }
+
+        Self(colors)
+    }
+
+    pub fn get(&self, index: u8) -> Option<[u8; 4]> {
+        self.0.get(index as usize).copied().flatten()
+    }
+}
+
+/// Where the virtual screen lands in a window: scaled by the largest whole
+/// number that fits and centered, the bars around it stay black.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Letterbox {
+    pub scale: f32,
+    /// Top left corner of the screen in the window
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Letterbox {
+    /// Fits a `screen` in a `window`, both in pixels. Windows smaller than
+    /// the screen crop it rather than scale it down.
+    pub fn fit(screen: (f32, f32), window: (f32, f32)) -> Self {
+        let scale = (window.0 / screen.0)
+            .min(window.1 / screen.1)
+            .floor()
+            .max(1.);
+
+        Self {
+            scale,
+            x: ((window.0 - screen.0 * scale) / 2.).floor(),
+            y: ((window.1 - screen.1 * scale) / 2.).floor(),
+        }
+    }
+
+    /// Converts window coordinates to pixels of the virtual screen
+    pub fn to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.x) / self.scale, (y - self.y) / self.scale)
+    }
+}
+
+/// A target `Ui` draws to, coordinates are pixels of the virtual screen.
+pub trait Renderer {
+    fn rect(&mut self, rect: Rect, color: Color) -> GameResult;
+
+    /// Draws the `src` pixels of a sheet in `colors`, multiplied by `tint`.
+    /// Flipped sprites are rotated by half a turn.
+    fn sprite(
+        &mut self,
+        sheet: &Sheet,
+        src: Rect,
+        dest: Point2<f32>,
+        colors: &SheetColors,
+        tint: Color,
+        flip: bool,
+    ) -> GameResult;
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use ggez::conf;
+use ggez::graphics;
+use ggez::graphics::{spritebatch::SpriteBatch, Canvas, Color, DrawParam, FilterMode, Image, Rect};
+use ggez::nalgebra::{Point2, Vector2};
+
+use crate::render::{Letterbox, Renderer, Sheet, SheetColors};
+use crate::*;
+
+/// Textures kept before the least recently used one is dropped
+const MAX_TEXTURES: usize = 64;
+
+/// GPU copies of the sheets in the colors of a palette and remap, uploaded
+/// again when their version changes. Colors only change with the palette
+/// or the remap, a cycling palette replaces the oldest texture each frame.
+#[derive(Debug, Default)]
+pub struct Textures {
+    /// Version of the sheet, last use and texture
+    images: HashMap<(usize, SheetColors), (u32, u64, Image)>,
+    uses: u64,
+}
+
+impl Textures {
+    fn get(&mut self, ctx: &mut Context, sheet: &Sheet, colors: &SheetColors) -> GameResult<Image> {
+        let key = (sheet.id(), *colors);
+        self.uses += 1;
+
+        if let Some((version, used, image)) = self.images.get_mut(&key) {
+            if *version == sheet.version() {
+                *used = self.uses;
+                return Ok(image.clone());
+            }
+        }
+
+        if self.images.len() >= MAX_TEXTURES && !self.images.contains_key(&key) {
+            let oldest = self
+                .images
+                .iter()
+                .min_by_key(|(_, (_, used, _))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.images.remove(&oldest);
+            }
+        }
+
+        let pixels = sheet.to_rgba(colors);
+        let image = Image::from_rgba8(ctx, sheet.width(), sheet.height(), &pixels)?;
+        self.images
+            .insert(key, (sheet.version(), self.uses, image.clone()));
+
+        Ok(image)
+    }
+}
+
+/// The virtual screen on the GPU: the `Ui` draws to a canvas of its size,
+/// which is then scaled to the window with `Letterbox` so every virtual
+/// pixel is the same whole number of window pixels.
+pub struct Screen {
+    canvas: Canvas,
+    size: (f32, f32),
+}
+
+impl Screen {
+    pub fn new(ctx: &mut Context, win: Rect) -> GameResult<Self> {
+        let mut canvas = Canvas::new(ctx, win.w as u16, win.h as u16, conf::NumSamples::One)?;
+        canvas.set_filter(FilterMode::Nearest);
+
+        Ok(Self {
+            canvas,
+            size: (win.w, win.h),
+        })
+    }
+
+    /// Where the screen is in the window, to convert mouse coordinates
+    pub fn letterbox(&self, ctx: &Context) -> Letterbox {
+        let window = graphics::screen_coordinates(ctx);
+        Letterbox::fit(self.size, (window.w, window.h))
+    }
+
+    /// Draws the game to the canvas and the canvas to the window. The
+    /// canvas is made again when the cart changes the screen size.
+    pub fn draw(&mut self, ctx: &mut Context, textures: &mut Textures, game: &Game) -> GameResult {
+        let win = game.ui.get_win();
+        if (win.w, win.h) != self.size {
+            *self = Self::new(ctx, win)?;
+        }
+
+        let window = graphics::screen_coordinates(ctx);
+        let (w, h) = self.size;
+
+        graphics::set_canvas(ctx, Some(&self.canvas));
+        graphics::set_screen_coordinates(ctx, Rect::new(0., 0., w, h))?;
+        graphics::clear(ctx, (0, 0, 0).into());
+
+        let filter_mode = game.ui.get_filter_mode();
+        let mut renderer = GgezRenderer::new(ctx, textures, filter_mode);
+        game.ui.draw_all(&mut renderer, game)?;
+        renderer.finish()?;
+
+        graphics::set_canvas(ctx, None);
+        graphics::set_screen_coordinates(ctx, window)?;
+        graphics::clear(ctx, (0, 0, 0).into());
+
+        let letterbox = self.letterbox(ctx);
+        graphics::draw(
+            ctx,
+            &self.canvas,
+            DrawParam::default()
+                .dest(Point2::new(letterbox.x, letterbox.y))
+                .scale(Vector2::new(letterbox.scale, letterbox.scale)),
+        )
+    }
+}
+
+/// Draws through ggez in pixels of the virtual screen, consecutive sprites
+/// of the same sheet and colors go in a single `SpriteBatch` whatever their
+/// tint. Call `finish` to draw what's left in the batch.
+pub struct GgezRenderer<'a> {
+    ctx: &'a mut Context,
+    textures: &'a mut Textures,
+    filter_mode: FilterMode,
+    batch: Option<((usize, SheetColors), SpriteBatch)>,
+}
+
+impl<'a> GgezRenderer<'a> {
+    pub fn new(ctx: &'a mut Context, textures: &'a mut Textures, filter_mode: FilterMode) -> Self {
+        Self {
+            ctx,
+            textures,
+            filter_mode,
+            batch: None,
+        }
+    }
+
+    pub fn finish(mut self) -> GameResult {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> GameResult {
+        if let Some((_, batch)) = self.batch.take() {
+            graphics::draw(self.ctx, &batch, DrawParam::default())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Renderer for GgezRenderer<'a> {
+    fn rect(&mut self, rect: Rect, color: Color) -> GameResult {
+        self.flush()?;
+
+        let mesh =
+            graphics::Mesh::new_rectangle(self.ctx, graphics::DrawMode::fill(), rect, color)?;
+
+        graphics::draw(self.ctx, &mesh, DrawParam::default())
+    }
+
+    fn sprite(
+        &mut self,
+        sheet: &Sheet,
+        src: Rect,
+        dest: Point2<f32>,
+        colors: &SheetColors,
+        tint: Color,
+        flip: bool,
+    ) -> GameResult {
+        let key = (sheet.id(), *colors);
+        let same_texture = match &self.batch {
+            Some((batch_key, _)) => *batch_key == key,
+            None => false,
+        };
+        if !same_texture {
+            self.flush()?;
+            let image = self.textures.get(self.ctx, sheet, colors)?;
+            let mut batch = SpriteBatch::new(image);
+            batch.set_filter(self.filter_mode);
+            self.batch = Some((key, batch));
+        }
+
+        let (w, h) = (sheet.width() as f32, sheet.height() as f32);
+        let mut param = DrawParam::default()
+            .src(Rect::new(src.x / w, src.y / h, src.w / w, src.h / h))
+            .dest(dest)
+            .color(tint);
+        if flip {
+            param = param
+                .rotation(std::f32::consts::PI)
+                .offset(Point2::new(0.9, 0.9));
+        }
+
+        if let Some((_, batch)) = &mut self.batch {
+            batch.add(param);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use ggez::graphics::{Color, Rect};
+use ggez::nalgebra::Point2;
+
+use crate::render::{Renderer, Sheet, SheetColors};
+use crate::*;
+
+/// CPU renderer drawing to an RGBA buffer, it needs neither a window nor
+/// a GPU so it works in tests and CI.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Framebuffer {
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Wraps RGBA bytes, missing pixels are transparent.
+    pub fn from_rgba(width: u16, height: u16, mut pixels: Vec<u8>) -> Self {
+        pixels.resize(width as usize * height as usize * 4, 0);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// RGBA bytes, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    pub fn pixel(&self, x: u16, y: u16) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[i..i + 4]);
+
+        pixel
+    }
+
+    pub fn clear(&mut self, color: impl Into<Color>) {
+        let (r, g, b, a) = color.into().to_rgba();
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&[r, g, b, a]);
+        }
+    }
+
+    /// Blends a color over the pixel at (x, y), pixels out of the buffer
+    /// are clipped.
+    fn blend(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || color.a <= 0. {
+            return;
+        }
+
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let dst = &mut self.pixels[i..i + 4];
+        let a = color.a.min(1.);
+        let mix = |src: f32, dst: u8| -> u8 {
+            (src.min(1.) * 255. * a + dst as f32 * (1. - a)).round() as u8
+        };
+
+        dst[0] = mix(color.r, dst[0]);
+        dst[1] = mix(color.g, dst[1]);
+        dst[2] = mix(color.b, dst[2]);
+        dst[3] = (a * 255. + dst[3] as f32 * (1. - a)).round() as u8;
+    }
+}
+
+impl Renderer for Framebuffer {
+    fn rect(&mut self, rect: Rect, color: Color) -> GameResult {
+        let x0 = rect.x.round() as i32;
+        let y0 = rect.y.round() as i32;
+        let x1 = (rect.x + rect.w).round() as i32;
+        let y1 = (rect.y + rect.h).round() as i32;
+
+        for y in y0.max(0)..y1.min(self.height as i32) {
+            for x in x0.max(0)..x1.min(self.width as i32) {
+                self.blend(x, y, color);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sprite(
+        &mut self,
+        sheet: &Sheet,
+        src: Rect,
+        dest: Point2<f32>,
+        colors: &SheetColors,
+        tint: Color,
+        flip: bool,
+    ) -> GameResult {
+        let (sx, sy) = (src.x as i32, src.y as i32);
+        let (sw, sh) = (src.w as i32, src.h as i32);
+        let (dx, dy) = (dest.x.round() as i32, dest.y.round() as i32);
+
+        for y in 0..sh {
+            for x in 0..sw {
+                let (px, py) = if flip {
+                    (sx + sw - 1 - x, sy + sh - 1 - y)
+                } else {
+                    (sx + x, sy + y)
+                };
+                if px < 0 || py < 0 || px >= sheet.width() as i32 || py >= sheet.height() as i32 {
+                    continue;
+                }
+
+                if let Some([r, g, b, a]) = colors.get(sheet.pixel(px as u16, py as u16)) {
+                    let tinted = Color::new(
+                        r as f32 / 255. * tint.r,
+                        g as f32 / 255. * tint.g,
+                        b as f32 / 255. * tint.b,
+                        a as f32 / 255. * tint.a,
+                    );
+                    self.blend(dx + x, dy + y, tinted);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
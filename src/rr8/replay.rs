@@ -0,0 +1,203 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use ggez::event::MouseButton;
+use ggez::GameError;
+
+use crate::keymap::parse_player;
+use crate::*;
+
+const RECORDING_HEADER: &str = "rr8 recording";
+
+/// An input event fed to the game, keys and controllers are already turned
+/// into buttons
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Input {
+    Press(usize, Btn),
+    Release(usize, Btn),
+    MouseMove(f32, f32),
+    MouseButton(MouseButton, bool),
+}
+
+/// The inputs of a session, by the tick that sampled them.
+///
+/// Together with the seed of the cart random generator this is enough to
+/// run the session again. It's saved as a text file with an input per
+/// line, players are counted from 1 like in the keymap:
+///
+/// ```text
+/// rr8 recording
+/// seed 1234
+/// ticks 300
+/// # tick input
+/// 12 press 1 a
+/// 15 release 1 a
+/// 40 move 120.5 88
+/// 41 mouse left down
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    /// Ticks run while recording
+    pub ticks: u32,
+    pub inputs: Vec<(u32, Input)>,
+}
+
+impl Recording {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, tick: u32, input: Input) {
+        self.inputs.push((tick, input));
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> GameResult {
+        std::fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> GameResult<Self> {
+        let mut recording = Self::default();
+        let mut lines = text.lines().enumerate();
+
+        if lines.next().map(|(_, l)| l.trim()) != Some(RECORDING_HEADER) {
+            return Err(recording_error(1, "missing header"));
+        }
+
+        for (i, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |what: &str| recording_error(i + 1, what);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                ["seed", seed] => {
+                    recording.seed = seed.parse().map_err(|_| error("bad seed"))?;
+                }
+                ["ticks", ticks] => {
+                    recording.ticks = ticks.parse().map_err(|_| error("bad ticks"))?;
+                }
+                [tick, ref input @ ..] => {
+                    let tick = tick.parse::<u32>().map_err(|_| error("bad tick"))?;
+                    let input = parse_input(input).ok_or_else(|| error("bad input"))?;
+                    recording.push(tick, input);
+                }
+                [] => {}
+            }
+        }
+
+        Ok(recording)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "{}", RECORDING_HEADER).unwrap();
+        writeln!(out, "seed {}", self.seed).unwrap();
+        writeln!(out, "ticks {}", self.ticks).unwrap();
+        writeln!(out, "# tick input").unwrap();
+        for (tick, input) in self.inputs.iter() {
+            let input = match input {
+                Input::Press(player, btn) => format!("press {} {}", player + 1, btn.name()),
+                Input::Release(player, btn) => format!("release {} {}", player + 1, btn.name()),
+                Input::MouseMove(x, y) => format!("move {} {}", x, y),
+                Input::MouseButton(button, pressed) => format!(
+                    "mouse {} {}",
+                    mouse_button_name(*button),
+                    if *pressed { "down" } else { "up" }
+                ),
+            };
+            writeln!(out, "{} {}", tick, input).unwrap();
+        }
+
+        out
+    }
+}
+
+/// A recording being played back, handing out the inputs of each tick
+#[derive(Debug)]
+pub struct Replay {
+    recording: Recording,
+    next: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    pub fn get_recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Inputs sampled by a tick, ticks are expected in order.
+    pub fn inputs(&mut self, tick: u32) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        while let Some((t, input)) = self.recording.inputs.get(self.next) {
+            if *t > tick {
+                break;
+            }
+            inputs.push(*input);
+            self.next += 1;
+        }
+
+        inputs
+    }
+
+    /// Whether every recorded tick has run
+    pub fn is_done(&self, tick: u32) -> bool {
+        tick >= self.recording.ticks && self.next >= self.recording.inputs.len()
+    }
+}
+
+fn parse_input(words: &[&str]) -> Option<Input> {
+    match words {
+        ["press", player, btn] => Some(Input::Press(parse_player(player)?, Btn::from_name(btn)?)),
+        ["release", player, btn] => {
+            Some(Input::Release(parse_player(player)?, Btn::from_name(btn)?))
+        }
+        ["move", x, y] => Some(Input::MouseMove(x.parse().ok()?, y.parse().ok()?)),
+        ["mouse", button, state] => {
+            let pressed = match *state {
+                "down" => true,
+                "up" => false,
+                _ => return None,
+            };
+            Some(Input::MouseButton(mouse_button_from_name(button)?, pressed))
+        }
+        _ => None,
+    }
+}
+
+fn mouse_button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "left".to_string(),
+        MouseButton::Right => "right".to_string(),
+        MouseButton::Middle => "middle".to_string(),
+        MouseButton::Other(n) => n.to_string(),
+    }
+}
+
+fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    match name {
+        "left" => Some(MouseButton::Left),
+        "right" => Some(MouseButton::Right),
+        "middle" => Some(MouseButton::Middle),
+        n => n.parse().ok().map(MouseButton::Other),
+    }
+}
+
+fn recording_error(line: usize, msg: &str) -> GameError {
+    GameError::ResourceLoadError(format!("recording line {}: {}", line, msg))
+}
@@ -0,0 +1,565 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Map, Scope, AST, FLOAT, INT};
+
+use crate::render::Renderer;
+use crate::rng::Rng;
+use crate::ui::palette::Remap;
+use crate::ui::tile::TileParam;
+use crate::*;
+
+/// Functions registered for scripts, highlighted by the code editor
+pub const FUNCTIONS: &[&str] = &[
+    "text", "tile", "tile_alt", "btn", "btnp", "released", "sfx", "music", "rnd", "srand", "pal",
+    "palt",
+];
+
+/// Functions of the script called by `ScriptSystem`
+pub const CALLBACKS: &[&str] = &["init", "update", "draw"];
+
+/// Draw calls issued by the script, replayed against `Ui` once the script
+/// function returns (rhai closures can't borrow the context).
+#[derive(Debug, Clone)]
+enum ScriptCmd {
+    Text(String, f32, f32, Pal),
+    Tile(u16, u16, f32, f32, Pal, bool),
+    TileAlt(u16, u16, f32, f32, Pal, bool),
+    Remap(Remap),
+}
+
+/// Variables of a running script, values are stored as rhai expressions
+/// so they can be saved as text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptState {
+    pub source: String,
+    pub this: String,
+    /// Top-level variables by name, and whether they're constant
+    pub globals: Vec<(String, bool, String)>,
+    pub rng: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ScriptIo {
+    cmds: Vec<ScriptCmd>,
+    buttons: ButtonState,
+    errors: Vec<String>,
+    sounds: Vec<usize>,
+    music: Option<INT>,
+    rng: Rng,
+    remap: Remap,
+}
+
+/// Runs a rhai script as a `System`.
+///
+/// Top-level statements run once on load, then `init()`, `update()` and
+/// `draw()` are called if the script defines them. Script state lives in
+/// `this`, which is an object map shared by all calls:
+///
+/// ```rhai
+/// fn init() { this.x = 0; }
+/// fn update() { if btn(3) { this.x += 1; } }
+/// fn draw() { text("hello", this.x, 5, 9); }
+/// ```
+///
+/// Buttons and colors are passed as indices into `Btn::ALL` and `Pal::ALL`,
+/// `btn(b)` checks the first player and `btn(b, p)` the player p counting
+/// from 0, `btnp` and `released` take the same arguments. `sfx(n)` plays
+/// the n-th sound effect of the cart, `music(n)` plays the song from
+/// position n of its order and `music(-1)` stops it.
+///
+/// `rnd()` returns a float in `0..1` and `rnd(n)` an integer in `0..n`,
+/// from a generator seeded when the script starts (`srand(seed)` reseeds
+/// it).
+///
+/// `draw()` runs once per frame rather than once per tick, so it works on a
+/// copy of the state that is thrown away afterwards: changes it makes to
+/// `this` or to globals, numbers it draws and sounds it plays are dropped.
+/// The same inputs on the same ticks always give the same game.
+///
+/// `pal(a, b)` draws color b in place of color a and `palt(c, true)`
+/// makes color c transparent, for every draw that follows until `pal()`
+/// resets both.
+pub struct ScriptSystem {
+    source: String,
+    engine: Engine,
+    ast: AST,
+    scope: RefCell<Scope<'static>>,
+    this: RefCell<Dynamic>,
+    io: Rc<RefCell<ScriptIo>>,
+    error: RefCell<Option<String>>,
+}
+
+impl fmt::Debug for ScriptSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptSystem")
+            .field("this", &self.this)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl ScriptSystem {
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let source = std::fs::read_to_string(path)?;
+        Self::new(&source)
+    }
+
+    pub fn new(source: &str) -> GameResult<Self> {
+        Self::with_seed(source, 0)
+    }
+
+    /// Starts a script with its random generator seeded.
+    pub fn with_seed(source: &str, seed: u64) -> GameResult<Self> {
+        let io = Rc::new(RefCell::new(ScriptIo {
+            rng: Rng::new(seed),
+            ..ScriptIo::default()
+        }));
+        let engine = Self::build_engine(&io);
+
+        let ast = engine.compile(source).map_err(script_error)?;
+
+        let mut scope = Scope::new();
+        engine
+            .consume_ast_with_scope(&mut scope, &ast)
+            .map_err(script_error)?;
+
+        let system = Self {
+            source: source.to_string(),
+            engine,
+            ast,
+            scope: RefCell::new(scope),
+            this: RefCell::new(Dynamic::from(Map::new())),
+            io,
+            error: RefCell::new(None),
+        };
+        system.call("init");
+
+        Ok(system)
+    }
+
+    /// Resumes a script from a saved state, without running its top-level
+    /// statements or `init()` again.
+    pub fn restore(state: &ScriptState) -> GameResult<Self> {
+        let io = Rc::new(RefCell::new(ScriptIo {
+            rng: Rng::new(state.rng),
+            ..ScriptIo::default()
+        }));
+        let engine = Self::build_engine(&io);
+
+        let ast = engine.compile(&state.source).map_err(script_error)?;
+
+        let mut scope = Scope::new();
+        for (name, constant, value) in state.globals.iter() {
+            let value = from_expr(&engine, value)?;
+            if *constant {
+                scope.push_constant_dynamic(name.clone(), value);
+            } else {
+                scope.push_dynamic(name.clone(), value);
+            }
+        }
+        let this = from_expr(&engine, &state.this)?;
+
+        Ok(Self {
+            source: state.source.clone(),
+            engine,
+            ast,
+            scope: RefCell::new(scope),
+            this: RefCell::new(this),
+            io,
+            error: RefCell::new(state.error.clone()),
+        })
+    }
+
+    /// Saves the variables of the script, fails on values that can't be
+    /// written as rhai literals like function pointers.
+    pub fn save_state(&self) -> GameResult<ScriptState> {
+        let globals = self
+            .scope
+            .borrow()
+            .iter()
+            .map(|(name, constant, value)| Ok((name.to_string(), constant, to_expr(&value)?)))
+            .collect::<GameResult<_>>()?;
+
+        Ok(ScriptState {
+            source: self.source.clone(),
+            this: to_expr(&self.this.borrow())?,
+            globals,
+            rng: self.io.borrow().rng.state(),
+            error: self.error(),
+        })
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+
+    /// Errors raised since the last call, for the console
+    pub fn take_errors(&self) -> Vec<String> {
+        std::mem::take(&mut self.io.borrow_mut().errors)
+    }
+
+    fn build_engine(io: &Rc<RefCell<ScriptIo>>) -> Engine {
+        let mut engine = Engine::new();
+
+        let cmds = io.clone();
+        engine.register_fn("text", move |text: &str, x: Dynamic, y: Dynamic, c: INT| {
+            cmds.borrow_mut().cmds.push(ScriptCmd::Text(
+                text.to_string(),
+                num(&x),
+                num(&y),
+                pal(c),
+            ));
+        });
+
+        let cmds = io.clone();
+        engine.register_result_fn(
+            "tile",
+            move |row: INT, column: INT, x: Dynamic, y: Dynamic, c: INT| -> ScriptResult {
+                cmds.borrow_mut().cmds.push(ScriptCmd::Tile(
+                    tile_index("row", row)?,
+                    tile_index("column", column)?,
+                    num(&x),
+                    num(&y),
+                    pal(c),
+                    false,
+                ));
+                Ok(())
+            },
+        );
+
+        let cmds = io.clone();
+        engine.register_result_fn(
+            "tile",
+            move |row: INT,
+                  column: INT,
+                  x: Dynamic,
+                  y: Dynamic,
+                  c: INT,
+                  flip: bool|
+                  -> ScriptResult {
+                cmds.borrow_mut().cmds.push(ScriptCmd::Tile(
+                    tile_index("row", row)?,
+                    tile_index("column", column)?,
+                    num(&x),
+                    num(&y),
+                    pal(c),
+                    flip,
+                ));
+                Ok(())
+            },
+        );
+
+        let cmds = io.clone();
+        engine.register_result_fn(
+            "tile_alt",
+            move |row: INT, column: INT, x: Dynamic, y: Dynamic, c: INT| -> ScriptResult {
+                cmds.borrow_mut().cmds.push(ScriptCmd::TileAlt(
+                    tile_index("row", row)?,
+                    tile_index("column", column)?,
+                    num(&x),
+                    num(&y),
+                    pal(c),
+                    false,
+                ));
+                Ok(())
+            },
+        );
+
+        let cmds = io.clone();
+        engine.register_result_fn(
+            "tile_alt",
+            move |row: INT,
+                  column: INT,
+                  x: Dynamic,
+                  y: Dynamic,
+                  c: INT,
+                  flip: bool|
+                  -> ScriptResult {
+                cmds.borrow_mut().cmds.push(ScriptCmd::TileAlt(
+                    tile_index("row", row)?,
+                    tile_index("column", column)?,
+                    num(&x),
+                    num(&y),
+                    pal(c),
+                    flip,
+                ));
+                Ok(())
+            },
+        );
+
+        let checks: [(&str, ButtonCheck); 3] = [
+            ("btn", ButtonState::btn),
+            ("btnp", ButtonState::btnp),
+            ("released", ButtonState::released),
+        ];
+        for (name, check) in checks.iter().copied() {
+            let buttons = io.clone();
+            engine.register_fn(name, move |b: INT| -> bool {
+                button(&buttons, check, b, 0)
+            });
+            let buttons = io.clone();
+            engine.register_fn(name, move |b: INT, p: INT| -> bool {
+                button(&buttons, check, b, p)
+            });
+        }
+
+        let sounds = io.clone();
+        engine.register_fn("sfx", move |n: INT| {
+            sounds.borrow_mut().sounds.push(n as usize);
+        });
+
+        let music = io.clone();
+        engine.register_fn("music", move |n: INT| {
+            music.borrow_mut().music = Some(n);
+        });
+
+        let rng = io.clone();
+        engine.register_fn("rnd", move || -> FLOAT {
+            rng.borrow_mut().rng.float() as FLOAT
+        });
+        let rng = io.clone();
+        engine.register_fn("rnd", move |n: INT| -> INT {
+            rng.borrow_mut().rng.below(n.max(0) as u64) as INT
+        });
+        let rng = io.clone();
+        engine.register_fn("srand", move |seed: INT| {
+            rng.borrow_mut().rng = Rng::new(seed as u64);
+        });
+
+        let remap = io.clone();
+        engine.register_fn("pal", move || {
+            let io = &mut *remap.borrow_mut();
+            io.remap = Remap::default();
+            io.cmds.push(ScriptCmd::Remap(io.remap));
+        });
+        let remap = io.clone();
+        engine.register_fn("pal", move |from: INT, to: INT| {
+            let io = &mut *remap.borrow_mut();
+            io.remap.set(pal(from), pal(to));
+            io.cmds.push(ScriptCmd::Remap(io.remap));
+        });
+        let remap = io.clone();
+        engine.register_fn("palt", move |c: INT, transparent: bool| {
+            let io = &mut *remap.borrow_mut();
+            io.remap.set_transparent(pal(c), transparent);
+            io.cmds.push(ScriptCmd::Remap(io.remap));
+        });
+
+        engine
+    }
+
+    /// Calls a script function by name, a missing function is not an error.
+    /// Errors stop the script and wait in `ScriptIo` to be logged.
+    fn call(&self, name: &str) {
+        if self.error.borrow().is_some() {
+            return;
+        }
+
+        let mut this = self.this.borrow_mut();
+        let result = self.engine.call_fn_dynamic(
+            &mut self.scope.borrow_mut(),
+            &self.ast,
+            false,
+            name,
+            Some(&mut *this),
+            Vec::<Dynamic>::new(),
+        );
+
+        if let Err(err) = result {
+            match *err {
+                // only the hook itself is optional, not the functions it calls
+                EvalAltResult::ErrorFunctionNotFound(ref f, _) if f == name => {}
+                err => {
+                    let message = format!("script error in {}(): {}", name, err);
+                    self.io.borrow_mut().errors.push(message);
+                    *self.error.borrow_mut() = Some(err.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl System for ScriptSystem {
+    fn update(&mut self, game: &mut Game) -> GameResult {
+        self.io.borrow_mut().buttons = game.get_buttons().clone();
+        self.call("update");
+
+        let sounds = std::mem::take(&mut self.io.borrow_mut().sounds);
+        for n in sounds {
+            game.play_sfx(n);
+        }
+        if let Some(n) = self.io.borrow_mut().music.take() {
+            game.play_music(if n < 0 { None } else { Some(n as usize) });
+        }
+
+        // errors of draw() show up here too, it can't reach the console
+        for error in self.take_errors() {
+            game.console.push(&error, Pal::Red);
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        let ui = &game.ui;
+
+        // draw runs once per frame, not per tick, so anything it changes is
+        // put back to keep updates deterministic
+        let this = self.this.borrow().clone();
+        let scope = self.scope.borrow().clone();
+        let (rng, sounds, music) = {
+            let io = self.io.borrow();
+            (io.rng, io.sounds.len(), io.music)
+        };
+        // remaps set by the last frame or by update() carry over
+        let remap = self.io.borrow().remap;
+        self.io.borrow_mut().cmds.clear();
+        self.call("draw");
+        *self.this.borrow_mut() = this;
+        *self.scope.borrow_mut() = scope;
+        {
+            let io = &mut *self.io.borrow_mut();
+            io.rng = rng;
+            io.sounds.truncate(sounds);
+            io.music = music;
+        }
+
+        let cmds = std::mem::take(&mut self.io.borrow_mut().cmds);
+        ui.set_remap(remap);
+        for cmd in cmds {
+            match cmd {
+                ScriptCmd::Text(text, x, y, c) => ui.draw_text(r, &text, x, y, c)?,
+                ScriptCmd::Tile(row, column, x, y, c, flip) => {
+                    ui.draw_tile(r, row, column, TileParam::new(x, y, c).flip(flip))?
+                }
+                ScriptCmd::TileAlt(row, column, x, y, c, flip) => {
+                    ui.draw_tile_alt(r, row, column, TileParam::new(x, y, c).flip(flip))?
+                }
+                ScriptCmd::Remap(remap) => ui.set_remap(remap),
+            }
+        }
+        ui.set_remap(Remap::default());
+
+        if let Some(err) = &*self.error.borrow() {
+            let (_, rows) = ui.screen_size();
+            ui.draw_text(r, err, 0., rows as f32 - 1., Pal::Red)?;
+        }
+
+        Ok(())
+    }
+}
+
+type ScriptResult = Result<(), Box<EvalAltResult>>;
+
+/// `btn`, `btnp` or `released` of `ButtonState`
+type ButtonCheck = fn(&ButtonState, usize, Btn) -> bool;
+
+/// Row or column of a tile, out of range values are script errors
+fn tile_index(what: &str, n: INT) -> Result<u16, Box<EvalAltResult>> {
+    if (0..=u16::MAX as INT).contains(&n) {
+        Ok(n as u16)
+    } else {
+        Err(format!("tile {} {} out of range", what, n).into())
+    }
+}
+
+fn num(d: &Dynamic) -> f32 {
+    if let Ok(i) = d.as_int() {
+        i as f32
+    } else if let Ok(f) = d.as_float() {
+        f as f32
+    } else {
+        0.
+    }
+}
+
+fn button(io: &Rc<RefCell<ScriptIo>>, check: ButtonCheck, b: INT, p: INT) -> bool {
+    match Btn::ALL.get(b as usize) {
+        Some(btn) if p >= 0 => check(&io.borrow().buttons, p as usize, *btn),
+        _ => false,
+    }
+}
+
+/// Writes a value as a rhai expression evaluating back to it.
+fn to_expr(value: &Dynamic) -> GameResult<String> {
+    let value = value.flatten_clone();
+
+    if value.is::<()>() {
+        Ok("()".to_string())
+    } else if let Ok(b) = value.as_bool() {
+        Ok(b.to_string())
+    } else if let Ok(i) = value.as_int() {
+        Ok(i.to_string())
+    } else if let Ok(f) = value.as_float() {
+        // rhai has no literals for these, float division doesn't check for 0
+        if f.is_nan() {
+            return Ok("(0.0 / 0.0)".to_string());
+        } else if f.is_infinite() {
+            return Ok(format!("({}1.0 / 0.0)", if f < 0. { "-" } else { "" }));
+        }
+        // rhai wants a dot before the exponent
+        let f = format!("{:?}", f);
+        Ok(match f.find('e') {
+            Some(e) if !f.contains('.') => format!("{}.0{}", &f[..e], &f[e..]),
+            _ => f,
+        })
+    } else if let Ok(c) = value.as_char() {
+        Ok(format!("'{}'", escape(&c.to_string())))
+    } else if value.is::<ImmutableString>() {
+        let s = value.take_string().map_err(script_error)?;
+        Ok(format!("\"{}\"", escape(&s)))
+    } else if value.is::<Array>() {
+        let items = value
+            .cast::<Array>()
+            .iter()
+            .map(to_expr)
+            .collect::<GameResult<Vec<_>>>()?;
+        Ok(format!("[{}]", items.join(", ")))
+    } else if value.is::<Map>() {
+        let items = value
+            .cast::<Map>()
+            .iter()
+            .map(|(k, v)| Ok(format!("\"{}\": {}", escape(k), to_expr(v)?)))
+            .collect::<GameResult<Vec<_>>>()?;
+        Ok(format!("#{{{}}}", items.join(", ")))
+    } else {
+        Err(script_error(format!("can't save a {}", value.type_name())))
+    }
+}
+
+fn from_expr(engine: &Engine, expr: &str) -> GameResult<Dynamic> {
+    engine
+        .eval_expression::<Dynamic>(expr)
+        .map_err(script_error)
+}
+
+/// Escapes a string for a rhai literal, keeping it on one line
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn pal(c: INT) -> Pal {
+    Pal::from_index(c as usize).unwrap_or(Pal::White)
+}
+
+fn script_error(err: impl fmt::Display) -> ggez::GameError {
+    ggez::GameError::ResourceLoadError(format!("script: {}", err))
+}
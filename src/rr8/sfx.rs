@@ -0,0 +1,208 @@
+use ggez::GameError;
+
+use crate::*;
+
+pub const SFX_COUNT: usize = 64;
+pub const SFX_STEPS: usize = 32;
+/// Pitches go from C2 (0) to D#7 (63)
+pub const MAX_PITCH: u8 = 63;
+pub const MAX_VOLUME: u8 = 7;
+
+const SFX_FORMAT: u8 = 1;
+const NOTES: [&str; 12] = [
+    "C-", "C#", "D-", "D#", "E-", "F-", "F#", "G-", "G#", "A-", "A#", "B-",
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Wave {
+    Square,
+    Triangle,
+    Saw,
+    Noise,
+}
+
+impl Wave {
+    pub const ALL: [Wave; 4] = [Wave::Square, Wave::Triangle, Wave::Saw, Wave::Noise];
+}
+
+/// What happens to a step while it plays
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Effect {
+    None,
+    /// Glides from the pitch of the previous step
+    Slide,
+    Vibrato,
+    FadeIn,
+    FadeOut,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 5] = [
+        Effect::None,
+        Effect::Slide,
+        Effect::Vibrato,
+        Effect::FadeIn,
+        Effect::FadeOut,
+    ];
+
+    /// Single char shown under the step in the editor
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Effect::None => " ",
+            Effect::Slide => "S",
+            Effect::Vibrato => "V",
+            Effect::FadeIn => "<",
+            Effect::FadeOut => ">",
+        }
+    }
+}
+
+/// A note of a sound effect, silent when the volume is 0
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    pub pitch: u8,
+    pub wave: Wave,
+    pub volume: u8,
+    pub effect: Effect,
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self {
+            pitch: 24,
+            wave: Wave::Square,
+            volume: 0,
+            effect: Effect::None,
+        }
+    }
+}
+
+/// Steps played one after the other, each lasting `speed` ticks of
+/// 1/120th of a second.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sfx {
+    pub speed: u8,
+    pub steps: Vec<Step>,
+}
+
+impl Default for Sfx {
+    fn default() -> Self {
+        Self {
+            speed: 16,
+            steps: vec![Step::default(); SFX_STEPS],
+        }
+    }
+}
+
+impl Sfx {
+    /// Number of steps up to the last one that makes a sound
+    pub fn len(&self) -> usize {
+        self.steps
+            .iter()
+            .rposition(|s| s.volume > 0)
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Name of a pitch like "C#3"
+pub fn note_name(pitch: u8) -> String {
+    format!("{}{}", NOTES[pitch as usize % 12], 2 + pitch / 12)
+}
+
+/// Binary form stored in carts: a header with the format and number of
+/// effects, then every effect as its speed followed by two bytes per step,
+/// pitch and wave then volume and effect.
+pub fn to_bytes(sfx: &[Sfx]) -> Vec<u8> {
+    let mut data = vec![SFX_FORMAT, sfx.len() as u8];
+
+    for sfx in sfx.iter() {
+        data.push(sfx.speed);
+        for step in sfx.steps.iter() {
+            data.push(step.pitch & 0b11_1111 | (step.wave as u8) << 6);
+            data.push(step.volume & 0b111 | (step.effect as u8) << 3);
+        }
+    }
+
+    data
+}
+
+pub fn from_bytes(data: &[u8]) -> GameResult<Vec<Sfx>> {
+    let error = || GameError::ResourceLoadError("sfx: truncated data".to_string());
+    let mut bytes = data.iter().copied();
+    let mut next = || bytes.next().ok_or_else(error);
+
+    let format = next()?;
+    if format != SFX_FORMAT {
+        return Err(GameError::ResourceLoadError(format!(
+            "sfx: unknown format {}",
+            format
+        )));
+    }
+    let count = next()? as usize;
+
+    let mut sfx = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut effect = Sfx {
+            speed: next()?.max(1),
+            steps: Vec::with_capacity(SFX_STEPS),
+        };
+        for _ in 0..SFX_STEPS {
+            let (a, b) = (next()?, next()?);
+            effect.steps.push(Step {
+                pitch: a & 0b11_1111,
+                wave: Wave::ALL[(a >> 6) as usize],
+                volume: b & 0b111,
+                effect: Effect::ALL
+                    .get((b >> 3) as usize)
+                    .copied()
+                    .unwrap_or(Effect::None),
+            });
+        }
+        sfx.push(effect);
+    }
+    sfx.resize(SFX_COUNT.max(count), Sfx::default());
+
+    Ok(sfx)
+}
+
+/// State of the sfx editor: the effect and step being edited and the
+/// wave drawn notes get.
+#[derive(Debug)]
+pub struct SfxEdit {
+    pub index: usize,
+    pub step: usize,
+    pub wave: Wave,
+}
+
+impl Default for SfxEdit {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            step: 0,
+            wave: Wave::Square,
+        }
+    }
+}
+
+impl SfxEdit {
+    pub fn next_sfx(&mut self, step: isize) {
+        let count = SFX_COUNT as isize;
+        self.index = (self.index as isize + step).rem_euclid(count) as usize;
+    }
+
+    pub fn move_step(&mut self, step: isize) {
+        let count = SFX_STEPS as isize;
+        self.step = (self.step as isize + step).rem_euclid(count) as usize;
+    }
+}
+
+/// Cycles through the values of `all`, starting from `value`
+pub fn cycle<T: Copy + PartialEq>(all: &[T], value: T, step: isize) -> T {
+    let i = all.iter().position(|v| *v == value).unwrap_or(0) as isize;
+    all[(i + step).rem_euclid(all.len() as isize) as usize]
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use ggez::graphics::Rect;
+use ggez::GameError;
+
+use crate::render::soft::Framebuffer;
+use crate::*;
+
+/// Set to write the rendered frames over the stored snapshots
+pub const BLESS_ENV: &str = "RR8_BLESS";
+
+const SCREEN_SIZE: f32 = 20. * TILE_SIZE as f32;
+
+/// Renders a single frame of a headless `Game` to compare it against a PNG
+/// stored in `tests/snapshots/`.
+///
+/// ```no_run
+/// # use rr8::{snapshot::Snapshot, ui::topbar::TopBar, GameMode};
+/// Snapshot::new(GameMode::Prompt)
+///     .unwrap()
+///     .system(TopBar::default())
+///     .assert_matches("topbar_prompt")
+///     .unwrap();
+/// ```
+///
+/// Run the tests with `RR8_BLESS=1` to record new snapshots or to accept
+/// intended changes, a missing snapshot is an error otherwise.
+#[derive(Debug)]
+pub struct Snapshot {
+    game: Game,
+}
+
+impl Snapshot {
+    pub fn new(mode: GameMode) -> GameResult<Self> {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let win = Rect::new(0., 0., SCREEN_SIZE, SCREEN_SIZE);
+
+        let ui = Ui::headless(resources, win)?;
+        let mut game = Game::new(ui)?;
+        game.mode = mode;
+
+        Ok(Self { game })
+    }
+
+    pub fn system<S: 'static + System>(mut self, system: S) -> Self {
+        self.game.ui.add_system(system);
+        self
+    }
+
+    /// To set up the game state before rendering.
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    pub fn render(&self) -> GameResult<Framebuffer> {
+        let win = self.game.ui.get_win();
+        let mut fb = Framebuffer::new(win.w as u16, win.h as u16);
+        self.game.ui.draw_all(&mut fb, &self.game)?;
+
+        Ok(fb)
+    }
+
+    pub fn assert_matches(&self, name: &str) -> GameResult {
+        assert_snapshot(&snapshot_dir(), name, &self.render()?)
+    }
+}
+
+pub fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+}
+
+/// Compares a frame with `<dir>/<name>.png`. On mismatch, or when there is
+/// no snapshot yet, the frame is written next to it as `<name>.new.png`.
+pub fn assert_snapshot(dir: &Path, name: &str, fb: &Framebuffer) -> GameResult {
+    let path = dir.join(format!("{}.png", name));
+    let bless = std::env::var_os(BLESS_ENV).is_some();
+
+    let new_path = dir.join(format!("{}.new.png", name));
+
+    if bless {
+        std::fs::create_dir_all(dir)?;
+        save_png(fb, &path)?;
+        println!("Recorded snapshot {}", path.display());
+        return Ok(());
+    }
+
+    if !path.exists() {
+        std::fs::create_dir_all(dir)?;
+        save_png(fb, &new_path)?;
+        return Err(GameError::RenderError(format!(
+            "snapshot {} is missing, see {} (set {} to record it)",
+            name,
+            new_path.display(),
+            BLESS_ENV
+        )));
+    }
+
+    let expected = load_png(&path)?;
+    if &expected == fb {
+        return Ok(());
+    }
+
+    save_png(fb, &new_path)?;
+
+    let diff = if expected.width() != fb.width() || expected.height() != fb.height() {
+        format!(
+            "size {}x{} != {}x{}",
+            fb.width(),
+            fb.height(),
+            expected.width(),
+            expected.height()
+        )
+    } else {
+        let pixels = expected
+            .pixels()
+            .chunks(4)
+            .zip(fb.pixels().chunks(4))
+            .filter(|(a, b)| a != b)
+            .count();
+        format!("{} pixels differ", pixels)
+    };
+
+    Err(GameError::RenderError(format!(
+        "snapshot {} doesn't match: {}, see {} (set {} to accept)",
+        name,
+        diff,
+        new_path.display(),
+        BLESS_ENV
+    )))
+}
+
+pub fn save_png(fb: &Framebuffer, path: &Path) -> GameResult {
+    image::save_buffer(
+        path,
+        fb.pixels(),
+        fb.width() as u32,
+        fb.height() as u32,
+        image::ColorType::RGBA(8),
+    )?;
+
+    Ok(())
+}
+
+pub fn load_png(path: &Path) -> GameResult<Framebuffer> {
+    let image = image::open(path)
+        .map_err(|e| GameError::ResourceLoadError(e.to_string()))?
+        .to_rgba();
+    let (w, h) = image.dimensions();
+
+    Ok(Framebuffer::from_rgba(w as u16, h as u16, image.into_raw()))
+}
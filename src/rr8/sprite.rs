@@ -0,0 +1,238 @@
+use crate::map::Tileset;
+use crate::render::{Sheet, TRANSPARENT};
+use crate::*;
+
+const MAX_UNDO: usize = 64;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tool {
+    Pencil,
+    Fill,
+    Line,
+    Rect,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 4] = [Tool::Pencil, Tool::Fill, Tool::Line, Tool::Rect];
+}
+
+/// Pixels of a tile before a stroke, to undo it
+#[derive(Debug)]
+struct Undo {
+    tileset: Tileset,
+    x: u16,
+    y: u16,
+    w: u16,
+    pixels: Vec<u8>,
+}
+
+/// State of the sprite editor: the tile being edited, the tool and color
+/// in use and the undo history.
+#[derive(Debug)]
+pub struct SpriteEdit {
+    pub tileset: Tileset,
+    pub row: u16,
+    pub column: u16,
+    pub narrow: bool,
+    pub tool: Tool,
+    pub color: Pal,
+    /// Pixel where the current line or rect started
+    pub drag: Option<(u16, u16)>,
+    undo: Vec<Undo>,
+}
+
+impl Default for SpriteEdit {
+    fn default() -> Self {
+        Self {
+            tileset: Tileset::Main,
+            row: 0,
+            column: 0,
+            narrow: false,
+            tool: Tool::Pencil,
+            color: Pal::White,
+            drag: None,
+            undo: Vec::new(),
+        }
+    }
+}
+
+impl SpriteEdit {
+    /// 8 pixels for 8x16 tiles, 16 otherwise
+    pub fn tile_width(&self) -> u16 {
+        if self.narrow {
+            TILE_SIZE / 2
+        } else {
+            TILE_SIZE
+        }
+    }
+
+    /// Top left pixel of the edited tile in its sheet
+    pub fn origin(&self) -> (u16, u16) {
+        (self.column * self.tile_width(), self.row * TILE_SIZE)
+    }
+
+    pub fn move_tile(&mut self, dx: i32, dy: i32, ui: &Ui) {
+        let (rows, columns) = ui.tileset_size(self.tileset);
+        let columns = columns * TILE_SIZE / self.tile_width();
+        // a tileset smaller than a tile has nothing to edit
+        if rows == 0 || columns == 0 {
+            return;
+        }
+        self.column = (self.column as i32 + dx).rem_euclid(columns as i32) as u16;
+        self.row = (self.row as i32 + dy).rem_euclid(rows as i32) as u16;
+    }
+
+    pub fn switch_tileset(&mut self, ui: &Ui) {
+        self.tileset = match self.tileset {
+            Tileset::Main => Tileset::Alt,
+            Tileset::Alt => Tileset::Main,
+        };
+        self.move_tile(0, 0, ui);
+    }
+
+    pub fn toggle_narrow(&mut self, ui: &Ui) {
+        self.column = if self.narrow {
+            self.column / 2
+        } else {
+            self.column * 2
+        };
+        self.narrow = !self.narrow;
+        self.move_tile(0, 0, ui);
+    }
+
+    pub fn next_color(&mut self, step: isize) {
+        let count = Pal::ALL.len() as isize;
+        let i = (self.color as isize + step).rem_euclid(count);
+        self.color = Pal::ALL[i as usize];
+    }
+
+    /// Saves the pixels of the tile, call before every stroke.
+    pub fn push_undo(&mut self, sheet: &Sheet) {
+        let (x, y) = self.origin();
+        let w = self.tile_width();
+        let mut pixels = Vec::new();
+        for py in y..y + TILE_SIZE {
+            for px in x..x + w {
+                pixels.push(sheet.pixel(px, py));
+            }
+        }
+
+        self.undo.push(Undo {
+            tileset: self.tileset,
+            x,
+            y,
+            w,
+            pixels,
+        });
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Restores the last saved tile, returns its tileset.
+    pub fn pop_undo(&mut self, ui: &mut Ui) -> Option<Tileset> {
+        let undo = self.undo.pop()?;
+        let sheet = ui.sheet_mut(undo.tileset);
+        for (i, pixel) in undo.pixels.iter().enumerate() {
+            let px = undo.x + i as u16 % undo.w;
+            let py = undo.y + i as u16 / undo.w;
+            sheet.set_pixel(px, py, *pixel);
+        }
+
+        Some(undo.tileset)
+    }
+
+    /// Applies the current tool at a pixel of the tile, `(x, y)` is
+    /// relative to the tile. Line and rect use the drag start, erasing
+    /// paints transparent pixels instead of the current color.
+    pub fn apply(&self, sheet: &mut Sheet, x: u16, y: u16, erase: bool) {
+        let index = if erase { TRANSPARENT } else { self.color as u8 };
+        let (ox, oy) = self.origin();
+        // the part of the tile inside the sheet
+        let w = self.tile_width().min(sheet.width().saturating_sub(ox));
+        let h = TILE_SIZE.min(sheet.height().saturating_sub(oy));
+        if x >= w || y >= h {
+            return;
+        }
+
+        let plot = |sheet: &mut Sheet, x: i32, y: i32| {
+            if x >= 0 && y >= 0 && (x as u16) < w && (y as u16) < h {
+                sheet.set_pixel(ox + x as u16, oy + y as u16, index);
+            }
+        };
+
+        match self.tool {
+            Tool::Pencil => plot(sheet, x as i32, y as i32),
+            Tool::Fill => {
+                let target = sheet.pixel(ox + x, oy + y);
+                if target == index {
+                    return;
+                }
+                let mut stack = vec![(x, y)];
+                while let Some((x, y)) = stack.pop() {
+                    if sheet.pixel(ox + x, oy + y) != target {
+                        continue;
+                    }
+                    sheet.set_pixel(ox + x, oy + y, index);
+                    if x > 0 {
+                        stack.push((x - 1, y));
+                    }
+                    if x + 1 < w {
+                        stack.push((x + 1, y));
+                    }
+                    if y > 0 {
+                        stack.push((x, y - 1));
+                    }
+                    if y + 1 < h {
+                        stack.push((x, y + 1));
+                    }
+                }
+            }
+            Tool::Line => {
+                let (x0, y0) = self.drag.unwrap_or((x, y));
+                for (px, py) in line((x0 as i32, y0 as i32), (x as i32, y as i32)) {
+                    plot(sheet, px, py);
+                }
+            }
+            Tool::Rect => {
+                let (x0, y0) = self.drag.unwrap_or((x, y));
+                let (x0, x1) = (x0.min(x) as i32, x0.max(x) as i32);
+                let (y0, y1) = (y0.min(y) as i32, y0.max(y) as i32);
+                for px in x0..=x1 {
+                    plot(sheet, px, y0);
+                    plot(sheet, px, y1);
+                }
+                for py in y0..=y1 {
+                    plot(sheet, x0, py);
+                    plot(sheet, x1, py);
+                }
+            }
+        }
+    }
+}
+
+/// Points of a line with Bresenham's algorithm, ends included
+pub fn line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let sx = if x < to.0 { 1 } else { -1 };
+    let sy = if y < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    let mut points = vec![(x, y)];
+    while (x, y) != to {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        points.push((x, y));
+    }
+
+    points
+}
@@ -0,0 +1,221 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use ggez::GameError;
+
+use crate::cart;
+use crate::script::ScriptState;
+use crate::*;
+
+pub const STATE_VERSION: u32 = 1;
+const STATE_HEADER: &str = "rr8 state";
+
+/// A snapshot of the running game, restoring it resumes right where it
+/// was taken.
+///
+/// The file starts like a cart, with a header and a version line, then a
+/// line per value. The source of the running script, if any, follows in a
+/// `__script__` section giving its length in bytes and the cart data (code,
+/// tilesets, map, sound) in a `__cart__` section holding a whole cart file.
+/// Script variables are written as rhai expressions:
+///
+/// ```text
+/// rr8 state
+/// version 1
+/// dt 1234
+/// cursor 0
+/// status ""
+/// music 2
+/// rng 9876
+/// this #{"x": 12, "name": "bob"}
+/// global lives 3
+/// const SPEED 2
+/// __script__ 29
+/// fn update() { this.x += 1; }
+/// __cart__
+/// rr8 cartridge
+/// ...
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveState {
+    pub dt: u32,
+    pub cursor: usize,
+    pub status: String,
+    /// Position of the song playing
+    pub music: Option<usize>,
+    pub script: Option<ScriptState>,
+    pub cart: Cart,
+}
+
+impl SaveState {
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> GameResult {
+        std::fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> GameResult<Self> {
+        let missing_cart = || state_error("missing cart");
+        let (head, rest) = text.split_at(find_section(text).ok_or_else(missing_cart)?);
+
+        let (line, body) = cart::next_line(rest);
+        let (source, rest) = match cart::section_header(line) {
+            Some(("script", Some(length))) => {
+                let (source, rest) = cart::raw_section(body, length)
+                    .ok_or_else(|| state_error(&format!("script shorter than {} bytes", length)))?;
+                (Some(source), rest)
+            }
+            Some(("script", None)) => return Err(state_error("missing script length")),
+            _ => (None, rest),
+        };
+
+        let (line, cart) = cart::next_line(rest);
+        if cart::section_header(line) != Some(("cart", None)) {
+            return Err(missing_cart());
+        }
+
+        let mut lines = head.lines();
+        if lines.next().map(str::trim) != Some(STATE_HEADER) {
+            return Err(state_error("missing header"));
+        }
+
+        let version = lines
+            .next()
+            .and_then(|l| l.trim().strip_prefix("version "))
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| state_error("missing version"))?;
+        if version > STATE_VERSION {
+            return Err(state_error(&format!(
+                "version {} is newer than {}",
+                version, STATE_VERSION
+            )));
+        }
+
+        let mut state = Self {
+            cart: Cart::parse(cart)?,
+            ..Self::default()
+        };
+        let mut script = ScriptState {
+            source: source.unwrap_or_default().to_string(),
+            ..ScriptState::default()
+        };
+
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let bad = || state_error(&format!("bad line {:?}", line));
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+            match key {
+                "dt" => state.dt = value.parse().map_err(|_| bad())?,
+                "cursor" => state.cursor = value.parse().map_err(|_| bad())?,
+                "status" => state.status = unquote(value).ok_or_else(bad)?,
+                "music" => state.music = Some(value.parse().map_err(|_| bad())?),
+                "rng" => script.rng = value.parse().map_err(|_| bad())?,
+                "this" => script.this = value.to_string(),
+                "error" => script.error = Some(unquote(value).ok_or_else(bad)?),
+                "global" | "const" => {
+                    let (name, expr) = value.split_at(value.find(' ').ok_or_else(bad)?);
+                    let constant = key == "const";
+                    script
+                        .globals
+                        .push((name.to_string(), constant, expr[1..].to_string()));
+                }
+                _ => return Err(bad()),
+            }
+        }
+
+        if source.is_some() {
+            state.script = Some(script);
+        }
+
+        Ok(state)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "{}", STATE_HEADER).unwrap();
+        writeln!(out, "version {}", STATE_VERSION).unwrap();
+        writeln!(out, "dt {}", self.dt).unwrap();
+        writeln!(out, "cursor {}", self.cursor).unwrap();
+        writeln!(out, "status {}", quote(&self.status)).unwrap();
+        if let Some(music) = self.music {
+            writeln!(out, "music {}", music).unwrap();
+        }
+
+        if let Some(script) = &self.script {
+            writeln!(out, "rng {}", script.rng).unwrap();
+            writeln!(out, "this {}", script.this).unwrap();
+            if let Some(error) = &script.error {
+                writeln!(out, "error {}", quote(error)).unwrap();
+            }
+            for (name, constant, expr) in script.globals.iter() {
+                let key = if *constant { "const" } else { "global" };
+                writeln!(out, "{} {} {}", key, name, expr).unwrap();
+            }
+
+            writeln!(out, "__script__ {}", script.source.len()).unwrap();
+            writeln!(out, "{}", script.source).unwrap();
+        }
+
+        writeln!(out, "__cart__").unwrap();
+        out.push_str(&self.cart.serialize());
+
+        out
+    }
+}
+
+/// Offset of the first section line
+fn find_section(text: &str) -> Option<usize> {
+    let mut start = 0;
+
+    for line in text.split_inclusive('\n') {
+        if cart::section_header(line).is_some() {
+            return Some(start);
+        }
+        start += line.len();
+    }
+
+    None
+}
+
+/// Strings are written as rust string literals so they stay on one line
+fn quote(s: &str) -> String {
+    format!("{:?}", s)
+}
+
+fn unquote(s: &str) -> Option<String> {
+    let s = s.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            'u' => {
+                let hex: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let code = u32::from_str_radix(hex.strip_prefix('{')?, 16).ok()?;
+                out.push(std::char::from_u32(code)?);
+            }
+            c => out.push(c),
+        }
+    }
+
+    Some(out)
+}
+
+fn state_error(msg: &str) -> GameError {
+    GameError::ResourceLoadError(format!("state: {}", msg))
+}
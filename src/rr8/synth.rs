@@ -0,0 +1,166 @@
+use std::f32::consts::PI;
+
+use crate::sfx::{Effect, Sfx, Step, Wave, MAX_VOLUME};
+
+/// Samples per second of everything the synth renders
+pub const SAMPLE_RATE: u32 = 22050;
+/// Sfx speeds are counted in ticks of 1/120th of a second
+pub const TICK_RATE: u32 = 120;
+
+/// Loudness of a single voice, so four of them can be mixed
+const VOICE_GAIN: f32 = 0.25;
+const VIBRATO_RATE: f32 = 6.;
+/// Vibrato depth in semitones
+const VIBRATO_DEPTH: f32 = 0.5;
+/// How fast the volume reaches its target, avoids clicks between steps
+const SMOOTHING: f32 = 0.02;
+
+/// Frequency in Hz of a pitch, 33 is A4
+pub fn frequency(pitch: u8) -> f32 {
+    440. * 2f32.powf((pitch as f32 - 33.) / 12.)
+}
+
+/// An oscillator playing steps one after the other.
+#[derive(Clone, Debug)]
+pub struct Voice {
+    rate: u32,
+    phase: f32,
+    volume: f32,
+    /// Samples played, drives the vibrato
+    time: u32,
+    lfsr: u16,
+    noise: f32,
+}
+
+impl Voice {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            phase: 0.,
+            volume: 0.,
+            time: 0,
+            lfsr: 0xACE1,
+            noise: 0.,
+        }
+    }
+
+    /// Next sample of a wave at `freq`, in -1..1
+    fn oscillate(&mut self, wave: Wave, freq: f32) -> f32 {
+        let previous = self.phase;
+        self.phase = (self.phase + freq / self.rate as f32).fract();
+
+        match wave {
+            Wave::Square => {
+                if self.phase < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            Wave::Triangle => 4. * (self.phase - 0.5).abs() - 1.,
+            Wave::Saw => 2. * self.phase - 1.,
+            Wave::Noise => {
+                // a new random value every period, so the pitch still matters
+                if self.phase < previous {
+                    let bit =
+                        (self.lfsr ^ (self.lfsr >> 2) ^ (self.lfsr >> 3) ^ (self.lfsr >> 5)) & 1;
+                    self.lfsr = (self.lfsr >> 1) | (bit << 15);
+                    self.noise = (self.lfsr & 0xFF) as f32 / 127.5 - 1.;
+                }
+                self.noise
+            }
+        }
+    }
+
+    /// Renders `len` samples of a step, `previous` is the step played
+    /// before it for slides.
+    pub fn play_step(
+        &mut self,
+        step: &Step,
+        previous: Option<&Step>,
+        len: usize,
+        out: &mut Vec<f32>,
+    ) {
+        let target = step.volume as f32 / MAX_VOLUME as f32;
+        let from = frequency(previous.map(|p| p.pitch).unwrap_or(step.pitch));
+        let to = frequency(step.pitch);
+
+        for i in 0..len {
+            let t = i as f32 / len as f32;
+            let mut freq = to;
+            let mut volume = target;
+
+            match step.effect {
+                Effect::None => {}
+                Effect::Slide => freq = from + (to - from) * t,
+                Effect::Vibrato => {
+                    let seconds = self.time as f32 / self.rate as f32;
+                    let semitones = VIBRATO_DEPTH * (2. * PI * VIBRATO_RATE * seconds).sin();
+                    freq *= 2f32.powf(semitones / 12.);
+                }
+                Effect::FadeIn => volume *= t,
+                Effect::FadeOut => volume *= 1. - t,
+            }
+
+            self.volume += (volume - self.volume) * SMOOTHING;
+            self.time = self.time.wrapping_add(1);
+            out.push(self.oscillate(step.wave, freq) * self.volume * VOICE_GAIN);
+        }
+    }
+
+    /// Renders the steps of an effect up to the last audible one.
+    pub fn play(&mut self, sfx: &Sfx, out: &mut Vec<f32>) {
+        let len = step_samples(sfx, self.rate);
+        let mut previous = None;
+        for step in sfx.steps.iter().take(sfx.len()) {
+            self.play_step(step, previous, len, out);
+            previous = Some(step);
+        }
+    }
+}
+
+/// Samples a number of ticks lasts
+pub fn tick_samples(ticks: u8, rate: u32) -> usize {
+    (rate / TICK_RATE) as usize * ticks.max(1) as usize
+}
+
+/// Samples each step of an effect lasts
+pub fn step_samples(sfx: &Sfx, rate: u32) -> usize {
+    tick_samples(sfx.speed, rate)
+}
+
+/// Renders an effect to mono samples in -1..1.
+pub fn render(sfx: &Sfx, rate: u32) -> Vec<f32> {
+    let mut out = Vec::new();
+    Voice::new(rate).play(sfx, &mut out);
+
+    out
+}
+
+/// Encodes mono samples in a 16-bit PCM WAV file.
+pub fn to_wav(samples: &[f32], rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&rate.to_le_bytes());
+    wav.extend_from_slice(&(rate * 2).to_le_bytes());
+    // block align and bits per sample
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+
+    for sample in samples {
+        let sample = (sample.clamp(-1., 1.) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    wav
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+/// Updates per second of game time
+pub const TICK_RATE: u32 = 60;
+
+/// Turns frame times into a number of fixed steps to update.
+///
+/// `speed` scales the time fed to the accumulator (0.5 is half speed slow
+/// motion) and `max_steps` caps how many updates run in a single frame,
+/// when the game can't keep up the extra time is dropped instead of
+/// piling up.
+#[derive(Debug)]
+pub struct Ticker {
+    step: Duration,
+    accumulator: Duration,
+    speed: f32,
+    max_steps: u32,
+    paused: bool,
+    queued: u32,
+}
+
+impl Default for Ticker {
+    fn default() -> Self {
+        Self::new(TICK_RATE)
+    }
+}
+
+impl Ticker {
+    pub const MIN_SPEED: f32 = 0.125;
+    pub const MAX_SPEED: f32 = 4.;
+    /// Steps that can be queued while paused, ten seconds of updates
+    pub const MAX_QUEUED: u32 = 10 * TICK_RATE;
+
+    pub fn new(rate: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / rate,
+            accumulator: Duration::from_secs(0),
+            speed: 1.,
+            max_steps: 5,
+            paused: false,
+            queued: 0,
+        }
+    }
+
+    /// Number of steps to run after `delta` time has passed.
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        if self.paused {
+            return std::mem::replace(&mut self.queued, 0);
+        }
+
+        self.accumulator += delta.mul_f32(self.speed);
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+
+            if steps == self.max_steps {
+                // frame skip limit reached, drop what's left
+                self.accumulator = Duration::from_secs(0);
+                break;
+            }
+        }
+
+        steps
+    }
+
+    pub fn get_step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        // `speed nan` parses, it's the slowest speed rather than a stuck ticker
+        self.speed = if speed.is_nan() {
+            Self::MIN_SPEED
+        } else {
+            speed.clamp(Self::MIN_SPEED, Self::MAX_SPEED)
+        };
+    }
+
+    pub fn get_max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.accumulator = Duration::from_secs(0);
+        self.queued = 0;
+    }
+
+    /// Queues a single step to run while paused, up to `MAX_QUEUED`.
+    pub fn step(&mut self) {
+        if self.paused && self.queued < Self::MAX_QUEUED {
+            self.queued += 1;
+        }
+    }
+}
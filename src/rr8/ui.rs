@@ -1,21 +1,37 @@
+pub mod code_editor;
 pub mod font;
+pub mod map_editor;
 pub mod mouse;
+pub mod music_editor;
 pub mod palette;
 pub mod prompt;
+pub mod sfx_editor;
+pub mod sprite_editor;
+pub mod text;
 pub mod tile;
 pub mod topbar;
 
-use ggez::graphics;
-use ggez::graphics::{spritebatch::SpriteBatch, Rect};
-use ggez::graphics::{Color, Drawable, FilterMode};
-use ggez::nalgebra::{Point2, Vector2};
+use std::path::Path;
+
+use ggez::event::MouseButton;
+use ggez::graphics::{Color, FilterMode, Rect};
+use ggez::nalgebra::Point2;
 use ggez::Context;
 
+use crate::map::{Cell, Tileset};
+use crate::render::{Renderer, Sheet, SheetColors};
 use crate::*;
 use font::Font;
 use mouse::Mouse;
-use palette::Pal;
-use tile::{TileLayout, TileMap};
+use palette::{Ink, Pal, Palette, Remap};
+use text::{Align, TextLayout};
+use tile::{Paint, TileLayout, TileMap, TileParam};
+
+/// Size in tiles of the virtual screen of carts that don't set one
+pub const SCREEN_COLUMNS: u16 = 20;
+pub const SCREEN_ROWS: u16 = 20;
+/// Largest screen a cart can ask for, in tiles on each side
+pub const MAX_SCREEN_TILES: u16 = 64;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Scale {
@@ -26,11 +42,47 @@ pub enum Scale {
     Max,
 }
 
+/// Size of the window in virtual screens, whole numbers so every virtual
+/// pixel is drawn as a square of window pixels.
 impl Scale {
-    pub const DELTA: f32 = 0.5;
+    pub const DELTA: f32 = 1.;
     pub const DEFAULT: f32 = Self::DELTA * 2.;
     pub const MIN: f32 = Self::DELTA;
-    pub const MAX: f32 = Self::DELTA * 7.;
+    pub const MAX: f32 = Self::DELTA * 4.;
+}
+
+/// Tilesets and font of a cart, decoded before any of them is swapped in.
+/// Sheets left to `None` keep the one in use.
+#[derive(Debug, Default)]
+pub struct CartSheets {
+    pub tiles: Option<Sheet>,
+    pub tiles_alt: Option<Sheet>,
+    pub font: Option<Sheet>,
+}
+
+impl CartSheets {
+    /// The sheets stored in a cart
+    pub fn decode(cart: &Cart) -> GameResult<Self> {
+        let decode = |data: &Option<Vec<u8>>| data.as_deref().map(Sheet::from_png).transpose();
+
+        Ok(Self {
+            tiles: decode(&cart.tiles)?.map(tileset).transpose()?,
+            tiles_alt: decode(&cart.tiles_alt)?.map(tileset).transpose()?,
+            font: decode(&cart.font)?,
+        })
+    }
+}
+
+/// The editors need at least one tile to work on
+fn tileset(sheet: Sheet) -> GameResult<Sheet> {
+    if sheet.width() < TILE_SIZE || sheet.height() < TILE_SIZE {
+        return Err(ggez::GameError::ResourceLoadError(format!(
+            "cart: tileset is smaller than a {0}x{0} tile",
+            TILE_SIZE
+        )));
+    }
+
+    Ok(sheet)
 }
 
 #[derive(Debug)]
@@ -40,10 +92,14 @@ pub struct Ui {
     map: TileMap,
     map2: TileMap,
     mouse: Mouse,
+    filter_mode: FilterMode,
     scale: f32,
     default_scale: f32,
     systems: Vec<Box<dyn System>>,
     win: Rect,
+    chrome: bool,
+    palette: Palette,
+    remap: std::cell::Cell<Remap>,
 }
 
 impl Ui {
@@ -53,228 +109,521 @@ impl Ui {
         win: Rect,
         scale: f32,
     ) -> GameResult<Self> {
-        let font = Font::new(ctx, filter_mode)?;
-        let layout = TileLayout::new(TILESET_PATH);
-        let layout2 = TileLayout::new(TILESET_ALT_PATH);
-        let map = TileMap::new(ctx, layout, filter_mode)?;
-        let map2 = TileMap::new(ctx, layout2, filter_mode)?;
+        let font = Sheet::load(ctx, FONT_PATH)?;
+        let tiles = Sheet::load(ctx, TILESET_PATH)?;
+        let tiles_alt = Sheet::load(ctx, TILESET_ALT_PATH)?;
 
-        let mouse = Mouse::default();
+        Self::with_sheets(font, tiles, tiles_alt, filter_mode, win, scale)
+    }
 
-        let systems: Vec<Box<dyn System>> = Vec::new();
+    /// Builds a `Ui` from the sheets of a cart filled with
+    /// `Cart::fill_from_resources`, so it needs no resource directory.
+    pub fn from_cart(
+        cart: &Cart,
+        filter_mode: FilterMode,
+        win: Rect,
+        scale: f32,
+    ) -> GameResult<Self> {
+        let load = |data: &Option<Vec<u8>>, name: &str| match data {
+            Some(data) => Sheet::from_png(data),
+            None => Err(ggez::GameError::ResourceLoadError(format!(
+                "cart has no {}",
+                name
+            ))),
+        };
+
+        Self::with_sheets(
+            load(&cart.font, "font")?,
+            load(&cart.tiles, "tiles")?,
+            load(&cart.tiles_alt, "tiles_alt")?,
+            filter_mode,
+            win,
+            scale,
+        )
+    }
+
+    /// Builds a `Ui` without a `Context`, loading the resources from a
+    /// directory on disk. Used to render with the software renderer.
+    pub fn headless(resources: impl AsRef<Path>, win: Rect) -> GameResult<Self> {
+        let dir = resources.as_ref();
+        let load = |path: &str| Sheet::load_file(dir.join(path.trim_start_matches('/')));
+
+        Self::with_sheets(
+            load(FONT_PATH)?,
+            load(TILESET_PATH)?,
+            load(TILESET_ALT_PATH)?,
+            FilterMode::Nearest,
+            win,
+            Scale::DEFAULT,
+        )
+    }
+
+    fn with_sheets(
+        font: Sheet,
+        tiles: Sheet,
+        tiles_alt: Sheet,
+        filter_mode: FilterMode,
+        win: Rect,
+        scale: f32,
+    ) -> GameResult<Self> {
+        let font = Font::new(font);
+        let map = TileMap::new(tiles, TileLayout::new(TILESET_PATH));
+        let map2 = TileMap::new(tiles_alt, TileLayout::new(TILESET_ALT_PATH));
 
         Self::validate_rect(win)?;
 
+        let mut mouse = Mouse::default();
+        mouse.set_viewport(viewport(win));
+
+        let systems: Vec<Box<dyn System>> = Vec::new();
+
         Ok(Self {
             dt: 0,
             font,
             map,
             map2,
             mouse,
+            filter_mode,
             scale,
             default_scale: scale,
             systems,
             win,
+            chrome: true,
+            palette: Palette::default(),
+            remap: std::cell::Cell::new(Remap::default()),
         })
     }
 
-    fn validate_rect(rect: Rect) -> GameResult {
+    /// Screens are whole tiles, at least the default size so the editors
+    /// fit and at most `MAX_SCREEN_TILES` on each side.
+    pub fn validate_rect(rect: Rect) -> GameResult {
         let unit = TILE_SIZE as f32;
+        let (min_w, min_h) = (SCREEN_COLUMNS as f32 * unit, SCREEN_ROWS as f32 * unit);
+        let max = MAX_SCREEN_TILES as f32 * unit;
 
         if rect.x % unit > 0. || rect.y % unit > 0. || rect.w % unit > 0. || rect.h % unit > 0. {
             Err(ggez::GameError::WindowError(format!(
                 "{:?} Not a multiple of {}",
                 rect, TILE_SIZE
             )))
+        } else if rect.w < min_w || rect.h < min_h || rect.w > max || rect.h > max {
+            Err(ggez::GameError::WindowError(format!(
+                "{:?} Not between {}x{} and {}x{} tiles",
+                rect, SCREEN_COLUMNS, SCREEN_ROWS, MAX_SCREEN_TILES, MAX_SCREEN_TILES
+            )))
         } else {
             Ok(())
         }
     }
 
+    /// Swaps the tilesets and font for the ones in the cart, falling back
+    /// to the default resources for the missing ones.
+    pub fn load_cart(&mut self, ctx: &mut Context, cart: &Cart) -> GameResult {
+        let load = |ctx: &mut Context, data: &Option<Vec<u8>>, path: &str| match data {
+            Some(data) => Sheet::from_png(data),
+            None => Sheet::load(ctx, path),
+        };
+
+        let sheets = CartSheets {
+            tiles: Some(tileset(load(ctx, &cart.tiles, TILESET_PATH)?)?),
+            tiles_alt: Some(tileset(load(ctx, &cart.tiles_alt, TILESET_ALT_PATH)?)?),
+            font: Some(load(ctx, &cart.font, FONT_PATH)?),
+        };
+        self.set_sheets(sheets);
+
+        Ok(())
+    }
+
     pub fn add_system<S: 'static + System>(&mut self, system: S) {
         self.systems.push(Box::new(system));
     }
 
-    pub fn draw_all(&self, ctx: &mut Context, game: &Game) -> GameResult {
+    pub(crate) fn take_systems(&mut self) -> Vec<Box<dyn System>> {
+        std::mem::take(&mut self.systems)
+    }
+
+    pub(crate) fn restore_systems(&mut self, mut systems: Vec<Box<dyn System>>) {
+        systems.append(&mut self.systems);
+        self.systems = systems;
+    }
+
+    /// Size of the virtual screen in pixels
+    pub fn get_win(&self) -> Rect {
+        self.win
+    }
+
+    /// Columns and rows of tiles of the virtual screen
+    pub fn screen_size(&self) -> (u16, u16) {
+        let unit = TILE_SIZE as f32;
+
+        ((self.win.w / unit) as u16, (self.win.h / unit) as u16)
+    }
+
+    /// Row of the status line at the bottom of the screen, in tiles
+    pub fn status_row(&self) -> f32 {
+        let (_, rows) = self.screen_size();
+
+        rows as f32 - 1.
+    }
+
+    /// Resizes the virtual screen, in tiles. The layout of the IDE and the
+    /// mouse viewport follow it.
+    pub fn set_screen(&mut self, columns: u16, rows: u16) -> GameResult {
+        let win = Self::screen_rect(columns, rows)?;
+        self.set_win(win);
+
+        Ok(())
+    }
+
+    /// The virtual screen in pixels for a size in tiles, if it is allowed
+    pub fn screen_rect(columns: u16, rows: u16) -> GameResult<Rect> {
+        let unit = TILE_SIZE as f32;
+        let win = Rect::new(0., 0., columns as f32 * unit, rows as f32 * unit);
+        Self::validate_rect(win)?;
+
+        Ok(win)
+    }
+
+    /// Resizes the virtual screen to a rect checked by `screen_rect`
+    pub(crate) fn set_win(&mut self, win: Rect) {
+        self.win = win;
+        self.mouse.set_viewport(viewport(win));
+    }
+
+    pub fn get_filter_mode(&self) -> FilterMode {
+        self.filter_mode
+    }
+
+    /// Whether the IDE frame, title and mouse cursor are drawn around the
+    /// game, the player turns them off.
+    pub fn set_chrome(&mut self, chrome: bool) {
+        self.chrome = chrome;
+    }
+
+    pub fn get_palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn get_remap(&self) -> Remap {
+        self.remap.get()
+    }
+
+    /// Swaps or hides colors for the draws that follow, scripts change it
+    /// while they draw.
+    pub fn set_remap(&self, remap: Remap) {
+        self.remap.set(remap);
+    }
+
+    /// The color an ink draws with in the current palette and remap, `None`
+    /// when it's transparent.
+    pub fn ink(&self, ink: impl Into<Ink>) -> Option<Color> {
+        self.palette.ink(ink, &self.remap.get())
+    }
+
+    /// What the pixels of a sheet draw as before the ink tints them:
+    /// `Pal::White` pixels stay plain white so they take the ink, the other
+    /// colors go through the palette and remap.
+    pub fn sheet_colors(&self) -> SheetColors {
+        let remap = self.remap.get();
+        let mut colors = [None; 17];
+
+        for (color, pal) in colors.iter_mut().zip(Pal::ALL.iter()) {
+            *color = match pal {
+                Pal::White => Some([0xff; 4]),
+                _ => self.palette.ink(*pal, &remap).map(|c| {
+                    let (r, g, b, a) = c.to_rgba();
+                    [r, g, b, a]
+                }),
+            };
+        }
+
+        SheetColors::new(colors)
+    }
+
+    pub fn draw_all(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        let (columns, rows) = self.screen_size();
+        let (w, h) = (columns as f32, rows as f32);
+
         // window
-        let mesh = self.mesh(ctx, self.win.w as u8, self.win.w as u8, Pal::Off)?;
-        self.draw(ctx, &mesh, 0., 0.)?;
+        self.draw_rect(r, 0., 0., w, h, Pal::Off)?;
 
-        // body
-        let mesh = self.mesh(ctx, 20, 18, Pal::DarkBlue.dark())?;
-        self.draw(ctx, &mesh, 0., 1.)?;
+        if self.chrome {
+            // body, between the top bar and the status row
+            self.draw_rect(r, 0., 1., w, h - 2., Pal::DarkBlue.dark())?;
+        }
 
-        for system in self.systems.iter() {
-            system.draw(ctx, game)?;
+        // editors use the whole body
+        let framed = self.chrome && !game.mode.is_editor();
+
+        if framed {
+            let title = "Rust Retro 8-bit";
+            let title_box = TextLayout::new(Rect::new(1., 2., w - 2., 1.)).align(Align::Center);
+            self.draw_textbox(r, &title_box, Pal::DarkBlue, title, Pal::Blue)?;
+            let body = TextLayout::new(Rect::new(1., 4., w - 2., h - 6.));
+            self.draw_textbox(r, &body, Pal::DarkBlue, "", Pal::White)?;
         }
 
-        self.draw_textbox(ctx, 1., 2., 18., 1., Pal::DarkBlue)?;
-        self.draw_textbox(ctx, 1., 4., 18., 14., Pal::DarkBlue)?;
+        for system in self.systems.iter() {
+            system.draw(r, game)?;
+        }
 
-        self.draw_text(ctx, "Rust Retro 8-bit", 6., 2., Pal::Blue)?;
+        if let Some(script) = game.get_script() {
+            script.draw(r, game)?;
+        }
 
-        // draw mouse last so it's above everything else
-        self.mouse.draw(ctx, game)?;
+        if self.chrome {
+            // draw mouse last so it's above everything else
+            self.mouse.draw(r, game)?;
+        }
 
         Ok(())
     }
 
-    pub fn draw(
-        &self,
-        ctx: &mut ggez::Context,
-        drawable: &impl graphics::Drawable,
-        x: f32,
-        y: f32,
-    ) -> ggez::GameResult {
-        self.draw_free(
-            ctx,
-            drawable,
-            x * TILE_SIZE as f32 * self.scale,
-            y * TILE_SIZE as f32 * self.scale,
-            self.scale,
-        )
+    /// Converts tile coordinates to pixels of the virtual screen
+    fn dest(&self, x: f32, y: f32) -> Point2<f32> {
+        Point2::new(x * TILE_SIZE as f32, y * TILE_SIZE as f32)
     }
 
-    pub fn draw_free(
-        &self,
-        ctx: &mut ggez::Context,
-        drawable: &impl graphics::Drawable,
-        x: f32,
-        y: f32,
-        scale: f32,
-    ) -> ggez::GameResult {
-        graphics::draw(
-            ctx,
-            drawable,
-            graphics::DrawParam::default()
-                .dest(Point2::new(x, y))
-                .scale(Vector2::new(scale, scale)),
-        )
+    /// `p` with its destination moved from tiles to pixels and its ink
+    /// resolved, `None` when the ink is transparent.
+    fn resolve(&self, p: TileParam) -> Option<TileParam<Paint>> {
+        let paint = Paint {
+            colors: self.sheet_colors(),
+            tint: self.ink(p.color)?,
+        };
+
+        Some(TileParam {
+            dest: self.dest(p.dest.x, p.dest.y),
+            color: paint,
+            flip: p.flip,
+        })
     }
 
-    pub fn draw_text(
+    pub fn draw_rect(
         &self,
-        ctx: &mut ggez::Context,
-        text: &str,
+        r: &mut dyn Renderer,
         x: f32,
         y: f32,
-        color: impl Into<graphics::Color>,
-    ) -> ggez::GameResult {
-        self.draw(ctx, &self.text_batch(text, color.into())?, x, y)
+        w: f32,
+        h: f32,
+        color: impl Into<Ink>,
+    ) -> GameResult {
+        let unit = TILE_SIZE as f32;
+
+        match self.ink(color) {
+            Some(color) => r.rect(Rect::new(x * unit, y * unit, w * unit, h * unit), color),
+            None => Ok(()),
+        }
     }
 
-    pub fn draw_textbox(
+    pub fn draw_text(
         &self,
-        ctx: &mut Context,
+        r: &mut dyn Renderer,
+        text: &str,
         x: f32,
         y: f32,
-        w: f32,
-        h: f32,
-        color: impl Into<Color> + Copy,
+        color: impl Into<Ink> + Copy,
     ) -> GameResult {
-        let fx = x * TILE_SIZE as f32 * self.scale;
-        let fy = y * TILE_SIZE as f32 * self.scale;
-        let fw = w * TILE_SIZE as f32;
-        let fh = h * TILE_SIZE as f32;
+        match self.ink(color) {
+            Some(tint) => {
+                let colors = self.sheet_colors();
+                self.font.draw_text(r, text, self.dest(x, y), &colors, tint)
+            }
+            None => Ok(()),
+        }
+    }
 
-        let bg = self.mesh_free(ctx, fw, fh, color)?;
+    /// Size in tiles a text takes when drawn
+    pub fn measure_text(&self, text: &str) -> (f32, f32) {
+        let (columns, rows) = text::measure(text);
+        let unit = TILE_SIZE as f32;
 
-        self.draw_free(ctx, &bg, fx, fy, self.scale)
+        (
+            (columns * FONT_WIDTH as usize) as f32 / unit,
+            (rows * FONT_HEIGHT as usize) as f32 / unit,
+        )
     }
 
-    pub fn mesh(
+    /// Draws text laid out in the box of the layout, nothing is drawn
+    /// outside of it.
+    pub fn draw_text_layout(
         &self,
-        ctx: &mut Context,
-        w: u8,
-        h: u8,
-        c: impl Into<Color>,
-    ) -> GameResult<impl Drawable> {
-        self.mesh_free(
-            ctx,
-            w as f32 * TILE_SIZE as f32,
-            h as f32 * TILE_SIZE as f32,
-            c,
-        )
+        r: &mut dyn Renderer,
+        text: &str,
+        layout: &TextLayout,
+        color: impl Into<Ink> + Copy,
+    ) -> GameResult {
+        for (line, x, y) in layout.lines(text) {
+            self.draw_text(r, &line, x, y, color)?;
+        }
+
+        Ok(())
     }
 
-    pub fn mesh_free(
+    /// Fills the box of a layout and draws text in it, wrapped to its width
+    /// and ellipsized when it's too long for its height.
+    pub fn draw_textbox(
         &self,
-        ctx: &mut Context,
-        w: f32,
-        h: f32,
-        c: impl Into<Color>,
-    ) -> GameResult<impl Drawable> {
-        let mode = graphics::DrawMode::fill();
-        let bounds = Rect::new(0., 0., w, h);
-        let color = c.into();
+        r: &mut dyn Renderer,
+        layout: &TextLayout,
+        color: impl Into<Ink> + Copy,
+        text: &str,
+        text_color: impl Into<Ink> + Copy,
+    ) -> GameResult {
+        let rect = layout.rect();
+        self.draw_rect(r, rect.x, rect.y, rect.w, rect.h, color)?;
 
-        graphics::Mesh::new_rectangle(ctx, mode, bounds, color)
+        self.draw_text_layout(r, text, layout, text_color)
     }
 
-    pub fn tile(
+    pub fn draw_tile(
         &self,
-        t: TileId,
+        r: &mut dyn Renderer,
+        t: impl Into<u16> + Copy + std::fmt::Debug,
         column: u16,
-        c: impl Into<Color> + Copy,
-        flip: bool,
-    ) -> GameResult<SpriteBatch> {
-        self.map.tile(t, column, c, TILE_SIZE, flip)
+        p: TileParam,
+    ) -> GameResult {
+        match self.resolve(p) {
+            Some(p) => self.map.tile(r, t, column, p, TILE_SIZE),
+            None => Ok(()),
+        }
     }
 
-    pub fn tile8(
+    pub fn draw_tile8(
         &self,
-        t: TileId,
+        r: &mut dyn Renderer,
+        t: impl Into<u16> + Copy + std::fmt::Debug,
         column: u16,
-        c: impl Into<Color> + Copy,
-        flip: bool,
-    ) -> GameResult<SpriteBatch> {
-        self.map.tile(t, column, c, TILE_SIZE / 2, flip)
+        p: TileParam,
+    ) -> GameResult {
+        match self.resolve(p) {
+            Some(p) => self.map.tile(r, t, column, p, TILE_SIZE / 2),
+            None => Ok(()),
+        }
     }
 
-    pub fn tile_alt(
+    pub fn draw_tile_alt(
         &self,
+        r: &mut dyn Renderer,
         row: u16,
         column: u16,
-        c: impl Into<Color> + Copy,
-        flip: bool,
-    ) -> GameResult<SpriteBatch> {
-        self.map2.tile(row, column, c, TILE_SIZE, flip)
+        p: TileParam,
+    ) -> GameResult {
+        match self.resolve(p) {
+            Some(p) => self.map2.tile(r, row, column, p, TILE_SIZE),
+            None => Ok(()),
+        }
     }
 
-    pub fn fill8(
+    pub fn draw_fill8(
         &self,
+        r: &mut dyn Renderer,
         t: TileId,
         column: u16,
-        w: u8,
-        h: u8,
-        c: impl Into<Color> + Copy,
-    ) -> GameResult<SpriteBatch> {
-        self.map.fill(t, column, w, h, c, TILE_SIZE / 2)
+        size: (u8, u8),
+        p: TileParam,
+    ) -> GameResult {
+        match self.resolve(p) {
+            Some(p) => self.map.fill(r, t, column, size, p, TILE_SIZE / 2),
+            None => Ok(()),
+        }
     }
 
-    pub fn fill_alt(
+    pub fn draw_fill_alt(
         &self,
+        r: &mut dyn Renderer,
         row: u16,
         column: u16,
-        w: u8,
-        h: u8,
-        c: impl Into<Color> + Copy,
-    ) -> GameResult<SpriteBatch> {
-        self.map2.fill(row, column, w, h, c, TILE_SIZE)
+        size: (u8, u8),
+        p: TileParam,
+    ) -> GameResult {
+        match self.resolve(p) {
+            Some(p) => self.map2.fill(r, row, column, size, p, TILE_SIZE),
+            None => Ok(()),
+        }
     }
 
-    pub fn text_batch(
-        &self,
-        text: &str,
-        color: impl Into<Color> + Copy,
-    ) -> GameResult<SpriteBatch> {
-        self.font.text_batch(text, color)
+    pub fn draw_cell(&self, r: &mut dyn Renderer, cell: &Cell, x: f32, y: f32) -> GameResult {
+        let p = TileParam::new(x, y, cell.color).flip(cell.flip);
+        match cell.tileset {
+            Tileset::Main => self.draw_tile(r, cell.row, cell.column, p),
+            Tileset::Alt => self.draw_tile_alt(r, cell.row, cell.column, p),
+        }
+    }
+
+    pub fn sheet(&self, tileset: Tileset) -> &Sheet {
+        match tileset {
+            Tileset::Main => self.map.sheet(),
+            Tileset::Alt => self.map2.sheet(),
+        }
+    }
+
+    pub fn sheet_mut(&mut self, tileset: Tileset) -> &mut Sheet {
+        match tileset {
+            Tileset::Main => self.map.sheet_mut(),
+            Tileset::Alt => self.map2.sheet_mut(),
+        }
+    }
+
+    /// Writes the tilesets edited since they were loaded into the cart.
+    pub fn store_cart(&self, cart: &mut Cart) -> GameResult {
+        if self.map.sheet().version() > 0 {
+            cart.tiles = Some(self.map.sheet().to_png()?);
+        }
+        if self.map2.sheet().version() > 0 {
+            cart.tiles_alt = Some(self.map2.sheet().to_png()?);
+        }
+
+        Ok(())
+    }
+
+    /// Writes every sheet into the cart, edited or not, so restoring it
+    /// doesn't need the default resources.
+    pub fn store_sheets(&self, cart: &mut Cart) -> GameResult {
+        cart.tiles = Some(self.map.sheet().to_png()?);
+        cart.tiles_alt = Some(self.map2.sheet().to_png()?);
+        cart.font = Some(self.font.sheet().to_png()?);
+
+        Ok(())
+    }
+
+    /// Swaps the sheets for the ones in the cart, keeping the current ones
+    /// for the missing ones.
+    pub fn set_sheets(&mut self, sheets: CartSheets) {
+        if let Some(sheet) = sheets.tiles {
+            self.map.set_sheet(sheet);
+        }
+        if let Some(sheet) = sheets.tiles_alt {
+            self.map2.set_sheet(sheet);
+        }
+        if let Some(sheet) = sheets.font {
+            self.font.set_sheet(sheet);
+        }
+    }
+
+    /// Rows and columns of 16x16 tiles in a tileset
+    pub fn tileset_size(&self, tileset: Tileset) -> (u16, u16) {
+        let sheet = self.sheet(tileset);
+
+        (sheet.height() / TILE_SIZE, sheet.width() / TILE_SIZE)
     }
 
     pub fn set_mouse_coords(&mut self, coords: (f32, f32)) {
         self.mouse.set_coords(coords);
     }
 
+    pub fn set_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.mouse.set_button(button, pressed);
+    }
+
+    pub fn get_mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+
     pub fn get_scale(&self) -> f32 {
         self.scale
     }
@@ -303,3 +652,10 @@ impl Ui {
         }
     }
 }
+
+/// Tiles of the body of a screen, below the top bar and above the status row
+fn viewport(win: Rect) -> (u16, u16) {
+    let unit = TILE_SIZE as f32;
+
+    ((win.w / unit) as u16, (win.h / unit) as u16 - 2)
+}
@@ -0,0 +1,102 @@
+use ggez::graphics::Rect;
+
+use crate::code::{highlight, GUTTER};
+use crate::render::Renderer;
+use crate::ui::text::TextLayout;
+use crate::ui::tile::TileParam;
+use crate::*;
+
+/// Draws the script being edited in `GameMode::Code`, with line numbers,
+/// the selection and colored tokens.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct CodeEditor;
+
+impl System for CodeEditor {
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        if game.mode != GameMode::Code {
+            return Ok(());
+        }
+        let ui = &game.ui;
+        let edit = game.get_code_edit();
+        let cursor = edit.cursor();
+        let scroll = edit.scroll();
+        let selection = edit.selection();
+        // the boxes span the screen but for a tile of margin on each side
+        let (screen_columns, _) = ui.screen_size();
+        let w = screen_columns as f32 - 2.;
+
+        let title = TextLayout::new(Rect::new(1., 2., w, 1.));
+        ui.draw_textbox(r, &title, Pal::DarkBlue, "", Pal::White)?;
+        let body = TextLayout::new(Rect::new(1., 4., w, edit.rows() as f32));
+        ui.draw_textbox(r, &body, Pal::DarkBlue, "", Pal::White)?;
+
+        match edit.get_search() {
+            Some(query) => {
+                ui.draw_text(r, "find:", 1., 2., Pal::Gray)?;
+                ui.draw_text(r, query, 4., 2., Pal::White)?;
+            }
+            None => {
+                let position = format!("{}:{}", cursor.line + 1, cursor.column + 1);
+                ui.draw_text(r, &position, 1., 2., Pal::Blue)?;
+            }
+        }
+
+        // text starts after the line numbers, two chars per tile
+        let left = 1. + GUTTER as f32 / 2.;
+        let columns = edit.columns() - GUTTER;
+        let x = |column: usize| left + (column - scroll.column) as f32 / 2.;
+
+        for (i, line) in edit.lines().iter().enumerate().skip(scroll.line) {
+            let row = i - scroll.line;
+            if row >= edit.rows() {
+                break;
+            }
+            let y = 4. + row as f32;
+            let number = format!("{:>3}", i + 1);
+            ui.draw_text(r, &number, 1., y, Pal::Gray.dark())?;
+
+            if let Some((start, end)) = selection.filter(|(s, e)| s.line <= i && i <= e.line) {
+                let from = if start.line == i { start.column } else { 0 };
+                // selected line breaks show as an extra char
+                let to = if end.line == i {
+                    end.column
+                } else {
+                    line.chars().count() + 1
+                };
+                let from = from.max(scroll.column);
+                let to = to.min(scroll.column + columns);
+                if from < to {
+                    let w = (to - from) as f32 / 2.;
+                    ui.draw_rect(r, x(from), y, w, 1., Pal::Purple)?;
+                }
+            }
+
+            if cursor.line == i && edit.get_search().is_none() {
+                let column = if ui.dt & 0b100000 > 0 { 15 } else { 20 };
+                let cx = x(cursor.column);
+                ui.draw_tile8(r, TileId::Ico, column, TileParam::new(cx, y, Pal::Red))?;
+            }
+
+            let mut column = 0;
+            for (token, text) in highlight(line) {
+                let len = text.chars().count();
+                let (from, to) = (
+                    column.max(scroll.column),
+                    (column + len).min(scroll.column + columns),
+                );
+                if from < to {
+                    let visible: String =
+                        text.chars().skip(from - column).take(to - from).collect();
+                    ui.draw_text(r, &visible, x(from), y, token.color())?;
+                }
+                column += len;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,43 +1,45 @@
 use std::collections::HashMap;
 
-use ggez::graphics;
-use ggez::graphics::{spritebatch::SpriteBatch, DrawParam, Image, Rect};
-use ggez::graphics::{Color, FilterMode};
+use ggez::graphics::{Color, Rect};
 use ggez::nalgebra::Point2;
 
+use crate::render::{Renderer, Sheet, SheetColors};
 use crate::*;
 
 #[derive(Debug)]
 pub struct Font {
-    image: graphics::Image,
-    font_map: HashMap<char, f32>,
-    filter_mode: FilterMode,
+    sheet: Sheet,
+    font_map: HashMap<char, u16>,
 }
 
 impl Font {
-    pub fn new(ctx: &mut Context, filter_mode: FilterMode) -> GameResult<Self> {
-        let image = Image::new(ctx, FONT_PATH)?;
+    pub fn new(sheet: Sheet) -> Self {
         let font_map = FONT_MAP
             .chars()
             .enumerate()
-            .map(|(i, c)| (c, i as f32))
+            .map(|(i, c)| (c, i as u16))
             .collect();
 
-        Ok(Self {
-            image,
-            font_map,
-            filter_mode,
-        })
+        Self { sheet, font_map }
     }
 
-    pub fn text_batch(
+    pub fn set_sheet(&mut self, sheet: Sheet) {
+        self.sheet = sheet;
+    }
+
+    pub fn sheet(&self) -> &Sheet {
+        &self.sheet
+    }
+
+    /// Draws text with its top left corner at `dest` (in pixels).
+    pub fn draw_text(
         &self,
+        r: &mut dyn Renderer,
         text: &str,
-        color: impl Into<Color> + Copy,
-    ) -> GameResult<SpriteBatch> {
-        let mut batch = SpriteBatch::new(self.image.clone());
-        batch.set_filter(self.filter_mode);
-
+        dest: Point2<f32>,
+        colors: &SheetColors,
+        tint: Color,
+    ) -> GameResult {
         let mut column = 0;
         let mut line = 0;
         for c in text.chars() {
@@ -46,29 +48,34 @@ impl Font {
                 line += 1;
                 continue;
             }
-            batch.add(
-                self.build_draw_param_from_char(&c)
-                    .color(color.into())
-                    .dest(Point2::new(
-                        (column * FONT_WIDTH) as f32,
-                        (line * FONT_HEIGHT) as f32,
-                    )),
-            );
+            r.sprite(
+                &self.sheet,
+                self.src_from_char(&c),
+                Point2::new(
+                    dest.x + (column * FONT_WIDTH) as f32,
+                    dest.y + (line * FONT_HEIGHT) as f32,
+                ),
+                colors,
+                tint,
+                false,
+            )?;
             column += 1;
         }
 
-        Ok(batch)
+        Ok(())
     }
 
-    fn build_draw_param_from_char(&self, c: &char) -> DrawParam {
-        let char_id = self.font_map.get(c).unwrap_or(&1023.);
-        let w = self.image.width() as f32;
-        let h = self.image.height() as f32;
-        DrawParam::default().src(Rect::new(
-            char_id * FONT_WIDTH as f32 / w,
-            (*char_id as u32 * FONT_WIDTH as u32 / w as u32) as f32 / (h / FONT_HEIGHT as f32),
-            FONT_WIDTH as f32 / w,
-            FONT_HEIGHT as f32 / h,
-        ))
+    fn src_from_char(&self, c: &char) -> Rect {
+        let char_id = *self.font_map.get(c).unwrap_or(&1023) as u32;
+        let w = self.sheet.width() as u32;
+        let x = char_id * FONT_WIDTH as u32 % w;
+        let y = char_id * FONT_WIDTH as u32 / w * FONT_HEIGHT as u32;
+
+        Rect::new(
+            x as f32,
+            (y % self.sheet.height() as u32) as f32,
+            FONT_WIDTH as f32,
+            FONT_HEIGHT as f32,
+        )
     }
 }
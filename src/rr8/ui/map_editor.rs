@@ -0,0 +1,60 @@
+use crate::render::Renderer;
+use crate::*;
+
+/// Draws the map and paints it with the mouse in `GameMode::Map`: left
+/// button paints the brush, right button erases.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct MapEditor;
+
+impl System for MapEditor {
+    fn update(&mut self, game: &mut Game) -> GameResult {
+        if game.mode != GameMode::Map {
+            return Ok(());
+        }
+
+        let mouse = game.ui.get_mouse();
+        let (left, right) = (mouse.is_left_down(), mouse.is_right_down());
+        if let Some((x, y)) = mouse.viewport_tile() {
+            let edit = game.get_map_edit();
+            let (layer, brush) = (edit.layer, edit.brush);
+
+            if left {
+                game.map.set(layer, x, y, Some(brush));
+            } else if right {
+                game.map.set(layer, x, y, None);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        if game.mode != GameMode::Map {
+            return Ok(());
+        }
+        let ui = &game.ui;
+
+        game.map.draw(r, ui, 0., 1.)?;
+
+        let edit = game.get_map_edit();
+        let brush = edit.brush;
+        let status_y = ui.status_row();
+        ui.draw_cell(r, &brush, 1., status_y)?;
+
+        let hidden = match game.map.layers().get(edit.layer) {
+            Some(layer) if !layer.visible => " hidden",
+            _ => "",
+        };
+        let status = format!(
+            "L{}{} {},{} {:?}",
+            edit.layer + 1,
+            hidden,
+            brush.row,
+            brush.column,
+            brush.color
+        );
+        ui.draw_text(r, &status, 2.5, status_y, Pal::Blue)?;
+
+        Ok(())
+    }
+}
@@ -1,49 +1,123 @@
+use crate::map::Tileset;
+use crate::render::Renderer;
+use crate::ui::tile::TileParam;
+use crate::ui::{SCREEN_COLUMNS, SCREEN_ROWS};
 use crate::*;
-use ggez::graphics::Color;
+use ggez::event::MouseButton;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct Mouse {
     coords: (f32, f32),
+    left: bool,
+    right: bool,
+    viewport: (u16, u16),
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Self {
+            coords: (0., 0.),
+            left: false,
+            right: false,
+            viewport: (SCREEN_COLUMNS, SCREEN_ROWS - 2),
+        }
+    }
 }
 
 impl Mouse {
+    /// Position in pixels of the virtual screen
     pub fn set_coords(&mut self, coords: (f32, f32)) {
         self.coords = coords;
     }
+
+    pub fn set_button(&mut self, button: MouseButton, pressed: bool) {
+        match button {
+            MouseButton::Left => self.left = pressed,
+            MouseButton::Right => self.right = pressed,
+            _ => {}
+        }
+    }
+
+    /// Columns and rows of tiles of the viewport, the `Ui` sets it from the
+    /// screen size.
+    pub fn set_viewport(&mut self, viewport: (u16, u16)) {
+        self.viewport = viewport;
+    }
+
+    pub fn get_viewport(&self) -> (u16, u16) {
+        self.viewport
+    }
+
+    pub fn is_left_down(&self) -> bool {
+        self.left
+    }
+
+    pub fn is_right_down(&self) -> bool {
+        self.right
+    }
+
+    /// Position of the mouse in the viewport in tiles, the viewport starts
+    /// below the top bar.
+    pub fn viewport_point(&self) -> Option<(f32, f32)> {
+        let (x, y) = self.coords;
+        let tx = x / TILE_SIZE as f32;
+        let ty = y / TILE_SIZE as f32 - 1.;
+        let (w, h) = self.viewport;
+
+        if 0. <= tx && tx < w as f32 && 0. <= ty && ty < h as f32 {
+            Some((tx, ty))
+        } else {
+            None
+        }
+    }
+
+    /// Tile of the viewport under the mouse
+    pub fn viewport_tile(&self) -> Option<(u16, u16)> {
+        self.viewport_point()
+            .map(|(x, y)| (x.floor() as u16, y.floor() as u16))
+    }
 }
 
 impl System for Mouse {
-    fn update(&mut self, ctx: &mut Context, game: &mut Game) -> GameResult {
-        todo!()
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
     }
 
-    fn draw(&self, ctx: &mut Context, game: &Game) -> GameResult {
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
         let ui = &game.ui;
 
-        // get coords in tile units (instead of float pixels), adjusted for the top and side shifting
-        let pixel_to_tile_coord = |coord: f32, offset: f32| -> f32 {
-            ((coord / ui.scale) / TILE_SIZE as f32 - offset) as u32 as f32
+        let (dx, dy) = match self.viewport_tile() {
+            Some((dx, dy)) => (dx as f32, dy as f32),
+            None => return Ok(()),
         };
+        // tiles origin is offset wrt mouse coords
+        let (ox, oy) = (0., 1.);
 
-        if let GameMode::Normal = game.mode {
-            let (x, y) = self.coords;
+        match game.mode {
+            GameMode::Normal => {
+                let (x, y) = self.coords;
 
-            let (ox, oy) = (0., 1.);
-            // tiles origin is offset wrt mouse coords
-            let dx = pixel_to_tile_coord(x, ox);
-            let dy = pixel_to_tile_coord(y, oy);
-
-            if 0. <= dx && dx < 20. && 0. <= dy && dy < 18. {
-                // viewport is Rect::new(1.,1.,18.,18.);
                 let (tx, ty) = (0, 1);
-                let mut hover_color: Color = Pal::Red.into();
-                hover_color.a = 0.5;
+                let hover_color = Pal::Red.alpha(0.5);
+
+                ui.draw_tile_alt(r, tx, ty, TileParam::new(dx + ox, dy + oy, hover_color))?;
 
-                let tile = ui.tile_alt(tx, ty, hover_color, false)?;
-                ui.draw(ctx, &tile, dx + ox, dy + oy)?;
+                // status row, below the viewport
+                let status_y = self.viewport.1 as f32 + 1.;
+                ui.draw_text(r, &p((dx, dy, x, y)), 1., status_y, Pal::DarkBlue)?;
+            }
+            GameMode::Map => {
+                // preview of what a click paints
+                let brush = game.get_map_edit().brush;
+                let hover_color = brush.color.alpha(0.5);
 
-                ui.draw_text(ctx, &p((dx, dy, x, y)), 1., 19., Pal::DarkBlue)?;
+                let p = TileParam::new(dx + ox, dy + oy, hover_color).flip(brush.flip);
+                match brush.tileset {
+                    Tileset::Main => ui.draw_tile(r, brush.row, brush.column, p)?,
+                    Tileset::Alt => ui.draw_tile_alt(r, brush.row, brush.column, p)?,
+                }
             }
+            _ => {}
         }
 
         Ok(())
@@ -0,0 +1,94 @@
+use crate::render::Renderer;
+use crate::sfx::MAX_PITCH;
+use crate::*;
+
+/// Positions of the order listed at once
+const ORDER_ROWS: usize = 16;
+const CHANNELS_X: f32 = 6.;
+const CHANNEL_H: f32 = 4.;
+/// Width of a step in the preview of the sfx of a channel, 32 steps fit
+/// right of the order
+const STEP_W: f32 = 0.375;
+
+/// Arranges the song in `GameMode::Music`: the pattern order is listed on
+/// the left with the loop, the sfx of the channels of the selected
+/// position on the right.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct MusicEditor;
+
+impl System for MusicEditor {
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        if game.mode != GameMode::Music {
+            return Ok(());
+        }
+        let ui = &game.ui;
+        let song = &game.song;
+        let edit = game.get_music_edit();
+
+        // keep the selected position in the list
+        let first = edit.position.saturating_sub(ORDER_ROWS - 1);
+        for (i, pattern) in song.order.iter().enumerate().skip(first).take(ORDER_ROWS) {
+            let y = 2. + (i - first) as f32;
+            if i == edit.position {
+                ui.draw_rect(r, 0.5, y, 4.5, 1., Pal::DarkBlue)?;
+            }
+            let looped = song.loop_start <= i && i <= song.loop_end;
+            let mark = match (i == song.loop_start, i == song.loop_end) {
+                (true, true) => "=",
+                (true, false) => "[",
+                (false, true) => "]",
+                _ if looped => ":",
+                _ => " ",
+            };
+            ui.draw_text(r, mark, 0.5, y, Pal::Orange)?;
+            let color = if looped { Pal::White } else { Pal::Gray };
+            ui.draw_text(r, &format!("{:02} {:02}", i, pattern), 1., y, color)?;
+        }
+
+        let pattern = song.pattern_at(edit.position).copied().unwrap_or_default();
+        let (columns, _) = ui.screen_size();
+        for (c, channel) in pattern.channels.iter().enumerate() {
+            let y = 2. + c as f32 * CHANNEL_H;
+            if c == edit.channel {
+                let w = columns as f32 - CHANNELS_X;
+                ui.draw_rect(r, CHANNELS_X - 0.5, y, w, CHANNEL_H - 0.5, Pal::DarkBlue)?;
+            }
+
+            let sfx = channel.and_then(|n| game.sfx.get(n as usize));
+            let label = match channel {
+                Some(n) => format!("CH{} SFX {:02}", c + 1, n),
+                None => format!("CH{} --", c + 1),
+            };
+            ui.draw_text(r, &label, CHANNELS_X, y, Pal::Blue)?;
+
+            // pitches of the steps, like in the sfx editor
+            let bottom = y + CHANNEL_H - 0.5;
+            let height = CHANNEL_H - 1.5;
+            for (i, step) in sfx.iter().flat_map(|s| s.steps.iter()).enumerate() {
+                if step.volume == 0 {
+                    continue;
+                }
+                let h = (step.pitch + 1) as f32 / (MAX_PITCH + 1) as f32 * height;
+                let x = CHANNELS_X + i as f32 * STEP_W;
+                ui.draw_rect(r, x, bottom - h, STEP_W / 2., h, Pal::Pink)?;
+            }
+        }
+
+        let playing = if game.get_audio().is_music_playing() {
+            " PLAYING"
+        } else {
+            ""
+        };
+        let status = format!(
+            "TEMPO {} LOOP {:02}-{:02}{}",
+            song.tempo, song.loop_start, song.loop_end, playing
+        );
+        ui.draw_text(r, &status, 1., ui.status_row(), Pal::Blue)?;
+
+        Ok(())
+    }
+}
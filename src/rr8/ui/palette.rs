@@ -1,6 +1,11 @@
+use std::path::Path;
+
 use ggez::graphics::Color;
+use ggez::GameError;
+
+use crate::*;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Pal {
     Off,
     Black,
@@ -22,53 +27,302 @@ pub enum Pal {
 }
 
 impl Pal {
-    pub fn darken(self, factor: f32) -> Color {
-        let mut color: Color = self.into();
-        color.r *= 1. - factor;
-        color.g *= 1. - factor;
-        color.b *= 1. - factor;
+    pub const ALL: [Pal; 17] = [
+        Pal::Off,
+        Pal::Black,
+        Pal::DarkBlue,
+        Pal::Purple,
+        Pal::DarkGreen,
+        Pal::Maroon,
+        Pal::Brown,
+        Pal::LightGray,
+        Pal::White,
+        Pal::Red,
+        Pal::Orange,
+        Pal::Yellow,
+        Pal::Green,
+        Pal::Blue,
+        Pal::Gray,
+        Pal::Pink,
+        Pal::Peach,
+    ];
 
-        color
+    pub fn from_index(i: usize) -> Option<Pal> {
+        Self::ALL.get(i).copied()
     }
 
-    pub fn dark(self) -> Color {
+    /// Shaded towards black by `factor`, a negative factor lightens it.
+    pub fn darken(self, factor: f32) -> Ink {
+        Ink::Pal(self, factor, 1.)
+    }
+
+    pub fn dark(self) -> Ink {
         self.darken(0.5)
     }
 
-    pub fn darker(self) -> Color {
+    pub fn darker(self) -> Ink {
         self.darken(0.75)
     }
 
-    pub fn light(self) -> Color {
+    pub fn light(self) -> Ink {
         self.darken(-0.5)
     }
 
-    pub fn lighter(self) -> Color {
+    pub fn lighter(self) -> Ink {
         self.darken(-0.75)
     }
+
+    pub fn alpha(self, alpha: f32) -> Ink {
+        Ink::Pal(self, 0., alpha)
+    }
 }
 
+/// The colors of the default palette, the palette of a cart overrides some
+/// of them.
 impl Into<Color> for Pal {
     fn into(self) -> Color {
-        match self {
-            Self::Off => Color::from((0x06, 0x08, 0x08)),
-            Self::Black => Color::from((0x10, 0x11, 0x11)),
-            Self::DarkBlue => Color::from((0x1D, 0x2B, 0x53)),
-            Self::Purple => Color::from((0x7E, 0x25, 0x53)),
-            Self::DarkGreen => Color::from((0x00, 0x87, 0x51)),
-            Self::Maroon => Color::from((0xAB, 0x52, 0x36)),
-            Self::Brown => Color::from((0x5F, 0x57, 0x4F)),
-            Self::LightGray => Color::from((0xC2, 0xC3, 0xC7)),
-            // Self::White => Color::from((0xFF, 0xF1, 0xE8)),
-            Self::White => Color::from((0xF0, 0xF6, 0xF0)),
-            Self::Red => Color::from((0xFF, 0x00, 0x4D)),
-            Self::Orange => Color::from((0xFF, 0xA3, 0x00)),
-            Self::Yellow => Color::from((0xFF, 0xEC, 0x27)),
-            Self::Green => Color::from((0x00, 0xE4, 0x36)),
-            Self::Blue => Color::from((0x29, 0xAD, 0xFF)),
-            Self::Gray => Color::from((0x83, 0x76, 0x9C)),
-            Self::Pink => Color::from((0xFF, 0x77, 0xA8)),
-            Self::Peach => Color::from((0xFF, 0xCC, 0xAA)),
+        Palette::default().color(self)
+    }
+}
+
+const DEFAULT_COLORS: [(u8, u8, u8); 17] = [
+    (0x06, 0x08, 0x08),
+    (0x10, 0x11, 0x11),
+    (0x1D, 0x2B, 0x53),
+    (0x7E, 0x25, 0x53),
+    (0x00, 0x87, 0x51),
+    (0xAB, 0x52, 0x36),
+    (0x5F, 0x57, 0x4F),
+    (0xC2, 0xC3, 0xC7),
+    // (0xFF, 0xF1, 0xE8),
+    (0xF0, 0xF6, 0xF0),
+    (0xFF, 0x00, 0x4D),
+    (0xFF, 0xA3, 0x00),
+    (0xFF, 0xEC, 0x27),
+    (0x00, 0xE4, 0x36),
+    (0x29, 0xAD, 0xFF),
+    (0x83, 0x76, 0x9C),
+    (0xFF, 0x77, 0xA8),
+    (0xFF, 0xCC, 0xAA),
+];
+
+/// The RGB values behind `Pal`, swapped per cart.
+///
+/// Palettes are imported from GIMP `.gpl` files or `.hex` files with one
+/// `rrggbb` color per line. A file of 17 colors sets all of them, shorter
+/// ones start at `Pal::Black` so 16 color PICO-8 palettes line up.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Palette {
+    colors: [(u8, u8, u8); 17],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: DEFAULT_COLORS,
+        }
+    }
+}
+
+impl Palette {
+    /// The default palette with the colors of a cart swapped in
+    pub fn from_overrides(overrides: &[(Pal, (u8, u8, u8))]) -> Self {
+        let mut palette = Self::default();
+        for (pal, rgb) in overrides.iter() {
+            palette.set(*pal, *rgb);
+        }
+
+        palette
+    }
+
+    /// Colors that differ from the default palette, as stored in carts
+    pub fn overrides(&self) -> Vec<(Pal, (u8, u8, u8))> {
+        Pal::ALL
+            .iter()
+            .filter(|pal| self.rgb(**pal) != DEFAULT_COLORS[**pal as usize])
+            .map(|pal| (*pal, self.rgb(*pal)))
+            .collect()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gpl") => Self::parse_gpl(&text),
+            Some("hex") => Self::parse_hex(&text),
+            _ => Err(palette_error(&format!(
+                "{} is not a .gpl or .hex file",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Reads a GIMP palette: a header, then `r g b name` lines
+    pub fn parse_gpl(text: &str) -> GameResult<Self> {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some("GIMP Palette") {
+            return Err(palette_error("missing GIMP Palette header"));
+        }
+
+        let mut colors = Vec::new();
+        for line in lines.map(str::trim) {
+            let is_header = line.starts_with("Name:") || line.starts_with("Columns:");
+            if line.is_empty() || line.starts_with('#') || is_header {
+                continue;
+            }
+
+            let bad = || palette_error(&format!("bad color {:?}", line));
+            let mut channels = line.split_whitespace().map(|c| c.parse::<u8>());
+            let mut next = || channels.next().and_then(Result::ok).ok_or_else(bad);
+            colors.push((next()?, next()?, next()?));
+        }
+
+        Self::from_colors(&colors)
+    }
+
+    /// Reads `rrggbb` lines, with or without a leading `#`
+    pub fn parse_hex(text: &str) -> GameResult<Self> {
+        let colors = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                parse_rgb(line).ok_or_else(|| palette_error(&format!("bad color {:?}", line)))
+            })
+            .collect::<GameResult<Vec<_>>>()?;
+
+        Self::from_colors(&colors)
+    }
+
+    fn from_colors(colors: &[(u8, u8, u8)]) -> GameResult<Self> {
+        let first = match colors.len() {
+            0 => return Err(palette_error("no colors")),
+            17 => 0,
+            n if n <= 16 => 1,
+            n => return Err(palette_error(&format!("{} colors, at most 17", n))),
+        };
+
+        let mut palette = Self::default();
+        for (pal, rgb) in Pal::ALL[first..].iter().zip(colors.iter()) {
+            palette.set(*pal, *rgb);
         }
+
+        Ok(palette)
+    }
+
+    pub fn rgb(&self, pal: Pal) -> (u8, u8, u8) {
+        self.colors[pal as usize]
     }
+
+    pub fn set(&mut self, pal: Pal, rgb: (u8, u8, u8)) {
+        self.colors[pal as usize] = rgb;
+    }
+
+    pub fn color(&self, pal: Pal) -> Color {
+        Color::from(self.rgb(pal))
+    }
+
+    /// The color an ink draws with, `None` if the remap hides it.
+    pub fn ink(&self, ink: impl Into<Ink>, remap: &Remap) -> Option<Color> {
+        match ink.into() {
+            Ink::Pal(pal, factor, alpha) => {
+                let mut color = self.color(remap.apply(pal)?);
+                color.r *= 1. - factor;
+                color.g *= 1. - factor;
+                color.b *= 1. - factor;
+                color.a = alpha;
+
+                Some(color)
+            }
+            Ink::Color(color) => Some(color),
+        }
+    }
+
+    /// The palette color closest to a pixel, blended and shaded colors end
+    /// up on their nearest palette color.
+    pub fn nearest(&self, pixel: [u8; 4]) -> Pal {
+        let distance = |pal: &Pal| {
+            let (r, g, b) = self.rgb(*pal);
+            [(r, pixel[0]), (g, pixel[1]), (b, pixel[2])]
+                .iter()
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+                .sum::<i32>()
+        };
+
+        *Pal::ALL.iter().min_by_key(|pal| distance(pal)).unwrap()
+    }
+}
+
+/// What `Ui` draws with: a palette color, resolved against the palette
+/// and remap of the `Ui`, or a color outside of the palette.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ink {
+    /// Color, shade (see `Pal::darken`) and alpha
+    Pal(Pal, f32, f32),
+    Color(Color),
+}
+
+impl From<Pal> for Ink {
+    fn from(pal: Pal) -> Self {
+        Ink::Pal(pal, 0., 1.)
+    }
+}
+
+impl From<Color> for Ink {
+    fn from(color: Color) -> Self {
+        Ink::Color(color)
+    }
+}
+
+/// Colors swapped or hidden when drawing, like `pal` and `palt` in
+/// PICO-8: drawing with `from` draws with `to` instead, drawing with a
+/// transparent color draws nothing.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Remap {
+    to: [Pal; 17],
+    transparent: [bool; 17],
+}
+
+impl Default for Remap {
+    fn default() -> Self {
+        Self {
+            to: Pal::ALL,
+            transparent: [false; 17],
+        }
+    }
+}
+
+impl Remap {
+    pub fn set(&mut self, from: Pal, to: Pal) {
+        self.to[from as usize] = to;
+    }
+
+    pub fn set_transparent(&mut self, pal: Pal, transparent: bool) {
+        self.transparent[pal as usize] = transparent;
+    }
+
+    /// The color drawn instead of `pal`, `None` when it's transparent
+    pub fn apply(&self, pal: Pal) -> Option<Pal> {
+        if self.transparent[pal as usize] {
+            None
+        } else {
+            Some(self.to[pal as usize])
+        }
+    }
+}
+
+/// Parses `rrggbb` or `#rrggbb`
+pub fn parse_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+
+    Some(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+fn palette_error(msg: &str) -> GameError {
+    GameError::ResourceLoadError(format!("palette: {}", msg))
 }
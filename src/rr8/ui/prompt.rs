@@ -1,30 +1,62 @@
+use ggez::graphics::Rect;
+
+use crate::render::Renderer;
+use crate::ui::text::{Align, TextLayout};
+use crate::ui::tile::TileParam;
 use crate::*;
 
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct Prompt;
 
 impl System for Prompt {
-    fn update(&mut self, ctx: &mut Context, game: &mut Game) -> GameResult {
-        todo!()
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
     }
-    fn draw(&self, ctx: &mut Context, game: &Game) -> GameResult {
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
         let ui = &game.ui;
 
         if let GameMode::Prompt = &game.mode {
             // GameMode::Normal => (Pal::Gray.dark(), game.get_status()),
             let column = if ui.dt & 0b100000 > 0 { 15 } else { 20 };
-            let beam = ui.tile8(TileId::Ico, column, Pal::Red, false)?;
-
             let (cursor_pos, prompt) = game.get_prompt();
+            let (columns, rows) = ui.screen_size();
+            // the prompt takes the status row, the scrollback the big text box
+            let prompt_y = ui.status_row();
 
             // this also works nice because drawing the beam before the
             // prompt makes the char underneath it visible
-            ui.draw(ctx, &beam, 2. + cursor_pos as f32 / 2., 19.)?;
+            let x = 2. + cursor_pos as f32 / 2.;
+            ui.draw_tile8(
+                r,
+                TileId::Ico,
+                column,
+                TileParam::new(x, prompt_y, Pal::Red),
+            )?;
 
-            let (prompt_color, prompt_text) = (Pal::Blue, prompt);
+            // scrollback in the big text box
+            let console = game.get_console();
+            for (i, (line, color)) in console.visible().iter().enumerate() {
+                ui.draw_text(r, line, 1., 4. + i as f32, *color)?;
+            }
+            if console.get_scroll() > 0 {
+                let more = format!("+{}", console.get_scroll());
+                let row = Rect::new(1., rows as f32 - 3., columns as f32 - 2., 1.);
+                let layout = TextLayout::new(row).align(Align::Right);
+                ui.draw_text_layout(r, &more, &layout, Pal::Gray.dark())?;
+            }
 
-            ui.draw_text(ctx, "#", 1., 19., Pal::Gray.dark())?;
-            ui.draw_text(ctx, prompt_text, 2., 19., prompt_color)?;
+            ui.draw_text(r, "#", 1., prompt_y, Pal::Gray.dark())?;
+            match game.get_message() {
+                Some((message, is_error)) if prompt.is_empty() => {
+                    // the output is cut to the prompt row, the console has
+                    // all of it
+                    let color = if is_error { Pal::Red } else { Pal::Gray };
+                    let row = Rect::new(2., prompt_y, columns as f32 - 3., 1.);
+                    let layout = TextLayout::new(row).wrap(false);
+                    ui.draw_text_layout(r, message, &layout, color)?;
+                }
+                _ => ui.draw_text(r, prompt, 2., prompt_y, Pal::Blue)?,
+            }
         }
 
         Ok(())
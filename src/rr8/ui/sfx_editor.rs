@@ -0,0 +1,129 @@
+use crate::render::Renderer;
+use crate::sfx::{note_name, Wave, MAX_PITCH, MAX_VOLUME, SFX_STEPS};
+use crate::*;
+
+/// Left of the step columns, each step is half a tile wide
+const STEPS_X: f32 = 1.;
+const STEP_W: f32 = 0.5;
+/// Pitches are drawn as bars rising from the bottom of their area
+const PITCH_TOP: f32 = 2.;
+const PITCH_BOTTOM: f32 = 14.;
+const EFFECT_Y: f32 = 14.;
+const VOLUME_TOP: f32 = 15.;
+const VOLUME_BOTTOM: f32 = 18.;
+
+fn wave_color(wave: Wave) -> Pal {
+    match wave {
+        Wave::Square => Pal::Pink,
+        Wave::Triangle => Pal::Green,
+        Wave::Saw => Pal::Orange,
+        Wave::Noise => Pal::LightGray,
+    }
+}
+
+/// Edits sound effects in `GameMode::Sfx`: the pitch and volume of every
+/// step are drawn as bars, left button draws them and right button
+/// silences steps.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SfxEditor;
+
+impl System for SfxEditor {
+    fn update(&mut self, game: &mut Game) -> GameResult {
+        if game.mode != GameMode::Sfx {
+            return Ok(());
+        }
+
+        let mouse = game.ui.get_mouse();
+        let (left, right) = (mouse.is_left_down(), mouse.is_right_down());
+        let (x, y) = match mouse.viewport_point() {
+            Some((x, y)) if left || right => (x, y + 1.),
+            _ => return Ok(()),
+        };
+
+        let step = ((x - STEPS_X) / STEP_W).floor();
+        if step < 0. || step >= SFX_STEPS as f32 {
+            return Ok(());
+        }
+        let step = step as usize;
+
+        let edit = game.get_sfx_edit_mut();
+        edit.step = step;
+        let (index, wave) = (edit.index, edit.wave);
+        let sfx = match game.sfx.get_mut(index) {
+            Some(sfx) => sfx,
+            None => return Ok(()),
+        };
+        let note = &mut sfx.steps[step];
+
+        if (PITCH_TOP..PITCH_BOTTOM).contains(&y) {
+            if right {
+                note.volume = 0;
+            } else {
+                let height = (PITCH_BOTTOM - y) / (PITCH_BOTTOM - PITCH_TOP);
+                note.pitch = ((height * (MAX_PITCH + 1) as f32) as u8).min(MAX_PITCH);
+                note.wave = wave;
+                if note.volume == 0 {
+                    note.volume = MAX_VOLUME - 2;
+                }
+            }
+        } else if (VOLUME_TOP..VOLUME_BOTTOM).contains(&y) {
+            let height = (VOLUME_BOTTOM - y) / (VOLUME_BOTTOM - VOLUME_TOP);
+            note.volume = if right {
+                0
+            } else {
+                ((height * (MAX_VOLUME + 1) as f32) as u8).min(MAX_VOLUME)
+            };
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        if game.mode != GameMode::Sfx {
+            return Ok(());
+        }
+        let ui = &game.ui;
+        let edit = game.get_sfx_edit();
+        let sfx = match game.sfx.get(edit.index) {
+            Some(sfx) => sfx,
+            None => return Ok(()),
+        };
+
+        let info = format!("SFX {:02} SPD {}", edit.index, sfx.speed);
+        ui.draw_text(r, &info, 1., 1., Pal::Blue)?;
+
+        let selected_x = STEPS_X + edit.step as f32 * STEP_W;
+        let selected_h = VOLUME_BOTTOM - PITCH_TOP;
+        ui.draw_rect(r, selected_x, PITCH_TOP, STEP_W, selected_h, Pal::DarkBlue)?;
+
+        let pitch_h = PITCH_BOTTOM - PITCH_TOP;
+        let volume_h = VOLUME_BOTTOM - VOLUME_TOP;
+        for (i, step) in sfx.steps.iter().enumerate() {
+            let x = STEPS_X + i as f32 * STEP_W;
+            // leave a pixel between steps
+            let w = STEP_W - 1. / TILE_SIZE as f32;
+
+            if step.volume > 0 {
+                let h = (step.pitch + 1) as f32 / (MAX_PITCH + 1) as f32 * pitch_h;
+                ui.draw_rect(r, x, PITCH_BOTTOM - h, w, h, wave_color(step.wave))?;
+
+                let h = step.volume as f32 / MAX_VOLUME as f32 * volume_h;
+                ui.draw_rect(r, x, VOLUME_BOTTOM - h, w, h, Pal::Blue)?;
+            }
+            ui.draw_text(r, step.effect.symbol(), x, EFFECT_Y, Pal::Gray)?;
+        }
+
+        let step = &sfx.steps[edit.step];
+        let status = format!(
+            "{:02} {} {:?} {} {:?}",
+            edit.step,
+            note_name(step.pitch),
+            step.wave,
+            step.volume,
+            step.effect
+        );
+        ui.draw_text(r, &status, 1., ui.status_row(), wave_color(edit.wave))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use ggez::graphics::{Color, Rect};
+use ggez::nalgebra::Point2;
+
+use crate::render::Renderer;
+use crate::sprite::Tool;
+use crate::*;
+
+/// Column of the color picker, the zoomed tile is left of it
+const PICKER_X: u16 = 17;
+
+/// Edits the pixels of a tile in `GameMode::Sprite`: the tile is drawn
+/// zoomed with one screen tile per pixel, left button paints with the
+/// current tool and right button erases.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SpriteEditor {
+    was_down: bool,
+    /// Whether the current stroke is made with the right button
+    erase: bool,
+    /// Last pixel under the mouse during a stroke
+    last: Option<(u16, u16)>,
+}
+
+impl System for SpriteEditor {
+    fn update(&mut self, game: &mut Game) -> GameResult {
+        if game.mode != GameMode::Sprite {
+            self.was_down = false;
+            return Ok(());
+        }
+
+        let mouse = game.ui.get_mouse();
+        let (left, right) = (mouse.is_left_down(), mouse.is_right_down());
+        let tile = mouse.viewport_tile();
+        let down = left || right;
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+
+        let edit = game.get_sprite_edit();
+        let (tool, width) = (edit.tool, edit.tile_width());
+        let pixel = tile.filter(|&(x, y)| x < width && y < TILE_SIZE);
+
+        if let (Some((x, y)), true) = (tile, pressed) {
+            if x == PICKER_X {
+                if let Some(color) = Pal::from_index(y as usize) {
+                    game.get_sprite_edit_mut().color = color;
+                }
+            }
+        }
+
+        if !down {
+            // line and rect are only painted once the button is released
+            if let Some((x, y)) = self.last.take() {
+                if tool == Tool::Line || tool == Tool::Rect {
+                    game.paint_sprite(x, y, self.erase);
+                }
+            }
+            game.get_sprite_edit_mut().drag = None;
+            return Ok(());
+        }
+
+        let (x, y) = match pixel {
+            Some(pixel) => pixel,
+            None => return Ok(()),
+        };
+        if pressed {
+            self.erase = right;
+            game.push_sprite_undo();
+            game.get_sprite_edit_mut().drag = Some((x, y));
+        }
+        if self.last.is_none() && !pressed {
+            // the stroke started outside of the tile
+            return Ok(());
+        }
+        self.last = Some((x, y));
+
+        match tool {
+            Tool::Pencil => game.paint_sprite(x, y, self.erase),
+            Tool::Fill if pressed => game.paint_sprite(x, y, self.erase),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        if game.mode != GameMode::Sprite {
+            return Ok(());
+        }
+        let ui = &game.ui;
+        let edit = game.get_sprite_edit();
+        let sheet = ui.sheet(edit.tileset);
+        let (ox, oy) = edit.origin();
+        let width = edit.tile_width();
+
+        for py in 0..TILE_SIZE {
+            for px in 0..width {
+                let (x, y) = (px as f32, 1. + py as f32);
+                if let Some(color) = Pal::from_index(sheet.pixel(ox + px, oy + py) as usize) {
+                    ui.draw_rect(r, x, y, 1., 1., color)?;
+                } else if (px + py) % 2 == 0 {
+                    // checkerboard behind transparent pixels
+                    ui.draw_rect(r, x, y, 1., 1., Pal::Black)?;
+                }
+            }
+        }
+
+        for (i, color) in Pal::ALL.iter().enumerate() {
+            let y = 1. + i as f32;
+            ui.draw_rect(r, PICKER_X as f32, y, 1., 1., *color)?;
+            if *color == edit.color {
+                ui.draw_text(r, ">", PICKER_X as f32 - 0.5, y, Pal::White)?;
+            }
+        }
+
+        // the tile at its actual size
+        let unit = TILE_SIZE as f32;
+        r.sprite(
+            sheet,
+            Rect::new(ox as f32, oy as f32, width as f32, unit),
+            Point2::new(18.5 * unit, unit),
+            &ui.sheet_colors(),
+            Color::from_rgba(0xff, 0xff, 0xff, 0xff),
+            false,
+        )?;
+
+        let status = format!(
+            "{:?} {:?} {},{}{} {:?}",
+            edit.tool,
+            edit.tileset,
+            edit.row,
+            edit.column,
+            if edit.narrow { " 8x16" } else { "" },
+            edit.color
+        );
+        ui.draw_text(r, &status, 1., ui.status_row(), Pal::Blue)?;
+
+        Ok(())
+    }
+}
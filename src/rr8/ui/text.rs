@@ -0,0 +1,183 @@
+use ggez::graphics::Rect;
+
+use crate::*;
+
+/// Marks text cut at the end of a line or of a box
+pub const ELLIPSIS: &str = "...";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Columns and rows of font cells a text takes: its longest line and its
+/// number of lines. Cells are half a tile wide and a tile high.
+pub fn measure(text: &str) -> (usize, usize) {
+    if text.is_empty() {
+        return (0, 0);
+    }
+
+    text.split('\n').fold((0, 0), |(columns, rows), line| {
+        (columns.max(line.chars().count()), rows + 1)
+    })
+}
+
+/// Breaks text into lines of at most `columns` cells, between words when
+/// possible. Spaces inside a line are kept, the ones where it breaks are
+/// dropped and words longer than a line are cut.
+pub fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    if columns == 0 {
+        return lines;
+    }
+
+    for paragraph in text.split('\n') {
+        let mut line: Vec<char> = Vec::new();
+        let mut spaces = 0;
+
+        for (i, word) in paragraph.split(' ').enumerate() {
+            if i > 0 {
+                spaces += 1;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            let mut word = word.chars().collect::<Vec<_>>();
+
+            if !line.is_empty() && line.len() + spaces + word.len() > columns {
+                lines.push(line.drain(..).collect());
+            } else {
+                // spaces before the word, a paragraph can start indented
+                let room = columns - line.len();
+                line.resize(line.len() + spaces.min(room), ' ');
+            }
+            spaces = 0;
+
+            while line.len() + word.len() > columns {
+                let rest = word.split_off(columns - line.len());
+                line.append(&mut word);
+                lines.push(line.drain(..).collect());
+                word = rest;
+            }
+            line.append(&mut word);
+        }
+
+        lines.push(line.into_iter().collect());
+    }
+
+    lines
+}
+
+/// Cuts a line to `columns` cells, ending it with `ELLIPSIS` when it
+/// doesn't fit.
+pub fn ellipsize(line: &str, columns: usize) -> String {
+    if line.chars().count() <= columns {
+        return line.to_string();
+    }
+
+    let dots = ELLIPSIS.len();
+    if columns <= dots {
+        return ELLIPSIS[..columns].to_string();
+    }
+
+    let cut = line.chars().take(columns - dots).collect::<String>();
+    format!("{}{}", cut.trim_end(), ELLIPSIS)
+}
+
+/// How text is laid out in a box given in tiles: wrapped or not, aligned,
+/// and clipped so nothing is drawn outside of the box. Text cut at the
+/// right or bottom edge is ellipsized unless turned off.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    rect: Rect,
+    align: Align,
+    wrap: bool,
+    ellipsis: bool,
+}
+
+impl TextLayout {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            align: Align::Left,
+            wrap: true,
+            ellipsis: true,
+        }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn ellipsis(mut self, ellipsis: bool) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Columns and rows of font cells that fit in the box
+    pub fn size(&self) -> (usize, usize) {
+        let unit = TILE_SIZE as f32;
+        let columns = self.rect.w * unit / FONT_WIDTH as f32;
+        let rows = self.rect.h * unit / FONT_HEIGHT as f32;
+
+        (columns.max(0.) as usize, rows.max(0.) as usize)
+    }
+
+    /// The lines of a text that fit in the box, with the position in tiles
+    /// of their top left corner.
+    pub fn lines(&self, text: &str) -> Vec<(String, f32, f32)> {
+        let (columns, rows) = self.size();
+        let cell = (
+            FONT_WIDTH as f32 / TILE_SIZE as f32,
+            FONT_HEIGHT as f32 / TILE_SIZE as f32,
+        );
+
+        let mut lines = if self.wrap {
+            wrap(text, columns)
+        } else {
+            text.split('\n').map(str::to_string).collect()
+        };
+        let cut = lines.len() > rows;
+        lines.truncate(rows);
+
+        if cut && self.ellipsis {
+            if let Some(last) = lines.last_mut() {
+                *last = ellipsize(&format!("{}{}", last.trim_end(), ELLIPSIS), columns);
+            }
+        }
+
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(row, line)| {
+                let line = if self.ellipsis {
+                    ellipsize(&line, columns)
+                } else {
+                    line.chars().take(columns).collect()
+                };
+                let free = columns - line.chars().count();
+                let offset = match self.align {
+                    Align::Left => 0,
+                    Align::Center => free / 2,
+                    Align::Right => free,
+                };
+
+                let x = self.rect.x + offset as f32 * cell.0;
+                let y = self.rect.y + row as f32 * cell.1;
+                (line, x, y)
+            })
+            .collect()
+    }
+}
@@ -1,11 +1,9 @@
-use ggez::graphics;
-use ggez::graphics::{spritebatch::SpriteBatch, DrawParam, Image, Rect};
-use ggez::graphics::{Color, FilterMode};
+use ggez::graphics::{Color, Rect};
 use ggez::nalgebra::Point2;
-use ggez::Context;
 
+use crate::render::{Renderer, Sheet, SheetColors};
+use crate::ui::palette::Ink;
 use crate::*;
-use graphics::Drawable;
 
 impl Into<u16> for TileId {
     fn into(self) -> u16 {
@@ -22,99 +20,131 @@ impl TileLayout {
     pub(crate) fn new(path: &'static str) -> Self {
         Self { path }
     }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+}
+
+/// Where and how a tile is drawn, in the spirit of ggez's `DrawParam`.
+/// `Ui` takes an `Ink` that it resolves to a `Paint` for `TileMap`.
+#[derive(Clone, Copy, Debug)]
+pub struct TileParam<C = Ink> {
+    pub dest: Point2<f32>,
+    pub color: C,
+    pub flip: bool,
+}
+
+impl<C> TileParam<C> {
+    pub fn new(x: f32, y: f32, color: impl Into<C>) -> Self {
+        Self {
+            dest: Point2::new(x, y),
+            color: color.into(),
+            flip: false,
+        }
+    }
+
+    pub fn flip(self, flip: bool) -> Self {
+        Self { flip, ..self }
+    }
+}
+
+/// An ink resolved for drawing a sheet: the colors of its pixels and the
+/// color tinting them, see `Renderer::sprite`.
+#[derive(Clone, Copy, Debug)]
+pub struct Paint {
+    pub colors: SheetColors,
+    pub tint: Color,
 }
 
 #[derive(Debug)]
 pub struct TileMap {
-    image: graphics::Image,
+    sheet: Sheet,
     layout: TileLayout,
-    filter_mode: FilterMode,
 }
 
 impl TileMap {
-    pub fn new(ctx: &mut Context, layout: TileLayout, filter_mode: FilterMode) -> GameResult<Self> {
-        let image = Image::new(ctx, layout.path)?;
-        Ok(Self {
-            image,
-            layout,
-            filter_mode,
-        })
+    pub fn new(sheet: Sheet, layout: TileLayout) -> Self {
+        Self { sheet, layout }
     }
 
-    pub fn batch(&self) -> SpriteBatch {
-        let mut batch = SpriteBatch::new(self.image.clone());
-        batch.set_filter(self.filter_mode);
+    pub fn set_sheet(&mut self, sheet: Sheet) {
+        self.sheet = sheet;
+    }
+
+    pub fn sheet(&self) -> &Sheet {
+        &self.sheet
+    }
 
-        batch
+    pub fn sheet_mut(&mut self) -> &mut Sheet {
+        &mut self.sheet
     }
 
+    pub fn layout(&self) -> &TileLayout {
+        &self.layout
+    }
+
+    /// Draws a single tile with its top left corner at `p.dest` (in pixels).
     pub fn tile(
         &self,
+        r: &mut dyn Renderer,
         t: impl Into<u16> + Copy + std::fmt::Debug,
         column: u16,
-        c: impl Into<Color> + Copy,
+        p: TileParam<Paint>,
         tile_width: u16,
-        flip: bool,
-    ) -> GameResult<SpriteBatch> {
-        let mut batch = self.batch();
-
+    ) -> GameResult {
         let rect = self.rect_with_tile_width(t.into(), column, tile_width);
 
-        let mut param = DrawParam::default().color(c.into()).src(rect);
-        if flip {
-            param = param
-                .rotation(std::f32::consts::PI)
-                .offset(Point2::new(0.9, 0.9));
-        }
-        batch.add(param);
-
-        Ok(batch)
+        r.sprite(
+            &self.sheet,
+            rect,
+            p.dest,
+            &p.color.colors,
+            p.color.tint,
+            p.flip,
+        )
     }
 
+    /// Repeats a tile over `w`x`h` cells starting at `p.dest` (in pixels).
     pub fn fill(
         &self,
+        r: &mut dyn Renderer,
         row: impl Into<u16>,
         column: u16,
-        w: u8,
-        h: u8,
-        c: impl Into<Color> + Copy,
+        (w, h): (u8, u8),
+        p: TileParam<Paint>,
         tile_width: u16,
-    ) -> GameResult<SpriteBatch> {
-        let mut batch = self.batch();
-
+    ) -> GameResult {
         let rect = self.rect_with_tile_width(row.into(), column, tile_width);
         for x in 0..w {
             for y in 0..h {
-                batch.add(
-                    DrawParam::default()
-                        .color(c.into())
-                        .src(rect)
-                        .dest(Point2::new(
-                            x as f32 * tile_width as f32,
-                            y as f32 * TILE_SIZE as f32,
-                        )),
-                );
+                r.sprite(
+                    &self.sheet,
+                    rect,
+                    Point2::new(
+                        p.dest.x + x as f32 * tile_width as f32,
+                        p.dest.y + y as f32 * TILE_SIZE as f32,
+                    ),
+                    &p.color.colors,
+                    p.color.tint,
+                    p.flip,
+                )?;
             }
         }
 
-        Ok(batch)
+        Ok(())
     }
 
     pub fn rect(&self, row: u16, column: u16) -> Rect {
         self.rect_with_tile_width(row, column, TILE_SIZE)
     }
 
+    /// Source rect of a tile in pixels of the sheet
     pub fn rect_with_tile_width(&self, row: u16, column: u16, tile_width: u16) -> Rect {
-        let x = column * tile_width;
-        let y = row * TILE_SIZE;
-
-        self.rect_raw(x, y, tile_width, TILE_SIZE)
-    }
-
-    fn rect_raw<N: Into<f32>>(&self, x: N, y: N, w: N, h: N) -> Rect {
-        let iw = self.image.width() as f32;
-        let ih = self.image.height() as f32;
+        // in f32, far rows and columns overflow u16
+        let x = column as f32 * tile_width as f32;
+        let y = row as f32 * TILE_SIZE as f32;
 
-        Rect::new(x.into() / iw, y.into() / ih, w.into() / iw, h.into() / ih)
+        Rect::new(x, y, tile_width as f32, TILE_SIZE as f32)
     }
 }
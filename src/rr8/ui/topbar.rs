@@ -1,36 +1,36 @@
+use crate::render::Renderer;
+use crate::ui::tile::TileParam;
 use crate::*;
 
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct TopBar;
 
 impl System for TopBar {
-    fn update(&mut self, ctx: &mut Context, game: &mut Game) -> GameResult {
-        todo!()
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
     }
 
-    fn draw(&self, ctx: &mut Context, game: &Game) -> GameResult {
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
         let ui = &game.ui;
 
         let default_color = Pal::DarkBlue;
         let ((bg_row, bg_column), (fg_row, fg_column), bg_color, fg_color) = match game.mode {
             GameMode::Normal => ((1, 16), (5, 16), default_color.darker(), default_color),
             GameMode::Prompt => ((1, 16), (1, 25), default_color.darker(), default_color),
+            GameMode::Map => ((1, 16), (3, 16), default_color.darker(), default_color),
+            GameMode::Sprite => ((1, 16), (4, 16), default_color.darker(), default_color),
+            GameMode::Code => ((1, 16), (2, 16), default_color.darker(), default_color),
+            GameMode::Sfx => ((1, 16), (6, 16), default_color.darker(), default_color),
+            GameMode::Music => ((1, 16), (7, 16), default_color.darker(), default_color),
         };
-        ui.draw(
-            ctx,
-            &ui.tile_alt(bg_row, bg_column, fg_color, false)?,
-            1.,
-            0.,
-        )?;
-        ui.draw(
-            ctx,
-            &ui.tile_alt(fg_row, fg_column, bg_color, false)?,
-            1.,
-            0.,
-        )?;
-        ui.draw_text(ctx, &p(&game.mode).to_uppercase(), 2.5, 0., default_color)?;
+        ui.draw_tile_alt(r, bg_row, bg_column, TileParam::new(1., 0., fg_color))?;
+        ui.draw_tile_alt(r, fg_row, fg_column, TileParam::new(1., 0., bg_color))?;
+        ui.draw_text(r, &p(&game.mode).to_uppercase(), 2.5, 0., default_color)?;
 
-        ui.draw_text(ctx, &format!("x{}", ui.scale), 18., 0., default_color)?;
+        // scale in the top right corner
+        let (columns, _) = ui.screen_size();
+        let x = columns as f32 - 2.;
+        ui.draw_text(r, &format!("x{}", ui.scale), x, 0., default_color)?;
 
         Ok(())
     }
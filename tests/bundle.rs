@@ -0,0 +1,29 @@
+use ggez::GameResult;
+
+use rr8::bundle;
+use rr8::cart::Cart;
+
+#[test]
+fn embed_roundtrip() -> GameResult {
+    let player = b"\x7fELF not really a player".to_vec();
+    let cart = Cart {
+        code: "fn draw() { text(\"hi\", 1, 1, 9); }\n".to_string(),
+        map: vec![1, 2, 3],
+        ..Cart::default()
+    };
+
+    let data = bundle::embed(&player, &cart);
+    assert!(data.starts_with(&player));
+    assert_eq!(bundle::extract(&data)?, Some(cart));
+
+    // plain executables carry no cart
+    assert_eq!(bundle::extract(&player)?, None);
+    assert_eq!(bundle::extract(b"")?, None);
+
+    // a trailer with a length past the start of the file is an error
+    let mut broken = data[data.len() - 16..].to_vec();
+    broken.insert(0, 0);
+    assert!(bundle::extract(&broken).is_err());
+
+    Ok(())
+}
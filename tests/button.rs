@@ -0,0 +1,72 @@
+use rr8::button::{ButtonState, REPEAT_DELAY, REPEAT_INTERVAL};
+use rr8::Btn;
+
+#[test]
+fn press_and_release_between_ticks() {
+    let mut buttons = ButtonState::default();
+    buttons.press(1, Btn::A);
+    // nothing changes until the next tick
+    assert!(!buttons.btn(1, Btn::A));
+
+    buttons.tick();
+    assert!(buttons.btn(1, Btn::A));
+    assert!(buttons.btnp(1, Btn::A));
+    assert!(!buttons.btn(0, Btn::A));
+    assert_eq!(buttons.held(1), vec![Btn::A]);
+
+    buttons.tick();
+    assert!(buttons.btn(1, Btn::A));
+    assert!(!buttons.btnp(1, Btn::A));
+
+    buttons.release(1, Btn::A);
+    buttons.tick();
+    assert!(!buttons.btn(1, Btn::A));
+    assert!(buttons.released(1, Btn::A));
+
+    buttons.tick();
+    assert!(!buttons.released(1, Btn::A));
+}
+
+#[test]
+fn quick_tap_lasts_a_tick() {
+    let mut buttons = ButtonState::default();
+    buttons.press(0, Btn::Start);
+    buttons.release(0, Btn::Start);
+
+    buttons.tick();
+    assert!(buttons.btnp(0, Btn::Start));
+    buttons.tick();
+    assert!(!buttons.btn(0, Btn::Start));
+    assert!(buttons.released(0, Btn::Start));
+}
+
+#[test]
+fn btnp_repeats() {
+    let mut buttons = ButtonState::default();
+    buttons.press(0, Btn::Left);
+
+    let mut pressed = Vec::new();
+    for tick in 1..=REPEAT_DELAY + 3 * REPEAT_INTERVAL {
+        buttons.tick();
+        if buttons.btnp(0, Btn::Left) {
+            pressed.push(tick);
+        }
+    }
+    let first = REPEAT_DELAY + 1;
+    let repeats = vec![
+        1,
+        first,
+        first + REPEAT_INTERVAL,
+        first + 2 * REPEAT_INTERVAL,
+    ];
+    assert_eq!(pressed, repeats);
+}
+
+#[test]
+fn unknown_players_are_ignored() {
+    let mut buttons = ButtonState::default();
+    buttons.press(99, Btn::B);
+    buttons.tick();
+    assert!(!buttons.btn(99, Btn::B));
+    assert!(!buttons.btnp(99, Btn::B));
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+
+use rr8::capture::{self, GifRecorder, GIF_FRAME_TICKS, MAX_GIF_SECONDS};
+use rr8::render::soft::Framebuffer;
+use rr8::render::Renderer;
+use rr8::ui::{
+    palette::{Pal, Palette},
+    Ui,
+};
+use rr8::Game;
+
+fn game() -> GameResult<Game> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let ui = Ui::headless(resources, Rect::new(0., 0., 320., 320.))?;
+    Game::new(ui)
+}
+
+#[test]
+fn nearest_palette_color() {
+    let palette = Palette::default();
+    for (i, pal) in Pal::ALL.iter().enumerate() {
+        let color: Color = (*pal).into();
+        let (r, g, b) = color.to_rgb();
+        assert_eq!(capture::pal_index(&palette, [r, g, b, 255]), i as u8);
+    }
+    assert_eq!(
+        capture::pal_index(&palette, [0xfe, 0x01, 0x50, 255]),
+        Pal::Red as u8
+    );
+    assert_eq!(capture::gif_palette(&palette).len(), Pal::ALL.len() * 3);
+}
+
+#[test]
+fn gif_frames() -> GameResult {
+    let mut gif = GifRecorder::new(0.1);
+    let mut fb = Framebuffer::new(4, 2);
+    while !gif.is_done() {
+        if gif.wants_frame() {
+            fb.rect(Rect::new(0., 0., 1., 1.), Pal::Blue.into())?;
+            gif.push(&fb);
+        }
+        gif.tick();
+    }
+    // 6 ticks at 60Hz
+    assert_eq!(gif.frame_count(), (6 / GIF_FRAME_TICKS) as usize);
+
+    let data = gif.encode()?;
+    let mut reader = gif::Decoder::new(&data[..]).read_info().unwrap();
+    let frame = reader.read_next_frame().unwrap().unwrap();
+    assert_eq!((frame.width, frame.height), (4, 2));
+    assert_eq!(frame.buffer[0], Pal::Blue as u8);
+    assert_eq!(frame.buffer[1], Pal::Off as u8);
+
+    Ok(())
+}
+
+#[test]
+fn gif_length_is_capped() {
+    let mut gif = GifRecorder::new(f32::INFINITY);
+    let mut ticks = 0;
+    while !gif.is_done() {
+        gif.tick();
+        ticks += 1;
+    }
+    assert_eq!(ticks, (MAX_GIF_SECONDS * 60.) as u32);
+}
+
+#[test]
+fn game_captures() -> GameResult {
+    let mut game = game()?;
+    let fb = game.capture()?;
+    assert_eq!((fb.width(), fb.height()), (320, 320));
+
+    let path = std::env::temp_dir().join("rr8-capture-test.gif");
+    let _ = std::fs::remove_file(&path);
+    game.start_gif(0.1, &path);
+    while game.is_capturing_gif() {
+        game.tick()?;
+    }
+    assert_eq!(game.get_dt(), 6);
+    assert!(std::fs::read(&path)?.starts_with(b"GIF89a"));
+    assert!(game.stop_gif().is_err());
+
+    Ok(())
+}
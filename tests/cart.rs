@@ -0,0 +1,75 @@
+use ggez::GameResult;
+
+use rr8::cart::{Cart, CART_VERSION};
+use rr8::ui::palette::Pal;
+
+fn cart(code: &str) -> Cart {
+    Cart {
+        code: code.to_string(),
+        tiles: Some(vec![0x89, 0x50, 0x4e, 0x47]),
+        map: (0..=255).collect(),
+        palette: vec![(Pal::Red, (0x12, 0x34, 0x56))],
+        ..Cart::default()
+    }
+}
+
+#[test]
+fn cart_roundtrip() -> GameResult {
+    for code in [
+        "",
+        "fn draw() { text(\"hi\", 1, 1, 9); }",
+        // a newline at the end is kept
+        "let x = 1;\n",
+        "let x = 1;\n\n",
+        // lines that look like sections are code
+        "__map__\nlet s = \"\n__tiles__\n00\n\";",
+        "__code__ 3\n\r\nrr8 cartridge",
+    ]
+    .iter()
+    {
+        let cart = cart(code);
+        assert_eq!(Cart::parse(&cart.serialize())?, cart);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cart_sections() -> GameResult {
+    let text = format!(
+        "rr8 cartridge\nversion {}\n__code__ 5\nlet x\n__map__\n0aff\n01\n",
+        CART_VERSION
+    );
+    let cart = Cart::parse(&text)?;
+    assert_eq!(cart.code, "let x");
+    assert_eq!(cart.map, vec![0x0a, 0xff, 0x01]);
+    assert_eq!(cart.tiles, None);
+
+    Ok(())
+}
+
+#[test]
+fn bad_carts_are_errors() {
+    let bad = [
+        "",
+        "not a cart\nversion 1\n",
+        "rr8 cartridge\n",
+        "rr8 cartridge\nversion 99\n",
+        "rr8 cartridge\nversion 1\nstray line\n",
+        "rr8 cartridge\nversion 1\n__nope__\n",
+        // code needs its length, and that many bytes
+        "rr8 cartridge\nversion 1\n__code__\nlet x = 1;\n",
+        "rr8 cartridge\nversion 1\n__code__ 50\nlet x = 1;\n",
+        "rr8 cartridge\nversion 1\n__map__ 2\n00\n",
+        // hex sections only hold pairs of hex digits
+        "rr8 cartridge\nversion 1\n__map__\n0\n",
+        "rr8 cartridge\nversion 1\n__map__\nzz\n",
+        "rr8 cartridge\nversion 1\n__map__\n0é\n",
+        "rr8 cartridge\nversion 1\n__map__\né0\n",
+        "rr8 cartridge\nversion 1\n__map__\n0é0\n",
+        "rr8 cartridge\nversion 1\n__pal__\n99 ffffff\n",
+    ];
+    for text in bad.iter() {
+        assert!(Cart::parse(text).is_err(), "{:?}", text);
+    }
+}
@@ -0,0 +1,62 @@
+use rr8::code::{highlight, CodeEdit, Pos, Token};
+
+#[test]
+fn edit_across_lines() {
+    let mut edit = CodeEdit::new("fn init() {\n}");
+    edit.move_to(Pos::new(0, 11), false);
+    edit.newline();
+    edit.insert("    this.x = 1;");
+    edit.newline();
+    assert_eq!(edit.text(), "fn init() {\n    this.x = 1;\n    \n}");
+    assert_eq!(edit.cursor(), Pos::new(2, 4));
+
+    // backspace at the start of a line joins it with the previous one
+    edit.home(false);
+    edit.backspace();
+    assert_eq!(edit.text(), "fn init() {\n    this.x = 1;    \n}");
+}
+
+#[test]
+fn cut_and_paste_selection() {
+    let mut edit = CodeEdit::new("one\ntwo\nthree");
+    edit.move_to(Pos::new(0, 1), false);
+    edit.move_to(Pos::new(1, 2), true);
+    assert_eq!(edit.selected_text().as_deref(), Some("ne\ntw"));
+
+    edit.cut();
+    assert_eq!(edit.text(), "oo\nthree");
+    edit.end(false);
+    edit.paste();
+    assert_eq!(edit.text(), "oone\ntw\nthree");
+}
+
+#[test]
+fn search_wraps_around() {
+    let mut edit = CodeEdit::new("let x = 1;\nx += 1;\nlet y = x;");
+    edit.move_to(Pos::new(1, 0), false);
+    edit.start_search();
+    for c in "let".chars() {
+        edit.input(c);
+    }
+
+    assert!(edit.find_next());
+    assert_eq!(edit.selection(), Some((Pos::new(2, 0), Pos::new(2, 3))));
+    assert!(edit.find_next());
+    assert_eq!(edit.selection(), Some((Pos::new(0, 0), Pos::new(0, 3))));
+}
+
+#[test]
+fn highlight_tokens() {
+    let tokens = highlight("let s = \"a b\"; text(s, 1) // hi");
+    let kinds: Vec<Token> = tokens.iter().map(|(t, _)| *t).collect();
+
+    assert_eq!(tokens[0], (Token::Keyword, "let"));
+    assert!(tokens.contains(&(Token::String, "\"a b\"")));
+    assert!(tokens.contains(&(Token::Function, "text")));
+    assert!(tokens.contains(&(Token::Number, "1")));
+    assert_eq!(tokens.last(), Some(&(Token::Comment, "// hi")));
+    assert!(kinds.contains(&Token::Symbol));
+    // tokens cover the whole line
+    let line: String = tokens.iter().map(|(_, s)| *s).collect();
+    assert_eq!(line, "let s = \"a b\"; text(s, 1) // hi");
+}
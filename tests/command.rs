@@ -0,0 +1,58 @@
+use rr8::command::{parse_args, Commands};
+
+fn args(line: &str) -> Vec<String> {
+    parse_args(line).unwrap()
+}
+
+#[test]
+fn args_split_on_whitespace() {
+    assert_eq!(args("load  game.rr8 "), vec!["load", "game.rr8"]);
+    assert_eq!(args("\tscale\tup"), vec!["scale", "up"]);
+    assert!(args("   ").is_empty());
+}
+
+#[test]
+fn quotes_group_words() {
+    assert_eq!(args("save \"my game.rr8\""), vec!["save", "my game.rr8"]);
+    assert_eq!(args("cd 'a b'/c"), vec!["cd", "a b/c"]);
+    // the other quote is a plain char inside a quoted word
+    assert_eq!(
+        args("say \"it's\" 'a \"b\"'"),
+        vec!["say", "it's", "a \"b\""]
+    );
+    // empty quotes are an empty argument
+    assert_eq!(args("ls \"\""), vec!["ls", ""]);
+}
+
+#[test]
+fn backslash_escapes() {
+    assert_eq!(args("cd my\\ game"), vec!["cd", "my game"]);
+    assert_eq!(args("say \\\"hi\\\""), vec!["say", "\"hi\""]);
+    assert_eq!(args("say \"a\\\"b\""), vec!["say", "a\"b"]);
+    assert_eq!(args("say a\\\\b"), vec!["say", "a\\b"]);
+}
+
+#[test]
+fn unterminated_args_are_errors() {
+    assert!(parse_args("save \"my game").is_err());
+    assert!(parse_args("save 'my game").is_err());
+    assert!(parse_args("save game\\").is_err());
+}
+
+#[test]
+fn registry_lookup() {
+    let mut commands = Commands::with_builtins();
+    assert!(commands.get("load").is_some());
+    assert!(commands.get("frobnicate").is_none());
+
+    let names: Vec<_> = commands.names().collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+
+    // registering again replaces the command
+    commands.register("load", "load <x>", "other", |_, _, _| Ok(String::new()));
+    assert_eq!(commands.get("load").unwrap().help, "other");
+    commands.register("frobnicate", "frobnicate", "", |_, _, _| Ok(String::new()));
+    assert!(commands.get("frobnicate").is_some());
+}
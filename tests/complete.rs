@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+use rr8::command::Commands;
+use rr8::complete::{complete, Completed, Completion};
+
+fn commands() -> Commands {
+    let mut commands = Commands::with_builtins();
+    for name in ["music", "museum"].iter() {
+        commands.register(name, name, "", |_, _, _| Ok(String::new()));
+    }
+    commands.register("say", "say", "", |_, _, _| Ok(String::new()));
+    commands.set_completion(
+        "say",
+        Completion::Words(vec!["hello".to_string(), "help".to_string()]),
+    );
+
+    commands
+}
+
+fn at_end(cwd: &Path, line: &str) -> Completed {
+    complete(&commands(), cwd, line, line.len())
+}
+
+/// The line with the completion applied
+fn completed(cwd: &Path, line: &str) -> String {
+    let completed = at_end(cwd, line);
+    let mut line = line.to_string();
+    if let Some(replacement) = completed.replacement {
+        line.replace_range(completed.start..completed.end, &replacement);
+    }
+
+    line
+}
+
+fn games_dir(test: &str) -> PathBuf {
+    let name = format!("rr8-{}-{}", test, std::process::id());
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("games")).unwrap();
+    for file in [
+        "games/space invaders.rr8",
+        "games/snake.rr8",
+        "gamma.txt",
+        ".hidden",
+    ]
+    .iter()
+    {
+        std::fs::write(dir.join(file), "").unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn unique_prefix() {
+    let cwd = Path::new(".");
+    assert_eq!(completed(cwd, "loads"), "loadstate ");
+    assert_eq!(completed(cwd, "say hel"), "say hel");
+    assert_eq!(completed(cwd, "say hell"), "say hello ");
+    // commands without completion leave arguments alone
+    assert_eq!(completed(cwd, "run x"), "run x");
+
+    // only the word under the cursor is replaced
+    let completed = complete(&commands(), cwd, "loads game", 5);
+    assert_eq!((completed.start, completed.end), (0, 5));
+    assert_eq!(completed.replacement, Some("loadstate ".to_string()));
+}
+
+#[test]
+fn ambiguous_prefix() {
+    let cwd = Path::new(".");
+    // the common prefix is inserted first
+    assert_eq!(completed(cwd, "m"), "mus");
+
+    // then the candidates are listed
+    let completed = at_end(cwd, "mus");
+    assert_eq!(completed.replacement, None);
+    assert_eq!(completed.candidates, vec!["museum", "music"]);
+
+    assert!(at_end(cwd, "nope").candidates.is_empty());
+}
+
+#[test]
+fn paths_with_directories() {
+    let dir = games_dir("paths");
+
+    assert_eq!(completed(&dir, "load g"), "load gam");
+    assert_eq!(
+        at_end(&dir, "load gam").candidates,
+        vec!["games/", "gamma.txt"]
+    );
+    // directories stay open for the next part
+    assert_eq!(completed(&dir, "load game"), "load games/");
+    assert_eq!(completed(&dir, "load games/sn"), "load games/snake.rr8 ");
+    assert_eq!(
+        at_end(&dir, "load games/s").candidates,
+        vec!["snake.rr8", "space invaders.rr8"]
+    );
+    // hidden files only when asked for
+    assert!(at_end(&dir, "load ")
+        .candidates
+        .iter()
+        .all(|c| !c.starts_with('.')));
+    assert_eq!(completed(&dir, "load .h"), "load .hidden ");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn paths_with_quotes_and_spaces() {
+    let dir = games_dir("quoted-paths");
+
+    // spaces are escaped, or kept inside the quote the word started with
+    assert_eq!(
+        completed(&dir, "load games/sp"),
+        "load games/space\\ invaders.rr8 "
+    );
+    assert_eq!(
+        completed(&dir, "load \"games/sp"),
+        "load \"games/space invaders.rr8\" "
+    );
+    assert_eq!(
+        completed(&dir, "load 'games/space i"),
+        "load 'games/space invaders.rr8' "
+    );
+    assert_eq!(
+        completed(&dir, "load games/space\\ i"),
+        "load games/space\\ invaders.rr8 "
+    );
+    assert_eq!(completed(&dir, "load \"gam"), "load \"gam");
+    assert_eq!(completed(&dir, "load \"game"), "load \"games/");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
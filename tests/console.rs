@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use ggez::graphics::Rect;
+use ggez::{GameError, GameResult};
+
+use rr8::console::{Console, CONSOLE_COLUMNS, CONSOLE_ROWS};
+use rr8::ui::{palette::Pal, Ui};
+use rr8::Game;
+
+fn texts(console: &Console) -> Vec<&str> {
+    console.visible().iter().map(|(l, _)| l.as_str()).collect()
+}
+
+#[test]
+fn long_lines_wrap() {
+    let mut console = Console::default();
+    let long = "x".repeat(CONSOLE_COLUMNS + 2);
+    console.push(&format!("{}\n\nend", long), Pal::Red);
+
+    let visible = console.visible();
+    assert_eq!(visible.len(), 4);
+    assert_eq!(visible[0].0.len(), CONSOLE_COLUMNS);
+    assert_eq!(visible[1], ("xx".to_string(), Pal::Red));
+    assert_eq!(visible[2].0, "");
+    assert_eq!(visible[3].0, "end");
+}
+
+#[test]
+fn scrollback() {
+    let mut console = Console::default();
+    for i in 0..CONSOLE_ROWS + 5 {
+        console.push(&i.to_string(), Pal::White);
+    }
+
+    // the box shows the last lines
+    let visible = texts(&console);
+    assert_eq!(visible.len(), CONSOLE_ROWS);
+    assert_eq!(visible[0], "5");
+    assert_eq!(*visible.last().unwrap(), (CONSOLE_ROWS + 4).to_string());
+
+    // scrolling stops at the first line
+    console.scroll_up(3);
+    assert_eq!(console.get_scroll(), 3);
+    assert_eq!(texts(&console)[0], "2");
+    console.scroll_up(100);
+    assert_eq!(console.get_scroll(), 5);
+    assert_eq!(texts(&console)[0], "0");
+    console.scroll_down(2);
+    assert_eq!(console.get_scroll(), 3);
+
+    // new output goes back to the bottom
+    console.push("new", Pal::White);
+    assert_eq!(console.get_scroll(), 0);
+    assert_eq!(*texts(&console).last().unwrap(), "new");
+
+    console.clear();
+    assert!(console.visible().is_empty());
+}
+
+#[test]
+fn history_navigation() {
+    let mut console = Console::default();
+    assert_eq!(console.history_prev("typed"), None);
+
+    console.add_history("load a.rr8");
+    console.add_history("   ");
+    console.add_history("run");
+    // repeated lines are kept once
+    console.add_history("run");
+
+    assert_eq!(console.history_prev("typed"), Some("run"));
+    assert_eq!(console.history_prev("run"), Some("load a.rr8"));
+    assert_eq!(console.history_prev("load a.rr8"), Some("load a.rr8"));
+    assert_eq!(console.history_next(), Some("run"));
+    // coming back to the bottom restores what was being typed
+    assert_eq!(console.history_next(), Some("typed"));
+    assert_eq!(console.history_next(), None);
+}
+
+#[test]
+fn history_persists() -> GameResult {
+    let dir = std::env::temp_dir().join(format!("rr8-history-{}", std::process::id()));
+    let path = dir.join("config").join("history");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // no file yet is an empty history
+    let mut console = Console::default();
+    console.load_history(&path)?;
+    assert_eq!(console.history_prev(""), None);
+
+    console.add_history("ls");
+    console.add_history("cd games");
+    console.save_history(&path)?;
+
+    let mut loaded = Console::default();
+    loaded.load_history(&path)?;
+    assert_eq!(loaded.history_prev(""), Some("cd games"));
+    assert_eq!(loaded.history_prev(""), Some("ls"));
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[test]
+fn reports_show_in_the_console() -> GameResult {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let mut game = Game::new(Ui::headless(resources, Rect::new(0., 0., 320., 320.))?)?;
+
+    game.report(Ok(String::new()));
+    assert!(game.get_console().visible().is_empty());
+    assert_eq!(game.get_message(), None);
+
+    game.report(Ok("saved".to_string()));
+    assert_eq!(texts(game.get_console()), vec!["saved"]);
+    assert_eq!(game.get_message(), Some(("saved", false)));
+
+    game.report(Err(GameError::WindowError("no window".to_string())));
+    let (line, color) = game.get_console().visible().last().unwrap().clone();
+    assert_eq!(color, Pal::Red);
+    assert_eq!(game.get_message(), Some((line.as_str(), true)));
+
+    Ok(())
+}
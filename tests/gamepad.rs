@@ -0,0 +1,66 @@
+use ggez::event::{Axis, Button};
+
+use rr8::gamepad::{Gamepads, PadChange, PadEvent};
+use rr8::keymap::PLAYERS;
+use rr8::Btn;
+
+fn change(player: usize, btn: Btn, pressed: bool) -> PadChange {
+    PadChange {
+        player,
+        btn,
+        pressed,
+    }
+}
+
+#[test]
+fn buttons() {
+    let mut pads = Gamepads::default();
+    assert_eq!(
+        pads.handle(7, PadEvent::ButtonDown(Button::South)),
+        vec![change(0, Btn::A, true)]
+    );
+    assert_eq!(
+        pads.handle(7, PadEvent::ButtonUp(Button::DPadLeft)),
+        vec![change(0, Btn::Left, false)]
+    );
+    assert!(pads
+        .handle(7, PadEvent::ButtonDown(Button::Mode))
+        .is_empty());
+}
+
+#[test]
+fn players_by_controller() {
+    let mut pads = Gamepads::default();
+    for id in 0..PLAYERS as u32 + 1 {
+        pads.handle(id * 10, PadEvent::ButtonDown(Button::Start));
+    }
+    assert_eq!(pads.player(0), Some(0));
+    assert_eq!(pads.player(30), Some(3));
+    assert_eq!(pads.player(40), None);
+
+    let changes = pads.handle(20, PadEvent::ButtonDown(Button::North));
+    assert_eq!(changes, vec![change(2, Btn::Y, true)]);
+    assert!(pads
+        .handle(40, PadEvent::ButtonDown(Button::North))
+        .is_empty());
+}
+
+#[test]
+fn stick_deadzone() {
+    let mut pads = Gamepads::default();
+    let mut axis = |value| pads.handle(1, PadEvent::Axis(Axis::LeftStickX, value));
+
+    assert!(axis(0.1).is_empty());
+    assert_eq!(axis(0.8), vec![change(0, Btn::Right, true)]);
+    assert!(axis(0.9).is_empty());
+    assert_eq!(
+        axis(-1.),
+        vec![change(0, Btn::Right, false), change(0, Btn::Left, true)]
+    );
+    assert_eq!(axis(-0.2), vec![change(0, Btn::Left, false)]);
+
+    // up is positive
+    let changes = pads.handle(1, PadEvent::Axis(Axis::LeftStickY, 0.5));
+    assert_eq!(changes, vec![change(0, Btn::Up, true)]);
+    assert!(pads.handle(1, PadEvent::Axis(Axis::RightZ, 1.)).is_empty());
+}
@@ -0,0 +1,70 @@
+use ggez::event::KeyCode;
+use ggez::GameResult;
+
+use rr8::keymap::{self, Keymap};
+use rr8::Btn;
+
+#[test]
+fn default_bindings() {
+    let keymap = Keymap::default();
+    let z: Vec<_> = keymap.buttons(KeyCode::Z).collect();
+    assert_eq!(z, vec![(0, Btn::A)]);
+    let i: Vec<_> = keymap.buttons(KeyCode::I).collect();
+    assert_eq!(i, vec![(1, Btn::Up)]);
+
+    // every button of both players has a key
+    for player in 0..2 {
+        for btn in Btn::ALL.iter() {
+            let mut bound = keymap.bindings().iter();
+            assert!(bound.any(|b| b.player == player && b.btn == *btn));
+        }
+    }
+}
+
+#[test]
+fn several_bindings_per_key_and_button() {
+    let mut keymap = Keymap::default();
+    keymap.bind(KeyCode::Space, 0, Btn::A);
+    keymap.bind(KeyCode::Space, 1, Btn::A);
+    keymap.bind(KeyCode::Space, 1, Btn::A);
+
+    let space: Vec<_> = keymap.buttons(KeyCode::Space).collect();
+    assert_eq!(space, vec![(0, Btn::A), (1, Btn::A)]);
+    let a = keymap
+        .bindings()
+        .iter()
+        .filter(|b| b.player == 0 && b.btn == Btn::A);
+    assert_eq!(a.count(), 2);
+
+    assert_eq!(keymap.unbind(KeyCode::Space), 2);
+    assert_eq!(keymap.buttons(KeyCode::Space).count(), 0);
+}
+
+#[test]
+fn text_roundtrip() -> GameResult {
+    let mut keymap = Keymap::default();
+    keymap.bind(KeyCode::Numpad8, 3, Btn::Up);
+    assert_eq!(Keymap::parse(&keymap.serialize())?, keymap);
+
+    let parsed = Keymap::parse("# comment\n\n2 start Return\n")?;
+    let bindings: Vec<_> = parsed.buttons(KeyCode::Return).collect();
+    assert_eq!(bindings, vec![(1, Btn::Start)]);
+
+    assert!(Keymap::parse("1 a").is_err());
+    assert!(Keymap::parse("0 a z").is_err());
+    assert!(Keymap::parse("1 jump z").is_err());
+    assert!(Keymap::parse("1 a f13").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn names() {
+    assert_eq!(keymap::key_from_name("LEFT"), Some(KeyCode::Left));
+    assert_eq!(keymap::key_name(KeyCode::Comma), Some("comma"));
+    assert_eq!(keymap::parse_player("4"), Some(3));
+    assert_eq!(keymap::parse_player("5"), None);
+    for btn in Btn::ALL.iter() {
+        assert_eq!(Btn::from_name(btn.name()), Some(*btn));
+    }
+}
@@ -0,0 +1,101 @@
+use ggez::GameResult;
+
+use rr8::map::{Cell, Map, Tileset};
+use rr8::ui::palette::Pal;
+
+#[test]
+fn map_bytes_roundtrip() -> GameResult {
+    let mut map = Map::new(4, 3, 2);
+    let cell = Cell {
+        tileset: Tileset::Main,
+        row: 12,
+        column: 300,
+        color: Pal::Orange,
+        flip: true,
+    };
+    map.set(0, 1, 2, Some(cell));
+    map.set(1, 3, 0, Some(Cell::default()));
+    // out of bounds is ignored
+    map.set(1, 4, 0, Some(cell));
+    map.layer_mut(1).unwrap().visible = false;
+
+    let loaded = Map::from_bytes(&map.to_bytes())?;
+    assert_eq!(loaded.get(0, 1, 2), Some(cell));
+    assert_eq!(loaded.get(1, 3, 0), Some(Cell::default()));
+    assert_eq!(loaded.get(0, 0, 0), None);
+    assert_eq!((loaded.width(), loaded.height()), (4, 3));
+    assert!(loaded.layers()[0].visible);
+    assert!(!loaded.layers()[1].visible);
+
+    Ok(())
+}
+
+#[test]
+fn map_bytes_layout() -> GameResult {
+    // 1x2 map with one hidden layer, a flipped main tile then an empty cell
+    let bytes = [
+        1,
+        1,
+        0,
+        2,
+        0,
+        1,
+        0,
+        0b101,
+        12,
+        1,
+        3,
+        0,
+        Pal::Orange as u8,
+        0,
+    ];
+
+    let loaded = Map::from_bytes(&bytes)?;
+    assert!(!loaded.layers()[0].visible);
+    assert_eq!(
+        loaded.get(0, 0, 0),
+        Some(Cell {
+            tileset: Tileset::Main,
+            row: 268,
+            column: 3,
+            color: Pal::Orange,
+            flip: true,
+        })
+    );
+    assert_eq!(loaded.get(0, 0, 1), None);
+    assert_eq!(loaded.to_bytes(), bytes);
+
+    Ok(())
+}
+
+#[test]
+fn map_truncated_bytes() {
+    let bytes = Map::default().to_bytes();
+    assert!(Map::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn map_bad_headers() {
+    // unknown format
+    assert!(Map::from_bytes(&[2, 1, 0, 1, 0, 1, 1, 0]).is_err());
+    // a huge size with no cells behind it
+    assert!(Map::from_bytes(&[1, 255, 255, 255, 255, 255, 1]).is_err());
+}
+
+#[test]
+fn map_grows_to_the_screen() {
+    // the viewport is below the top bar and above the status row
+    let map = Map::for_screen((24, 22));
+    assert_eq!((map.width(), map.height()), (24, 20));
+
+    let mut map = Map::new(3, 2, 2);
+    map.set(1, 2, 1, Some(Cell::default()));
+    map.grow(5, 4);
+    assert_eq!((map.width(), map.height()), (5, 4));
+    assert_eq!(map.get(1, 2, 1), Some(Cell::default()));
+    assert_eq!(map.get(1, 4, 3), None);
+
+    // a smaller screen keeps the cells
+    map.grow(2, 2);
+    assert_eq!((map.width(), map.height()), (5, 4));
+}
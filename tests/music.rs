@@ -0,0 +1,114 @@
+use ggez::GameResult;
+
+use rr8::music::{Pattern, Song, TrackCache};
+use rr8::sfx::{Effect, Sfx, Step, Wave, SFX_STEPS};
+use rr8::synth::{tick_samples, SAMPLE_RATE};
+
+fn song() -> (Song, Vec<Sfx>) {
+    let mut sfx = vec![Sfx::default(); 2];
+    sfx[0].steps[0] = Step {
+        pitch: 30,
+        wave: Wave::Triangle,
+        volume: 7,
+        effect: Effect::None,
+    };
+    sfx[1].steps[4] = Step {
+        pitch: 12,
+        wave: Wave::Noise,
+        volume: 4,
+        effect: Effect::FadeOut,
+    };
+
+    let mut song = Song {
+        tempo: 2,
+        order: vec![0, 1, 1, 0],
+        loop_start: 1,
+        loop_end: 2,
+        ..Song::default()
+    };
+    song.patterns[0] = Pattern {
+        channels: [Some(0), None, Some(1), None],
+    };
+    song.patterns[1] = Pattern {
+        channels: [None, Some(1), None, None],
+    };
+
+    (song, sfx)
+}
+
+#[test]
+fn loop_positions() {
+    let (song, _) = song();
+    assert_eq!(song.positions(0), (vec![0], vec![1, 2]));
+    assert_eq!(song.positions(2), (vec![2], vec![1, 2]));
+    // past the loop the song plays to the end once
+    assert_eq!(song.positions(3), (vec![3], vec![]));
+}
+
+#[test]
+fn render_mixes_channels() {
+    let (song, sfx) = song();
+    let track = song.render(&sfx, 0, SAMPLE_RATE);
+    let pattern_len = SFX_STEPS * tick_samples(song.tempo, SAMPLE_RATE);
+
+    assert_eq!(track.intro.len(), pattern_len);
+    assert_eq!(track.looped.len(), 2 * pattern_len);
+    assert!(track.intro.iter().any(|s| s.abs() > 0.1));
+    assert!(track.looped.iter().any(|s| s.abs() > 0.01));
+    assert!(track
+        .intro
+        .iter()
+        .chain(&track.looped)
+        .all(|s| s.abs() <= 1.));
+
+    let wav = song.to_wav(&sfx, SAMPLE_RATE);
+    assert_eq!(wav.len(), 44 + 2 * 3 * pattern_len);
+}
+
+#[test]
+fn song_bytes_roundtrip() -> GameResult {
+    let (song, _) = song();
+    assert_eq!(Song::from_bytes(&song.to_bytes())?, song);
+    assert!(Song::from_bytes(&song.to_bytes()[..6]).is_err());
+
+    // the order plays a pattern that isn't there
+    let mut bytes = song.to_bytes();
+    bytes[5] = 200;
+    assert!(Song::from_bytes(&bytes).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn tracks_are_rendered_again_on_changes() {
+    let (mut song, sfx) = song();
+    let mut tracks = TrackCache::default();
+
+    assert_eq!(
+        *tracks.render(&song, &sfx, 0, SAMPLE_RATE),
+        song.render(&sfx, 0, SAMPLE_RATE)
+    );
+    assert_eq!(
+        *tracks.render(&song, &sfx, 3, SAMPLE_RATE),
+        song.render(&sfx, 3, SAMPLE_RATE)
+    );
+
+    song.tempo = 4;
+    assert_eq!(
+        *tracks.render(&song, &sfx, 3, SAMPLE_RATE),
+        song.render(&sfx, 3, SAMPLE_RATE)
+    );
+}
+
+#[test]
+fn position_after_ticks() {
+    let (song, _) = song();
+    // 32 steps of 2 ticks at 120Hz last 32 game ticks
+    assert_eq!(song.position_after(0, 31), Some(0));
+    assert_eq!(song.position_after(0, 32), Some(1));
+    assert_eq!(song.position_after(0, 64), Some(2));
+    assert_eq!(song.position_after(0, 96), Some(1));
+    // playing past the loop end doesn't loop
+    assert_eq!(song.position_after(3, 0), Some(3));
+    assert_eq!(song.position_after(3, 32), None);
+}
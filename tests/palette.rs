@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use ggez::graphics::Rect;
+use ggez::nalgebra::Point2;
+use ggez::GameResult;
+
+use rr8::render::soft::Framebuffer;
+use rr8::render::{Renderer, Sheet, TRANSPARENT};
+use rr8::ui::palette::{Pal, Palette, Remap};
+use rr8::ui::Ui;
+
+fn ui() -> GameResult<Ui> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    Ui::headless(resources, Rect::new(0., 0., 320., 320.))
+}
+
+#[test]
+fn import_files() -> GameResult {
+    let gpl = "GIMP Palette\nName: test\nColumns: 2\n#\n  0   0   0\tBlack\n255 128   1 Orange\n";
+    let palette = Palette::parse_gpl(gpl)?;
+    // short palettes start at Black, Off keeps its color
+    assert_eq!(palette.rgb(Pal::Off), Palette::default().rgb(Pal::Off));
+    assert_eq!(palette.rgb(Pal::Black), (0, 0, 0));
+    assert_eq!(palette.rgb(Pal::DarkBlue), (255, 128, 1));
+    assert_eq!(palette.rgb(Pal::Peach), Palette::default().rgb(Pal::Peach));
+
+    let hex = (0..17)
+        .map(|i| format!("#0000{:02x}\n", i))
+        .collect::<String>();
+    let palette = Palette::parse_hex(&hex)?;
+    assert_eq!(palette.rgb(Pal::Off), (0, 0, 0));
+    assert_eq!(palette.rgb(Pal::Peach), (0, 0, 16));
+
+    assert!(Palette::parse_gpl("0 0 0\n").is_err());
+    assert!(Palette::parse_gpl("GIMP Palette\n0 0 300\n").is_err());
+    assert!(Palette::parse_hex("").is_err());
+    assert!(Palette::parse_hex("12345\n").is_err());
+    assert!(Palette::parse_hex(&hex.repeat(2)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn overrides_roundtrip() {
+    let mut palette = Palette::default();
+    assert!(palette.overrides().is_empty());
+
+    palette.set(Pal::Red, (1, 2, 3));
+    assert_eq!(palette.overrides(), vec![(Pal::Red, (1, 2, 3))]);
+    assert_eq!(Palette::from_overrides(&palette.overrides()), palette);
+    assert_eq!(palette.nearest([1, 2, 4, 255]), Pal::Red);
+}
+
+#[test]
+fn remap_applies_to_draws() -> GameResult {
+    let mut ui = ui()?;
+    let mut palette = Palette::default();
+    palette.set(Pal::Red, (10, 20, 30));
+    ui.set_palette(palette);
+
+    let mut fb = Framebuffer::new(32, 16);
+    ui.draw_rect(&mut fb, 0., 0., 1., 1., Pal::Red)?;
+    assert_eq!(fb.pixel(0, 0), [10, 20, 30, 255]);
+
+    let mut remap = Remap::default();
+    remap.set(Pal::Blue, Pal::Red);
+    remap.set_transparent(Pal::Green, true);
+    ui.set_remap(remap);
+    ui.draw_rect(&mut fb, 1., 0., 1., 1., Pal::Blue)?;
+    ui.draw_rect(&mut fb, 1., 0., 1., 1., Pal::Green)?;
+    assert_eq!(fb.pixel(16, 0), [10, 20, 30, 255]);
+
+    ui.set_remap(Remap::default());
+    ui.draw_rect(&mut fb, 1., 0., 1., 1., Pal::Green)?;
+    assert_ne!(fb.pixel(16, 0), [10, 20, 30, 255]);
+
+    Ok(())
+}
+
+#[test]
+fn sheets_are_indexed() -> GameResult {
+    // white, a color close to red and a half transparent pixel
+    let rgba = vec![255, 255, 255, 255, 250, 5, 80, 255, 0, 0, 0, 100];
+    let sheet = Sheet::from_rgba(3, 1, rgba)?;
+    assert_eq!(
+        sheet.pixels(),
+        &[Pal::White as u8, Pal::Red as u8, TRANSPARENT]
+    );
+    assert_eq!(Sheet::from_png(&sheet.to_png()?)?.pixels(), sheet.pixels());
+    assert!(Sheet::from_indices(2, 1, vec![0, 17]).is_err());
+
+    let mut ui = ui()?;
+    let mut palette = Palette::default();
+    palette.set(Pal::Blue, (1, 2, 3));
+    palette.set(Pal::Green, (4, 5, 6));
+    ui.set_palette(palette);
+    let mut remap = Remap::default();
+    remap.set(Pal::Red, Pal::Green);
+    ui.set_remap(remap);
+
+    // white takes the ink, other colors go through the remap and get tinted
+    let src = Rect::new(0., 0., 3., 1.);
+    let draw = |ink: Pal| -> GameResult<Framebuffer> {
+        let mut fb = Framebuffer::new(3, 1);
+        let tint = ui.ink(ink).unwrap();
+        fb.sprite(
+            &sheet,
+            src,
+            Point2::new(0., 0.),
+            &ui.sheet_colors(),
+            tint,
+            false,
+        )?;
+        Ok(fb)
+    };
+    let fb = draw(Pal::Blue)?;
+    assert_eq!(fb.pixel(0, 0), [1, 2, 3, 255]);
+    assert_eq!(fb.pixel(1, 0), [0, 0, 0, 255]);
+    assert_eq!(fb.pixel(2, 0), [0; 4]);
+    let fb = draw(Pal::White)?;
+    let (r, g, b, a) = ui.ink(Pal::White).unwrap().to_rgba();
+    assert_eq!(fb.pixel(0, 0), [r, g, b, a]);
+    assert_eq!(fb.pixel(1, 0)[3], 255);
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use ggez::event::MouseButton;
+use ggez::graphics::Rect;
+use ggez::GameResult;
+
+use rr8::code::CodeEdit;
+use rr8::render::soft::Framebuffer;
+use rr8::replay::{Input, Recording};
+use rr8::rng::Rng;
+use rr8::ui::Ui;
+use rr8::{Btn, Game};
+
+/// Changes its state while drawing, which must not show in updates
+const DRAWING_CODE: &str = r#"
+let frames = 0;
+fn init() { this.x = 0; }
+fn update() { this.x += 1 + rnd(10) + frames; }
+fn draw() {
+    this.x += 100;
+    frames += 1;
+    rnd(10);
+    text("x " + this.x, 1, 1, 9);
+}
+"#;
+
+fn frame(game: &Game) -> GameResult<Framebuffer> {
+    let mut fb = Framebuffer::new(320, 320);
+    game.ui.draw_all(&mut fb, game)?;
+
+    Ok(fb)
+}
+
+fn game() -> GameResult<Game> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let ui = Ui::headless(resources, Rect::new(0., 0., 320., 320.))?;
+    Game::new(ui)
+}
+
+/// Buttons held by the first player on each tick
+fn run(game: &mut Game, ticks: u32) -> GameResult<Vec<Vec<Btn>>> {
+    let mut held = Vec::new();
+    for _ in 0..ticks {
+        game.tick()?;
+        held.push(game.get_buttons().held(0));
+    }
+
+    Ok(held)
+}
+
+#[test]
+fn text_roundtrip() -> GameResult {
+    let mut recording = Recording::new(42);
+    recording.ticks = 9;
+    recording.push(1, Input::Press(0, Btn::A));
+    recording.push(3, Input::Release(3, Btn::Start));
+    recording.push(5, Input::MouseMove(120.5, -3.));
+    recording.push(5, Input::MouseButton(MouseButton::Right, true));
+    assert_eq!(Recording::parse(&recording.serialize())?, recording);
+
+    let parsed = Recording::parse("rr8 recording\n# comment\n\nseed 7\n2 press 2 up\n")?;
+    assert_eq!(parsed.seed, 7);
+    assert_eq!(parsed.inputs, vec![(2, Input::Press(1, Btn::Up))]);
+
+    assert!(Recording::parse("seed 7").is_err());
+    assert!(Recording::parse("rr8 recording\nseed x").is_err());
+    assert!(Recording::parse("rr8 recording\n1 press 9 a").is_err());
+    assert!(Recording::parse("rr8 recording\n1 mouse left sideways").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rng_is_seeded() {
+    let numbers = |seed| {
+        let mut rng = Rng::new(seed);
+        (0..8).map(|_| rng.below(100)).collect::<Vec<_>>()
+    };
+    assert_eq!(numbers(1), numbers(1));
+    assert_ne!(numbers(1), numbers(2));
+
+    let mut rng = Rng::new(3);
+    assert_eq!(rng.below(0), 0);
+    assert!((0..100)
+        .map(|_| rng.float())
+        .all(|f| (0. ..1.).contains(&f)));
+}
+
+#[test]
+fn replay_reproduces_session() -> GameResult {
+    let mut game = game()?;
+    game.start_recording()?;
+    game.input(Input::Press(0, Btn::A));
+    let mut recorded = run(&mut game, 3)?;
+    game.input(Input::Press(0, Btn::Left));
+    game.input(Input::Release(0, Btn::A));
+    recorded.extend(run(&mut game, 4)?);
+    let recording = game.stop_recording().unwrap();
+    assert_eq!(recording.ticks, 7);
+    assert_eq!(recording.inputs.len(), 3);
+
+    game.start_replay(recording.clone())?;
+    assert_eq!(game.get_seed(), recording.seed);
+    assert_eq!(game.get_dt(), 0);
+    // live input doesn't get in the way
+    game.input(Input::Press(0, Btn::B));
+    assert_eq!(run(&mut game, 7)?, recorded);
+
+    // the replay ends with the recording and buttons are let go
+    assert!(game.get_replay().is_none());
+    game.tick()?;
+    assert!(game.get_buttons().held(0).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn replay_ignores_draw_changes() -> GameResult {
+    let mut game = game()?;
+    *game.get_code_edit_mut() = CodeEdit::new(DRAWING_CODE);
+    game.start_recording()?;
+    // a different number of frames is drawn between each tick
+    for frames in [0, 3, 1, 2].iter() {
+        game.tick()?;
+        for _ in 0..*frames {
+            frame(&game)?;
+        }
+    }
+    let recording = game.stop_recording().unwrap();
+    let state = frame(&game)?;
+
+    game.start_replay(recording)?;
+    run(&mut game, 4)?;
+    assert_eq!(game.get_script().unwrap().error(), None);
+    // the frame shows the x of the last update
+    assert!(frame(&game)? == state);
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use ggez::graphics::Rect;
+use ggez::GameResult;
+
+use rr8::cart::Cart;
+use rr8::render::Letterbox;
+use rr8::ui::Ui;
+use rr8::Game;
+
+fn ui() -> GameResult<Ui> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    Ui::headless(resources, Rect::new(0., 0., 320., 320.))
+}
+
+#[test]
+fn letterbox_scales_by_whole_numbers() {
+    let screen = (320., 320.);
+
+    let fit = Letterbox::fit(screen, (640., 640.));
+    assert_eq!(
+        fit,
+        Letterbox {
+            scale: 2.,
+            x: 0.,
+            y: 0.
+        }
+    );
+
+    // x1.5 would fit but pixels would be uneven, bars fill the rest
+    let fit = Letterbox::fit(screen, (1000., 480.));
+    assert_eq!(
+        fit,
+        Letterbox {
+            scale: 1.,
+            x: 340.,
+            y: 80.
+        }
+    );
+
+    let fit = Letterbox::fit(screen, (1920., 1080.));
+    assert_eq!(
+        fit,
+        Letterbox {
+            scale: 3.,
+            x: 480.,
+            y: 60.
+        }
+    );
+
+    // too small a window crops the screen around its center
+    let fit = Letterbox::fit(screen, (300., 320.));
+    assert_eq!(
+        fit,
+        Letterbox {
+            scale: 1.,
+            x: -10.,
+            y: 0.
+        }
+    );
+}
+
+#[test]
+fn window_to_screen() {
+    let fit = Letterbox::fit((320., 320.), (1920., 1080.));
+    assert_eq!(fit.to_screen(480., 60.), (0., 0.));
+    assert_eq!(fit.to_screen(480. + 3. * 16., 60. + 3. * 32.), (16., 32.));
+    assert_eq!(fit.to_screen(0., 0.), (-160., -20.));
+}
+
+#[test]
+fn cart_screen_roundtrip() -> GameResult {
+    let cart = Cart {
+        screen: Some((24, 22)),
+        ..Cart::default()
+    };
+    let text = cart.serialize();
+    assert!(text.contains("__screen__\n24 22\n"));
+    assert_eq!(Cart::parse(&text)?, cart);
+
+    // carts without the section keep the default size
+    assert_eq!(Cart::parse(&Cart::default().serialize())?.screen, None);
+
+    let bad = "rr8 cartridge\nversion 1\n__screen__\n24\n";
+    assert!(Cart::parse(bad).is_err());
+    let bad = "rr8 cartridge\nversion 1\n__screen__\n24 x\n";
+    assert!(Cart::parse(bad).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn screen_size_is_validated() -> GameResult {
+    let mut ui = ui()?;
+    assert_eq!(ui.screen_size(), (20, 20));
+
+    // smaller than the editors or bigger than the max
+    assert!(ui.set_screen(19, 20).is_err());
+    assert!(ui.set_screen(20, 65).is_err());
+    assert_eq!(ui.screen_size(), (20, 20));
+    assert!(Ui::validate_rect(Rect::new(0., 0., 330., 320.)).is_err());
+
+    ui.set_screen(24, 22)?;
+    assert_eq!(ui.screen_size(), (24, 22));
+    assert_eq!(ui.get_win(), Rect::new(0., 0., 384., 352.));
+
+    Ok(())
+}
+
+#[test]
+fn layout_follows_screen() -> GameResult {
+    let mut game = Game::new(ui()?)?;
+
+    // the viewport is the body, between the top bar and the status row
+    game.ui.set_mouse_coords((23. * 16. + 1., 20. * 16. + 1.));
+    assert_eq!(game.ui.get_mouse().viewport_tile(), None);
+
+    game.set_screen(24, 22)?;
+    assert_eq!(game.ui.get_mouse().get_viewport(), (24, 20));
+    assert_eq!(game.ui.get_mouse().viewport_tile(), Some((23, 19)));
+
+    let fb = game.capture()?;
+    assert_eq!((fb.width(), fb.height()), (384, 352));
+
+    // the text boxes, status row and map grow with it
+    let console = game.get_console();
+    assert_eq!((console.rows(), console.columns()), (16, 44));
+    let code = game.get_code_edit();
+    assert_eq!((code.rows(), code.columns()), (16, 44));
+    assert_eq!(game.ui.status_row(), 21.);
+    assert_eq!((game.map.width(), game.map.height()), (24, 20));
+
+    // a size that doesn't fit is refused
+    assert!(game.set_screen(8, 8).is_err());
+    assert_eq!(game.ui.screen_size(), (24, 22));
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use ggez::graphics::Rect;
+use ggez::GameResult;
+
+use rr8::render::soft::Framebuffer;
+use rr8::script::ScriptSystem;
+use rr8::ui::Ui;
+use rr8::Game;
+
+fn game(source: &str) -> GameResult<Game> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let ui = Ui::headless(resources, Rect::new(0., 0., 320., 320.))?;
+    let mut game = Game::new(ui)?;
+    game.set_script(ScriptSystem::new(source)?);
+
+    Ok(game)
+}
+
+fn logged(game: &Game, text: &str) -> bool {
+    game.get_console()
+        .visible()
+        .iter()
+        .any(|(line, _)| line.contains(text))
+}
+
+#[test]
+fn missing_hooks_are_skipped() -> GameResult {
+    let script = ScriptSystem::new("let x = 1;")?;
+    assert_eq!(script.error(), None);
+
+    Ok(())
+}
+
+#[test]
+fn missing_functions_in_hooks_are_errors() -> GameResult {
+    let script = ScriptSystem::new("fn init() { setup_player(); }")?;
+    assert!(script.error().unwrap().contains("setup_player"));
+
+    Ok(())
+}
+
+#[test]
+fn syntax_errors_fail_to_load() {
+    assert!(ScriptSystem::new("fn init( {").is_err());
+}
+
+#[test]
+fn tiles_out_of_range_are_errors() -> GameResult {
+    for call in ["tile(-1, 0, 1, 1, 9)", "tile_alt(0, 70000, 1, 1, 9)"].iter() {
+        let script = ScriptSystem::new(&format!("fn init() {{ {}; }}", call))?;
+        assert!(script.error().unwrap().contains("out of range"));
+    }
+
+    // far tiles are in range but outside of the sheet
+    let script = ScriptSystem::new("fn init() { tile(5000, 5000, 1, 1, 9); }")?;
+    assert_eq!(script.error(), None);
+
+    Ok(())
+}
+
+#[test]
+fn errors_are_logged_to_the_console() -> GameResult {
+    let loaded = game("fn init() { setup(); }")?;
+    assert!(logged(&loaded, "script error in init()"));
+
+    let mut game = game("fn update() { update_player(); }")?;
+    game.tick()?;
+    assert!(logged(&game, "script error in update()"));
+
+    Ok(())
+}
+
+#[test]
+fn draw_errors_are_logged_on_the_next_tick() -> GameResult {
+    let mut game = game("fn draw() { tile(-1, 0, 1, 1, 9); }")?;
+    let mut fb = Framebuffer::new(320, 320);
+    game.ui.draw_all(&mut fb, &game)?;
+    assert!(!logged(&game, "script error in draw()"));
+
+    game.tick()?;
+    assert!(logged(&game, "script error in draw()"));
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use ggez::GameResult;
+
+use rr8::render::{soft::Framebuffer, Renderer};
+use rr8::snapshot::{self, Snapshot, BLESS_ENV};
+use rr8::ui::{palette::Pal, prompt::Prompt, topbar::TopBar};
+use rr8::{Game, GameMode, System};
+
+/// Draws a fixed text in the console box
+#[derive(Debug)]
+struct Text(&'static str);
+
+impl System for Text {
+    fn update(&mut self, _game: &mut Game) -> GameResult {
+        Ok(())
+    }
+
+    fn draw(&self, r: &mut dyn Renderer, game: &Game) -> GameResult {
+        game.ui.draw_text(r, self.0, 1., 4., Pal::White)
+    }
+}
+
+#[test]
+fn topbar_normal() -> GameResult {
+    Snapshot::new(GameMode::Normal)?
+        .system(TopBar)
+        .assert_matches("topbar_normal")
+}
+
+#[test]
+fn topbar_prompt() -> GameResult {
+    Snapshot::new(GameMode::Prompt)?
+        .system(TopBar)
+        .assert_matches("topbar_prompt")
+}
+
+#[test]
+fn prompt_typed() -> GameResult {
+    let mut snapshot = Snapshot::new(GameMode::Prompt)?
+        .system(TopBar)
+        .system(Prompt);
+    for c in "help scale".chars() {
+        snapshot.game_mut().update_prompt(c);
+    }
+
+    snapshot.assert_matches("prompt_typed")
+}
+
+#[test]
+fn font_glyphs() -> GameResult {
+    Snapshot::new(GameMode::Prompt)?
+        .system(Text(
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ\n\
+             ÀÁÂÄÇÈÉÊËÒòÔÖÙùÛÜ\n\
+             abcdefghijklmnopqrstuvwxyz\n\
+             àáâäçè\n\
+             #%&@$.,!?:;'\"()[]*/\\+-<=>\n\
+             0123456789\n\
+             unknown: ~{}",
+        ))
+        .assert_matches("font_glyphs")
+}
+
+#[test]
+fn missing_snapshot_fails() -> GameResult {
+    if std::env::var_os(BLESS_ENV).is_some() {
+        return Ok(());
+    }
+
+    let dir = std::env::temp_dir().join(format!("rr8-snapshots-{}", std::process::id()));
+    let fb = Framebuffer::new(2, 2);
+    let result = snapshot::assert_snapshot(&dir, "missing", &fb);
+    let written = dir.join("missing.new.png").exists();
+    std::fs::remove_dir_all(&dir)?;
+
+    assert!(result.is_err());
+    assert!(written);
+
+    Ok(())
+}
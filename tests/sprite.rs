@@ -0,0 +1,47 @@
+use ggez::GameResult;
+
+use rr8::render::{Sheet, TRANSPARENT};
+use rr8::sprite::{line, SpriteEdit, Tool};
+use rr8::ui::palette::Pal;
+
+#[test]
+fn line_includes_both_ends() {
+    assert_eq!(line((0, 0), (3, 1)), vec![(0, 0), (1, 0), (2, 1), (3, 1)]);
+    assert_eq!(line((2, 2), (2, 2)), vec![(2, 2)]);
+    assert_eq!(line((0, 2), (0, 0)), vec![(0, 2), (0, 1), (0, 0)]);
+}
+
+#[test]
+fn fill_stays_inside_the_tile() -> GameResult {
+    // two 16x16 tiles side by side
+    let mut sheet = Sheet::from_indices(32, 16, vec![TRANSPARENT; 32 * 16])?;
+    let mut edit = SpriteEdit::default();
+    edit.tool = Tool::Fill;
+    edit.color = Pal::Red;
+
+    edit.apply(&mut sheet, 3, 4, false);
+    assert_eq!(sheet.pixel(15, 15), Pal::Red as u8);
+    assert_eq!(sheet.pixel(16, 0), TRANSPARENT);
+
+    edit.apply(&mut sheet, 0, 0, true);
+    assert_eq!(sheet.pixel(15, 15), TRANSPARENT);
+
+    Ok(())
+}
+
+#[test]
+fn tiny_sheets_are_not_overrun() -> GameResult {
+    let mut sheet = Sheet::from_rgba(4, 4, vec![0; 4 * 4 * 4])?;
+    let mut edit = SpriteEdit::default();
+    edit.color = Pal::Red;
+
+    for &tool in Tool::ALL.iter() {
+        edit.tool = tool;
+        edit.apply(&mut sheet, 10, 10, false);
+    }
+    edit.push_undo(&sheet);
+    assert_eq!(sheet.pixel(3, 3), TRANSPARENT);
+    assert_eq!(sheet.pixel(40, 3), TRANSPARENT);
+
+    Ok(())
+}
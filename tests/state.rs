@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use ggez::graphics::Rect;
+use ggez::GameResult;
+
+use rr8::code::CodeEdit;
+use rr8::render::Sheet;
+use rr8::state::SaveState;
+use rr8::ui::Ui;
+use rr8::Game;
+
+const CODE: &str = r#"
+let lives = 3;
+const SPEED = 2;
+fn init() {
+    this.x = 0;
+    this.name = "say \"hi\"\n";
+    this.items = [1, 2.5, true, 'c'];
+}
+fn update() {
+    this.x += 1 + rnd(10);
+}
+"#;
+
+fn game() -> GameResult<Game> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let ui = Ui::headless(resources, Rect::new(0., 0., 320., 320.))?;
+    let mut game = Game::new(ui)?;
+    *game.get_code_edit_mut() = CodeEdit::new(CODE);
+    game.run_cart_with_seed(5)?;
+
+    Ok(game)
+}
+
+#[test]
+fn text_roundtrip() -> GameResult {
+    let mut game = game()?;
+    for _ in 0..10 {
+        game.tick()?;
+    }
+
+    let state = game.save_state()?;
+    let script = state.script.as_ref().unwrap();
+    assert!(script
+        .globals
+        .contains(&("lives".to_string(), false, "3".to_string())));
+    assert!(script
+        .globals
+        .contains(&("SPEED".to_string(), true, "2".to_string())));
+    assert!(script.this.contains(r#""name": "say \"hi\"\n""#));
+    assert_eq!(SaveState::parse(&state.serialize())?, state);
+
+    assert!(SaveState::parse("rr8 state\nversion 1\n").is_err());
+    assert!(SaveState::parse("rr8 state\nversion 99\n__cart__\n").is_err());
+
+    // section lines in the code don't cut it
+    let mut marked = state.clone();
+    let code = "fn f() {}\n__cart__\n__code__ 3\n".to_string();
+    marked.script.as_mut().unwrap().source = code.clone();
+    marked.cart.code = code;
+    assert_eq!(SaveState::parse(&marked.serialize())?, marked);
+
+    // the script needs its length
+    let cart = "__cart__\nrr8 cartridge\nversion 1\n__code__ 0\n\n";
+    assert!(SaveState::parse(&format!("rr8 state\nversion 1\n__script__\n{}", cart)).is_err());
+    assert!(SaveState::parse(&format!("rr8 state\nversion 1\n__script__ 99\n{}", cart)).is_err());
+    assert!(SaveState::parse(&format!("rr8 state\nversion 1\n__script__ 0\n\n{}", cart)).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn special_floats_roundtrip() -> GameResult {
+    let mut game = game()?;
+    *game.get_code_edit_mut() =
+        CodeEdit::new("fn init() { this.values = [1.0 / 0.0, -1.0 / 0.0, 0.0 / 0.0]; }");
+    game.run_cart_with_seed(5)?;
+
+    let state = game.save_state()?;
+    assert!(state.script.as_ref().unwrap().this.contains("(0.0 / 0.0)"));
+
+    let mut other = self::game()?;
+    other.restore_state(SaveState::parse(&state.serialize())?)?;
+    assert_eq!(other.save_state()?, state);
+
+    Ok(())
+}
+
+#[test]
+fn bad_state_changes_nothing() -> GameResult {
+    let mut game = game()?;
+    for _ in 0..5 {
+        game.tick()?;
+    }
+    let before = game.save_state()?;
+
+    // the tilesets and the screen size are fine, the map isn't
+    let mut bad = before.clone();
+    bad.dt = 99;
+    bad.cart.tiles_alt = bad.cart.tiles.clone();
+    bad.cart.screen = Some((24, 24));
+    bad.cart.map = vec![99];
+    assert!(game.restore_state(bad).is_err());
+    assert_eq!(game.ui.screen_size(), (20, 20));
+    assert_eq!(game.save_state()?, before);
+
+    // a tileset too small to edit
+    let mut bad = before.clone();
+    bad.cart.tiles = Some(Sheet::from_rgba(8, 8, vec![0; 8 * 8 * 4])?.to_png()?);
+    assert!(game.restore_state(bad).is_err());
+    assert_eq!(game.save_state()?, before);
+
+    Ok(())
+}
+
+#[test]
+fn restore_resumes() -> GameResult {
+    let mut game = game()?;
+    for _ in 0..10 {
+        game.tick()?;
+    }
+    let state = game.save_state()?;
+
+    for _ in 0..5 {
+        game.tick()?;
+    }
+    let later = game.save_state()?;
+
+    // restoring in a fresh game goes on exactly like the original one
+    let mut other = self::game()?;
+    other.restore_state(SaveState::parse(&state.serialize())?)?;
+    assert_eq!(other.get_dt(), 10);
+    for _ in 0..5 {
+        other.tick()?;
+    }
+    assert_eq!(other.save_state()?, later);
+
+    game.quick_save()?;
+    game.tick()?;
+    game.quick_load()?;
+    assert_eq!(game.save_state()?, later);
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use ggez::GameResult;
+
+use rr8::sfx::{self, Effect, Sfx, Step, Wave};
+use rr8::synth::{frequency, render, step_samples, to_wav, SAMPLE_RATE};
+
+fn note(pitch: u8, wave: Wave, effect: Effect) -> Step {
+    Step {
+        pitch,
+        wave,
+        volume: 5,
+        effect,
+    }
+}
+
+#[test]
+fn pitch_frequencies() {
+    assert_eq!(frequency(33), 440.);
+    assert!((frequency(45) - 880.).abs() < 0.01);
+    assert_eq!(sfx::note_name(0), "C-2");
+    assert_eq!(sfx::note_name(34), "A#4");
+}
+
+#[test]
+fn render_stops_after_last_note() {
+    let mut sfx = Sfx::default();
+    assert!(render(&sfx, SAMPLE_RATE).is_empty());
+
+    sfx.steps[0] = note(24, Wave::Square, Effect::None);
+    sfx.steps[2] = note(30, Wave::Noise, Effect::FadeOut);
+    let samples = render(&sfx, SAMPLE_RATE);
+    assert_eq!(samples.len(), 3 * step_samples(&sfx, SAMPLE_RATE));
+
+    // the rest in the middle fades to silence
+    let len = step_samples(&sfx, SAMPLE_RATE);
+    let rest = &samples[2 * len - 10..2 * len];
+    assert!(rest.iter().all(|s| s.abs() < 0.01));
+    assert!(samples[..len].iter().any(|s| s.abs() > 0.1));
+    assert!(samples.iter().all(|s| s.abs() <= 1.));
+}
+
+#[test]
+fn every_wave_and_effect_makes_sound() {
+    for wave in Wave::ALL.iter() {
+        for effect in Effect::ALL.iter() {
+            let mut sfx = Sfx::default();
+            sfx.steps[0] = note(20, *wave, Effect::None);
+            sfx.steps[1] = note(32, *wave, *effect);
+
+            let samples = render(&sfx, SAMPLE_RATE);
+            let len = step_samples(&sfx, SAMPLE_RATE);
+            let peak = samples[len..].iter().fold(0f32, |m, s| m.max(s.abs()));
+            assert!(peak > 0.05, "{:?} {:?} is silent", wave, effect);
+        }
+    }
+}
+
+#[test]
+fn sfx_bytes_roundtrip() -> GameResult {
+    let mut bank = vec![Sfx::default(); 3];
+    bank[1].speed = 4;
+    bank[1].steps[7] = note(63, Wave::Saw, Effect::Vibrato);
+    bank[2].steps[31] = note(0, Wave::Triangle, Effect::Slide);
+
+    let loaded = sfx::from_bytes(&sfx::to_bytes(&bank))?;
+    assert_eq!(&loaded[..3], &bank[..]);
+    assert_eq!(loaded.len(), sfx::SFX_COUNT);
+    assert!(sfx::from_bytes(&sfx::to_bytes(&bank)[..10]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn wav_header() {
+    let wav = to_wav(&[0., 1., -1.], SAMPLE_RATE);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(wav.len(), 44 + 6);
+    assert_eq!(&wav[46..48], &i16::MAX.to_le_bytes());
+}
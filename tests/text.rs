@@ -0,0 +1,71 @@
+use ggez::graphics::Rect;
+
+use rr8::ui::text::{self, Align, TextLayout};
+
+#[test]
+fn measure() {
+    assert_eq!(text::measure(""), (0, 0));
+    assert_eq!(text::measure("hello"), (5, 1));
+    assert_eq!(text::measure("hi\nthere\n"), (5, 3));
+    assert_eq!(text::measure("àé"), (2, 1));
+}
+
+#[test]
+fn wrap_between_words() {
+    assert_eq!(
+        text::wrap("the quick brown fox", 10),
+        vec!["the quick", "brown fox"]
+    );
+    // inner and leading spaces are kept, the ones at a break are not
+    assert_eq!(text::wrap("  a  b", 10), vec!["  a  b"]);
+    assert_eq!(text::wrap("aaaa    bb", 6), vec!["aaaa", "bb"]);
+    // long words are cut
+    assert_eq!(text::wrap("abcdefghij k", 4), vec!["abcd", "efgh", "ij k"]);
+    assert_eq!(text::wrap("a\n\nb", 4), vec!["a", "", "b"]);
+    assert_eq!(text::wrap("", 4), vec![""]);
+    assert!(text::wrap("a", 0).is_empty());
+}
+
+#[test]
+fn ellipsize() {
+    assert_eq!(text::ellipsize("short", 5), "short");
+    assert_eq!(text::ellipsize("too long line", 7), "too...");
+    assert_eq!(text::ellipsize("abcdef", 2), "..");
+    assert_eq!(text::ellipsize("abcdef", 0), "");
+}
+
+#[test]
+fn layout_aligns_and_clips() {
+    // 4 tiles are 8 cells wide, 2 tiles are 2 rows high
+    let rect = Rect::new(1., 2., 4., 2.);
+    let layout = TextLayout::new(rect);
+    assert_eq!(layout.size(), (8, 2));
+
+    assert_eq!(
+        layout.lines("hello you"),
+        vec![("hello".to_string(), 1., 2.), ("you".to_string(), 1., 3.)]
+    );
+
+    let right = layout.align(Align::Right);
+    assert_eq!(right.lines("abcd"), vec![("abcd".to_string(), 3., 2.)]);
+    let center = layout.align(Align::Center);
+    assert_eq!(center.lines("abcd"), vec![("abcd".to_string(), 2., 2.)]);
+
+    // lines past the bottom are dropped, the last one shows it
+    assert_eq!(
+        layout.lines("one two three four"),
+        vec![
+            ("one two".to_string(), 1., 2.),
+            ("three...".to_string(), 1., 3.)
+        ]
+    );
+
+    // without wrapping long lines are cut at the right edge
+    let line = TextLayout::new(Rect::new(0., 0., 2., 1.)).wrap(false);
+    assert_eq!(line.lines("abcdefgh"), vec![("a...".to_string(), 0., 0.)]);
+    let clipped = line.ellipsis(false);
+    assert_eq!(
+        clipped.lines("abcdefgh\nij"),
+        vec![("abcd".to_string(), 0., 0.)]
+    );
+}
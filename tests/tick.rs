@@ -0,0 +1,118 @@
+use std::cell::Cell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use ggez::graphics::Rect;
+use ggez::GameResult;
+
+use rr8::render::Renderer;
+use rr8::tick::Ticker;
+use rr8::ui::Ui;
+use rr8::{Btn, Game, System};
+
+#[derive(Debug)]
+struct Counter(Rc<Cell<u32>>);
+
+impl System for Counter {
+    fn update(&mut self, game: &mut Game) -> GameResult {
+        self.0.set(self.0.get() + 1);
+        assert_eq!(game.get_dt(), self.0.get());
+        Ok(())
+    }
+
+    fn draw(&self, _r: &mut dyn Renderer, _game: &Game) -> GameResult {
+        Ok(())
+    }
+}
+
+fn game() -> GameResult<Game> {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let ui = Ui::headless(resources, Rect::new(0., 0., 320., 320.))?;
+    Game::new(ui)
+}
+
+#[test]
+fn ticker_fixed_steps() {
+    let mut ticker = Ticker::new(60);
+    let step = ticker.get_step();
+
+    assert_eq!(ticker.advance(step / 2), 0);
+    assert_eq!(ticker.advance(step / 2), 1);
+    assert_eq!(ticker.advance(step * 3), 3);
+}
+
+#[test]
+fn ticker_frame_skip() {
+    let mut ticker = Ticker::new(60);
+    ticker.set_max_steps(2);
+
+    assert_eq!(ticker.advance(Duration::from_secs(1)), 2);
+    // the time left over is dropped
+    assert_eq!(ticker.advance(Duration::from_secs(0)), 0);
+}
+
+#[test]
+fn ticker_slow_motion() {
+    let mut ticker = Ticker::new(60);
+    ticker.set_speed(0.5);
+    let step = ticker.get_step();
+
+    assert_eq!(ticker.advance(step), 0);
+    assert_eq!(ticker.advance(step), 1);
+
+    ticker.set_speed(100.);
+    assert_eq!(ticker.get_speed(), Ticker::MAX_SPEED);
+    ticker.set_speed(f32::NAN);
+    assert_eq!(ticker.get_speed(), Ticker::MIN_SPEED);
+}
+
+#[test]
+fn ticker_pause_and_step() {
+    let mut ticker = Ticker::new(60);
+    ticker.set_paused(true);
+
+    assert_eq!(ticker.advance(Duration::from_secs(1)), 0);
+    ticker.step();
+    ticker.step();
+    assert_eq!(ticker.advance(Duration::from_secs(0)), 2);
+
+    for _ in 0..Ticker::MAX_QUEUED + 5 {
+        ticker.step();
+    }
+    assert_eq!(ticker.advance(Duration::from_secs(0)), Ticker::MAX_QUEUED);
+}
+
+#[test]
+fn tick_updates_systems() -> GameResult {
+    let count = Rc::new(Cell::new(0));
+    let mut game = game()?;
+    game.ui.add_system(Counter(count.clone()));
+    game.ui.add_system(Counter(Rc::new(Cell::new(0))));
+
+    for _ in 0..3 {
+        game.tick()?;
+    }
+
+    assert_eq!(count.get(), 3);
+    assert_eq!(game.ui.dt, 3);
+
+    Ok(())
+}
+
+#[test]
+fn tick_samples_buttons() -> GameResult {
+    let mut game = game()?;
+    game.get_buttons_mut().press(0, Btn::A);
+    assert!(!game.btn(0, Btn::A));
+
+    game.tick()?;
+    assert!(game.btnp(0, Btn::A));
+    game.get_buttons_mut().release(0, Btn::A);
+    assert!(game.btn(0, Btn::A));
+
+    game.tick()?;
+    assert!(game.released(0, Btn::A));
+
+    Ok(())
+}